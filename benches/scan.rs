@@ -0,0 +1,70 @@
+//! Benchmark comparing the byte-by-byte parser (reading stdin) with the memory-mapped
+//! fast path (reading a file argument).
+//!
+//! Run with `cargo bench`. The size of the generated log can be set in megabytes with
+//! the `NFIX_BENCH_MB` environment variable.
+
+use std::{fs::File, path::{Path, PathBuf}, process::{Command, Stdio}, time::{Duration, Instant}};
+
+const NFIX:       &str  = env!("CARGO_BIN_EXE_nfix");
+const RUNS:       usize = 5;
+const DEFAULT_MB: usize = 100;
+
+fn main() {
+	let megabytes = std::env::var("NFIX_BENCH_MB").ok().and_then(|mb| mb.parse().ok()).unwrap_or(DEFAULT_MB);
+	let log       = generate_log(megabytes);
+
+	let stdin = bench("parser (stdin)", megabytes, &log, |output| {
+		Command::new(NFIX)
+			.args(["-s", ";"])
+			.stdin(File::open(&log).unwrap())
+			.stdout(output)
+			.status()
+			.unwrap()
+	});
+
+	let mmap = bench("scanner (mmap)", megabytes, &log, |output| {
+		Command::new(NFIX)
+			.args(["-s", ";"])
+			.arg(&log)
+			.stdin(Stdio::null())
+			.stdout(output)
+			.status()
+			.unwrap()
+	});
+
+	assert_eq!(std::fs::read(stdin).unwrap(), std::fs::read(mmap).unwrap(), "Parser and scanner output differ");
+
+	std::fs::remove_file(&log).unwrap();
+}
+
+/// Runs the command `RUNS` times and prints the best throughput.
+/// Returns the path of the output.
+fn bench(name: &str, megabytes: usize, log: &Path, run: impl Fn(File) -> std::process::ExitStatus) -> PathBuf {
+	let output_path = log.with_extension(name.split(' ').next().unwrap());
+	let mut best    = Duration::MAX;
+
+	for _ in 0..RUNS {
+		let output = File::create(&output_path).unwrap();
+		let start  = Instant::now();
+		assert!(run(output).success());
+		best = best.min(start.elapsed());
+	}
+
+	println!("{:<16} {:>8.1} ms {:>8.1} MB/s", name, best.as_secs_f64() * 1000.0, megabytes as f64 / best.as_secs_f64());
+	output_path
+}
+
+/// Generates a log of the given size by repeating the test data.
+fn generate_log(megabytes: usize) -> PathBuf {
+	let messages = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/test-data/messages.txt")).unwrap();
+	let path     = std::env::temp_dir().join(format!("nfix-bench-{}.log", std::process::id()));
+	let mut log  = Vec::with_capacity(megabytes << 20);
+
+	while log.len() < megabytes << 20 {
+		log.extend_from_slice(&messages);
+	}
+
+	std::fs::write(&path, log).unwrap();
+	path
+}
@@ -1,15 +1,22 @@
-use clap::Parser;
+use std::{num::NonZeroUsize, path::PathBuf};
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::{timestamp::{Pattern, TimeSource}, timezone::TimeZone, parser::COMMAND_NAME, proxy::rule::Rule, replay, simulate::Fill, window::Bound};
 
 const SOH: char = '\x01';
 
-/// Parse FIX messages on stdin and output on stdout.
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+/// Parse FIX messages on stdin (or in files) and output on stdout.
+#[derive(Parser, Debug, Clone)]
+#[command(name = COMMAND_NAME, version, about, long_about = None)]
 pub struct Args {
+	#[command(subcommand)]
+	pub command: Option<Command>,
+
 	/// Separator character between fields.
 	/// Only ascii values are supported.
 	/// Defaults to SOH ('\x01').
-	#[arg(short='s', long, default_value_t = SOH)]
+	#[arg(short='s', long, default_value_t = SOH, global = true)]
 	pub field_separator: char,
 
 	/// Show all fields and not just the most relevant fields.
@@ -19,4 +26,275 @@ pub struct Args {
 	/// Keep original ordering of tags.
 	#[arg(short = 'o', long, default_value_t = false)]
 	pub original_tag_ordering: bool,
+
+	/// Show UTCTimestamp fields (e.g. SendingTime) in a time zone as well: `local`, a name like
+	/// `Europe/London`, an offset like `+05:30` or a POSIX TZ rule.
+	#[arg(long, value_name = "ZONE", value_parser = TimeZone::parse)]
+	pub tz: Option<TimeZone>,
+
+	/// Show the time since the previous message of the session (the pair of SenderCompID and TargetCompID
+	/// in either direction) after SendingTime.
+	#[arg(long, default_value_t = false, conflicts_with = "jobs")]
+	pub delta: bool,
+
+	/// Follow a file as it grows (like `tail -F`) instead of reading stdin.
+	/// The file is reopened if it is rotated or truncated.
+	#[arg(short = 'f', long, value_name = "FILE", conflicts_with = "files")]
+	pub follow: Option<PathBuf>,
+
+	/// Files to read instead of stdin.
+	/// Files are memory-mapped and scanned with a fast path that requires messages to start with `8=FIX`.
+	#[arg(value_name = "FILES")]
+	pub files: Vec<PathBuf>,
+
+	/// Number of threads used for processing files.
+	/// Files are split in chunks at line boundaries and the output is written in the original order.
+	#[arg(short = 'j', long, default_value = "1", requires = "files")]
+	pub jobs: NonZeroUsize,
+
+	/// Read files as network captures (pcap or pcapng) and decode FIX messages from the TCP streams.
+	#[arg(long, default_value_t = false, requires = "files", conflicts_with = "jobs")]
+	pub pcap: bool,
+
+	/// Print an analysis of the messages instead of the messages themselves.
+	#[arg(long, value_enum, conflicts_with_all = ["follow", "pcap"])]
+	pub mode: Option<Mode>,
+
+	/// Keep the field separator of the input instead of SOH in `--mode normalize` output.
+	#[arg(long, default_value_t = false, requires = "mode")]
+	pub keep_separator: bool,
+
+	/// Tags redacted by `--mode redact`.
+	#[arg(long, value_name = "TAGS", value_delimiter = ',', default_value = "1,109,448,115,58", requires = "mode")]
+	pub redact_tags: Vec<u32>,
+
+	/// Mask redacted values with `***` instead of replacing them with tokens.
+	#[arg(long, default_value_t = false, requires = "mode")]
+	pub mask: bool,
+
+	/// Derive tokens from a keyed hash (HMAC-SHA256) of the value, e.g. `Account-5f1d3c9a0b7e2d44`, so they
+	/// match across files and runs with the same key. Tokens are numbered per run without it.
+	#[arg(long, value_name = "KEY", requires = "mode", conflicts_with = "mask")]
+	pub redact_key: Option<String>,
+
+	/// Keep only the messages at or after the time (e.g. `2026-10-16T13:00` or `13:00` on the date of `--to`
+	/// or the first message). Files are assumed to be sorted by time and searched for the start of the window.
+	#[arg(long, value_name = "TIME", value_parser = Bound::parse, conflicts_with_all = ["follow", "pcap", "jobs"])]
+	pub from: Option<Bound>,
+
+	/// Keep only the messages before the time (e.g. `2026-10-16T13:05` or `13:05` on the date of `--from`).
+	#[arg(long, value_name = "TIME", value_parser = Bound::parse, conflicts_with_all = ["follow", "pcap", "jobs"])]
+	pub to: Option<Bound>,
+
+	/// Where the time of a message is taken from for `--from`, `--to`, `--mode latency` and `--mode stats`.
+	#[arg(long, value_enum, default_value_t = TimeSource::SendingTime)]
+	pub time_source: TimeSource,
+
+	/// Pattern of the timestamp in log lines for `--time-source log` with `%Y`, `%m`, `%d`, `%H`, `%M`, `%S`
+	/// and `%f` (fractional seconds with 3, 6, 9 or 12 digits), e.g. `%Y/%m/%d-%H:%M:%S,%f`. Any timestamp in the line is found without it.
+	#[arg(long, value_name = "PATTERN", value_parser = Pattern::parse)]
+	pub time_format: Option<Pattern>,
+}
+
+/// Analysis printed instead of the messages.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Mode {
+	/// Group messages in order chains (linked by ClOrdID, OrigClOrdID, OrderID and ExecID) and
+	/// print each chain with a status summary.
+	Chains,
+	/// Check the sequence numbers (34) of each session for gaps, duplicates, retransmissions and resets.
+	Sessions,
+	/// Pair requests with their responses (e.g. NewOrderSingle and the first ExecutionReport) and print
+	/// latency percentiles per session and MsgType.
+	Latency,
+	/// Print aggregate counts (per MsgType, session, Symbol and OrdStatus, messages per second, unknown tags
+	/// and parse, BodyLength and CheckSum failures).
+	Stats,
+	/// Check each message against its definition in the dictionary (required fields, unknown tags,
+	/// field order, duplicates, repeating groups and data types) and print the violations.
+	Validate,
+	/// Re-encode each message as canonical tag=value (one message per line): fields are separated by SOH,
+	/// header and trailer fields are put in dictionary order and BodyLength and CheckSum are recalculated.
+	Normalize,
+	/// Replace the values of sensitive fields (see `--redact-tags`) with tokens and print the input with
+	/// BodyLength and CheckSum of the redacted messages recalculated.
+	/// The same value of a tag is always replaced by the same token (e.g. `Account-1`).
+	Redact,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+	/// Build a well-formed message from fields like `35=D 55=VOD.L 54=1 38=100` and print it.
+	/// BeginString (8), BodyLength (9) and CheckSum (10) are filled in.
+	Build(BuildArgs),
+	/// Compare two messages field by field and show added, removed and changed fields.
+	/// Repeating groups are compared instance by instance.
+	Diff(DiffArgs),
+	/// Log on to a FIX engine (or accept a connection from one) and print the application messages received.
+	/// Messages to send are read from stdin, one per line with `tag=value` or `name=value` fields separated by
+	/// the field separator (e.g. `-s '|'` and `35=D|Symbol=VOD.L|54=1|38=100`). The session logs out at the end of stdin.
+	Session(SessionArgs),
+	/// Act as an exchange: accept FIX sessions, keep a price-time order book per Symbol and answer NewOrderSingle,
+	/// OrderCancelRequest and OrderCancelReplaceRequest with ExecutionReports.
+	Simulate(SimulateArgs),
+	/// Replay the application messages one party sent in a log over a new session and print the responses.
+	/// CompIDs, MsgSeqNum, SendingTime and CheckSum are those of the new session, and the original pacing is kept.
+	Replay(ReplayArgs),
+	/// Forward FIX connections to an upstream engine and print the messages going each way.
+	/// Rules can drop, delay or change matching messages for fault injection (e.g. `--rule 'drop if 35=0'`).
+	Proxy(ProxyArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct BuildArgs {
+	/// Fields as `tag=value` or `name=value` with the tag name from the dictionary (e.g. `Symbol=VOD.L`).
+	#[arg(value_name = "FIELDS", required = true)]
+	pub fields: Vec<String>,
+
+	/// BeginString (8) unless given in the fields.
+	#[arg(long, default_value = "FIX.4.4")]
+	pub begin_string: String,
+
+	/// Add MsgSeqNum (34).
+	#[arg(long, value_name = "SEQ_NUM")]
+	pub seq_num: Option<u64>,
+
+	/// Add SendingTime (52) with the current time.
+	#[arg(long, default_value_t = false)]
+	pub sending_time: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct DiffArgs {
+	/// Files to read the messages from instead of stdin.
+	#[arg(value_name = "FILES")]
+	pub files: Vec<PathBuf>,
+
+	/// Positions of the two messages to compare (the first two by default).
+	#[arg(short = 'm', long, value_name = "LEFT,RIGHT", value_delimiter = ',')]
+	pub messages: Vec<usize>,
+
+	/// Compare all fields and not just the most relevant fields.
+	#[arg(short = 'a', long, default_value_t = false)]
+	pub show_all_fields: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct SessionArgs {
+	/// Connect to the acceptor at `host:port` (as the initiator).
+	#[arg(long, value_name = "HOST:PORT", required_unless_present = "listen", conflicts_with = "listen")]
+	pub connect: Option<String>,
+
+	/// Listen on `host:port` (or `:port` on all interfaces) and accept one connection (as the acceptor).
+	#[arg(long, value_name = "HOST:PORT")]
+	pub listen: Option<String>,
+
+	#[command(flatten)]
+	pub settings: SessionSettings,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct SimulateArgs {
+	/// Listen on `host:port` (or `:port` on all interfaces) and accept one session after the other.
+	#[arg(long, value_name = "HOST:PORT", default_value = ":9876")]
+	pub listen: String,
+
+	#[command(flatten)]
+	pub settings: SessionSettings,
+
+	/// How orders are filled besides matching each other.
+	#[arg(long, value_enum, default_value_t = Fill::Book)]
+	pub fill: Fill,
+
+	/// Share of the rest of an order filled with `--fill partial`.
+	#[arg(long, value_name = "PERCENT", default_value_t = 50, value_parser = clap::value_parser!(u8).range(1..=100))]
+	pub partial_fill: u8,
+
+	/// Reject the orders for these Symbols (as unknown symbols).
+	#[arg(long, value_name = "SYMBOLS", value_delimiter = ',')]
+	pub reject_symbols: Vec<String>,
+
+	/// Reject every Nth order.
+	#[arg(long, value_name = "N")]
+	pub reject_every: Option<NonZeroUsize>,
+
+	/// Delay before answering each message.
+	#[arg(long, value_name = "MILLISECONDS", default_value_t = 0)]
+	pub latency: u64,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ReplayArgs {
+	/// Connect to the acceptor at `host:port` (as the initiator).
+	#[arg(long, value_name = "HOST:PORT")]
+	pub connect: String,
+
+	/// Files to read the messages from instead of stdin.
+	#[arg(value_name = "FILES")]
+	pub files: Vec<PathBuf>,
+
+	#[command(flatten)]
+	pub settings: SessionSettings,
+
+	/// Replay the messages sent by this SenderCompID (49) in the log (the sender of the first message by default).
+	#[arg(long, value_name = "COMP_ID")]
+	pub source: Option<String>,
+
+	/// Multiplier of the original pacing (e.g. `2` for twice as fast or `0` to send without pauses).
+	#[arg(long, value_name = "MULTIPLIER", default_value_t = 1.0, value_parser = replay::parse_speed)]
+	pub speed: f64,
+
+	/// Time to wait for responses after the last message before logging out.
+	#[arg(long, value_name = "MILLISECONDS", default_value_t = 1000)]
+	pub wait: u64,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ProxyArgs {
+	/// Listen on `host:port` (or `:port` on all interfaces) for the connections to forward.
+	#[arg(long, value_name = "HOST:PORT")]
+	pub listen: String,
+
+	/// Forward the connections to the engine at `host:port`.
+	#[arg(long, value_name = "HOST:PORT")]
+	pub upstream: String,
+
+	/// Write the messages to the file instead of stdout.
+	#[arg(long, value_name = "FILE")]
+	pub log: Option<PathBuf>,
+
+	/// Fault injection rule `ACTION [if FIELD...]` applied to the messages in both directions, with the action
+	/// `drop`, `delay MILLISECONDS`, `set TAG=VALUE` or `remove TAG` (e.g. `delay 500 if 35=8 39=2`).
+	/// A delayed message holds back the messages after it in the same direction.
+	#[arg(long = "rule", value_name = "RULE", value_parser = Rule::parse)]
+	pub rules: Vec<Rule>,
+}
+
+/// Settings of a FIX session.
+#[derive(clap::Args, Debug, Clone)]
+pub struct SessionSettings {
+	/// SenderCompID (49) of the messages sent.
+	#[arg(long, value_name = "COMP_ID")]
+	pub sender_comp_id: String,
+
+	/// TargetCompID (56) of the messages sent.
+	#[arg(long, value_name = "COMP_ID")]
+	pub target_comp_id: String,
+
+	/// BeginString (8).
+	#[arg(long, default_value = "FIX.4.4")]
+	pub begin_string: String,
+
+	/// Heartbeat interval (HeartBtInt 108) the initiator asks for at Logon.
+	#[arg(long, value_name = "SECONDS", default_value_t = 30)]
+	pub heart_bt_int: u32,
+
+	/// Directory keeping the sequence numbers and sent messages so that the session can be resumed.
+	/// Without it each session starts at MsgSeqNum 1.
+	#[arg(long, value_name = "DIRECTORY")]
+	pub store: Option<PathBuf>,
+
+	/// Start over at MsgSeqNum 1 with ResetSeqNumFlag (141) at Logon.
+	#[arg(long, default_value_t = false)]
+	pub reset_seq_num: bool,
 }
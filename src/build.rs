@@ -0,0 +1,115 @@
+//! Module for building well-formed messages from a compact description like `35=D 55=VOD.L 54=1 38=100`.
+//! Tags can be given by name and BeginString (8), BodyLength (9) and CheckSum (10) are filled in.
+
+use std::{io::{Error, ErrorKind, Write}, time::SystemTime};
+
+use crate::{args::BuildArgs, dictionary::Dictionary, encoder, parser::{field::{Field, MAX_TAG_LENGTH}, message::Message, tag::Tag}, timestamp::Timestamp};
+
+const BEGIN_STRING: u32 = 8;
+const MSG_SEQ_NUM:  u32 = 34;
+const SENDING_TIME: u32 = 52;
+
+/// Builds the message and writes it followed by a newline.
+pub fn run<D: Dictionary>(args: &BuildArgs, separator: u8, output: &mut impl Write) -> Result<(), Error> {
+	let message = build(args, &D::default(), SystemTime::now())?;
+	output.write_all(&encoder::encode(&message, separator))?;
+	writeln!(output)
+}
+
+fn build(args: &BuildArgs, dictionary: &impl Dictionary, now: SystemTime) -> Result<Message, Error> {
+	let mut fields = args.fields.iter()
+		.map(|field| parse(field, dictionary))
+		.collect::<Result<Vec<Field>, Error>>()?;
+
+	// Header fields that are filled in go before the given fields (but after MsgType).
+	let given      = |tag| fields.iter().any(|field: &Field| field.tag().number() == tag);
+	let mut header = Vec::new();
+	if !given(BEGIN_STRING) {
+		header.push(field(BEGIN_STRING, args.begin_string.clone()));
+	}
+	if let Some(seq_num) = args.seq_num && !given(MSG_SEQ_NUM) {
+		header.push(field(MSG_SEQ_NUM, seq_num.to_string()));
+	}
+	if args.sending_time && !given(SENDING_TIME) {
+		let since_epoch = now.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+		header.push(field(SENDING_TIME, Timestamp::from_duration(since_epoch, 3).to_string()));
+	}
+	header.append(&mut fields);
+
+	Ok(encoder::complete(Message::new(header))?)
+}
+
+/// Parses `tag=value` or `name=value`.
+pub(crate) fn parse(description: &str, dictionary: &impl Dictionary) -> Result<Field, Error> {
+	let invalid = |reason: &str| Error::new(ErrorKind::InvalidInput, format!("Invalid field '{}': {}", description, reason));
+
+	let Some((tag, value)) = description.split_once('=') else {
+		return Err(invalid("expected tag=value"));
+	};
+	if value.is_empty() {
+		return Err(invalid("empty value"));
+	}
+	let tag = if !tag.is_empty() && tag.bytes().all(|byte| byte.is_ascii_digit()) {
+		if tag.len() > MAX_TAG_LENGTH {
+			return Err(invalid(&format!("tag is longer than {} digits", MAX_TAG_LENGTH)));
+		}
+		tag.parse::<u32>().ok().and_then(|number| Tag::try_from(number).ok()).ok_or_else(|| invalid("tag must be positive"))?
+	}
+	else {
+		dictionary.tag(tag).ok_or_else(|| invalid("unknown tag name"))?
+	};
+
+	Ok(Field::new(tag, value.as_bytes().to_vec()))
+}
+
+fn field(tag: u32, value: String) -> Field {
+	let tag = Tag::try_from(tag).unwrap_or_else(|_| panic!("Should be a valid tag {}", tag));
+	Field::new(tag, value.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use clap::Parser;
+
+	use super::*;
+	use crate::{args::{Args, Command}, dictionary::BaseDictionary, parser::COMMAND_NAME};
+
+	fn build_args(arguments: &[&str]) -> BuildArgs {
+		let args = Args::parse_from([COMMAND_NAME, "build"].iter().chain(arguments));
+		let Some(Command::Build(build_args)) = args.command else {
+			panic!("Should be the build command");
+		};
+		build_args
+	}
+
+	fn built(arguments: &[&str]) -> Result<String, Error> {
+		let now     = SystemTime::UNIX_EPOCH + Duration::from_millis(1_231_352_116_000);
+		let message = build(&build_args(arguments), &BaseDictionary::default(), now)?;
+		Ok(String::from_utf8(encoder::encode(&message, b'|')).unwrap())
+	}
+
+	#[test]
+	fn tag_numbers_and_names() {
+		assert_eq!(built(&["35=D", "Symbol=VOD.L", "54=1", "OrderQty=100"]).unwrap(), "8=FIX.4.4|9=26|35=D|55=VOD.L|54=1|38=100|10=007|");
+	}
+
+	#[test]
+	fn header_fields_if_asked() {
+		assert_eq!(
+			built(&["--begin-string", "FIX.4.2", "--seq-num", "177", "--sending-time", "MsgType=A", "49=SERVER", "56=CLIENT", "98=0", "108=30"]).unwrap(),
+			"8=FIX.4.2|9=69|35=A|34=177|52=20090107-18:15:16.000|49=SERVER|56=CLIENT|98=0|108=30|10=000|"
+		);
+	}
+
+	#[test]
+	fn invalid_fields() {
+		assert_eq!(built(&["35=D", "Symbl=VOD.L"]).unwrap_err().to_string(), "Invalid field 'Symbl=VOD.L': unknown tag name");
+		assert_eq!(built(&["35=D", "55="]).unwrap_err().to_string(),         "Invalid field '55=': empty value");
+		assert_eq!(built(&["35=D", "55"]).unwrap_err().to_string(),          "Invalid field '55': expected tag=value");
+		assert_eq!(built(&["35=D", "0=X"]).unwrap_err().to_string(),         "Invalid field '0=X': tag must be positive");
+		assert_eq!(built(&["35=D", "1234567=X"]).unwrap_err().to_string(),   "Invalid field '1234567=X': tag is longer than 6 digits");
+		assert_eq!(built(&["55=VOD.L"]).unwrap_err().to_string(),            "MsgType (35) is missing");
+	}
+}
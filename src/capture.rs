@@ -0,0 +1,123 @@
+//! Module for decoding FIX messages from network captures (pcap and pcapng).
+//! TCP data is reassembled per direction of each connection and fed through its own `Decoder`.
+//! Each message is labelled with the capture time and the source and destination endpoints.
+
+use std::{collections::BTreeMap, io::{Error, Write}, net::SocketAddr, time::Duration};
+
+use crate::{args::Args, capture::{pcap::Packets, stream::Stream}, dictionary::BaseDictionary, filter::BaseFilter, formatter::{FixFormatter, FromArgs, SimpleFormatter}, parser::{Flush, decoder::{Decoder, Event}}, timestamp::Timestamp};
+
+pub(crate) mod pcap;
+pub(crate) mod packet;
+pub(crate) mod stream;
+
+type Formatter = SimpleFormatter<BaseDictionary, BaseFilter>;
+
+/// One direction of a TCP connection.
+type Direction = (SocketAddr, SocketAddr);
+
+#[derive(Debug)]
+struct Connection {
+	stream:    Stream,
+	decoder:   Decoder,
+	timestamp: Duration,
+}
+
+pub fn process(input: &[u8], output: &mut impl Write, args: Args, flush: Flush) -> Result<(), Error> {
+	let formatter       = Formatter::new(&args);
+	let mut connections = BTreeMap::<Direction, Connection>::new();
+
+	for packet in Packets::new(input)? {
+		let packet = packet?;
+		let Some(segment) = packet::segment(packet.link_type, packet.data) else {
+			continue;
+		};
+		let direction = (segment.source, segment.destination);
+
+		if segment.syn {
+			// New connection - finish any previous connection between the same endpoints.
+			if let Some(mut connection) = connections.remove(&direction) {
+				let data = connection.stream.finish();
+				write_messages(&formatter, output, flush, direction, connection.timestamp, connection.decoder.feed(&data))?;
+			}
+		}
+
+		let connection = connections.entry(direction).or_insert_with(|| Connection {
+			stream:    Stream::new(),
+			decoder:   Decoder::new(args.field_separator as u8),
+			timestamp: packet.timestamp,
+		});
+		connection.timestamp = packet.timestamp;
+
+		let data = connection.stream.push(segment.sequence, segment.syn, segment.payload);
+		write_messages(&formatter, output, flush, direction, packet.timestamp, connection.decoder.feed(&data))?;
+	}
+
+	for (direction, mut connection) in connections {
+		let data = connection.stream.finish();
+		write_messages(&formatter, output, flush, direction, connection.timestamp, connection.decoder.feed(&data))?;
+	}
+
+	output.flush()
+}
+
+/// Writes the messages decoded from the data of a connection.
+/// Bytes that are not part of a FIX message are dropped.
+fn write_messages(formatter: &Formatter, output: &mut impl Write, flush: Flush, (source, destination): Direction, timestamp: Duration, events: impl Iterator<Item = Event>) -> Result<(), Error> {
+	for event in events {
+		let message = match event {
+			Event::Message(message) => message,
+			Event::Passthrough(_)
+			| Event::Invalid(_)     => continue,
+		};
+		writeln!(output, "{} {} -> {}", Timestamp::from_duration(timestamp, 6).format_utc(), source, destination)?;
+		formatter.format(&message, output)?;
+		if flush == Flush::EachMessage {
+			output.flush()?;
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{capture::{packet::tests::frame, pcap::tests::pcap}, parser::COMMAND_NAME};
+
+	#[test]
+	fn messages_from_reassembled_streams() {
+		let client = "10.0.0.1:5001";
+		let server = "10.0.0.2:9876";
+		let order  = b"8=FIX.4.2\x019=5\x0135=D\x0149=CLIENT\x0156=SERVER\x0110=123\x01";
+		let ack    = b"8=FIX.4.2\x019=5\x0135=8\x0149=SERVER\x0156=CLIENT\x0110=123\x01";
+		let (order_start, order_end) = order.split_at(20);
+
+		let capture = pcap(&[
+			(1_583_147_676, 393_000, &frame(client, server, 1000, true, b"")),
+			// Out of order.
+			(1_583_147_676, 394_000, &frame(client, server, 1001 + 20, false, order_end)),
+			(1_583_147_676, 395_000, &frame(client, server, 1001, false, order_start)),
+			// Retransmitted.
+			(1_583_147_676, 396_000, &frame(client, server, 1001, false, order_start)),
+			(1_583_147_676, 531_000, &frame(server, client, 5000, false, ack)),
+		]);
+
+		let mut output = Vec::new();
+		process(&capture, &mut output, args(), Flush::EndOfInput).unwrap();
+
+		insta::assert_snapshot!(String::from_utf8(output).unwrap(), @r"
+		2020-03-02 11:14:36.395000 10.0.0.1:5001 -> 10.0.0.2:9876
+		    35 : MsgType      = D
+		    49 : SenderCompID = CLIENT
+		    56 : TargetCompID = SERVER
+		2020-03-02 11:14:36.531000 10.0.0.2:9876 -> 10.0.0.1:5001
+		    35 : MsgType      = 8
+		    49 : SenderCompID = SERVER
+		    56 : TargetCompID = CLIENT
+		");
+	}
+
+	fn args() -> Args {
+		use clap::Parser;
+		Args::parse_from([COMMAND_NAME, "--pcap", "capture.pcap"])
+	}
+}
@@ -0,0 +1,199 @@
+//! Module for decoding TCP segments from captured link layer frames.
+//! Supports Ethernet (with VLAN tags), Linux cooked captures, BSD loopback and raw IP
+//! carrying IPv4 or IPv6. Fragmented IPv4 packets and IPv6 extension headers are not supported.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+const LINK_TYPE_NULL:       u16 = 0;
+const LINK_TYPE_ETHERNET:   u16 = 1;
+const LINK_TYPE_RAW:        u16 = 101;
+const LINK_TYPE_LINUX_SLL:  u16 = 113;
+const LINK_TYPE_IPV4:       u16 = 228;
+const LINK_TYPE_IPV6:       u16 = 229;
+const LINK_TYPE_LINUX_SLL2: u16 = 276;
+
+const ETHER_TYPE_IPV4: u16 = 0x0800;
+const ETHER_TYPE_IPV6: u16 = 0x86dd;
+const ETHER_TYPE_VLAN: u16 = 0x8100;
+const ETHER_TYPE_QINQ: u16 = 0x88a8;
+
+const PROTOCOL_TCP: u8 = 6;
+
+const TCP_SYN: u8 = 0x02;
+
+/// TCP segment from one endpoint to another.
+#[derive(Debug, PartialEq)]
+pub struct Segment<'a> {
+	pub source:      SocketAddr,
+	pub destination: SocketAddr,
+	pub sequence:    u32,
+	pub syn:         bool,
+	pub payload:     &'a [u8],
+}
+
+/// Decodes the TCP segment in a frame.
+/// Returns `None` for anything else.
+pub fn segment(link_type: u16, frame: &[u8]) -> Option<Segment<'_>> {
+	match link_type {
+		LINK_TYPE_ETHERNET   => ethernet(frame),
+		LINK_TYPE_LINUX_SLL  => ether_type_payload(read_u16(frame, 14)?, frame.get(16..)?),
+		LINK_TYPE_LINUX_SLL2 => ether_type_payload(read_u16(frame, 0)?, frame.get(20..)?),
+		LINK_TYPE_NULL       => {
+			// Address family in host byte order - IPv4 is 2 and IPv6 is 24, 28 or 30 depending on OS.
+			let family = *frame.first()?.max(frame.get(3)?);
+			match family {
+				2            => ipv4(frame.get(4..)?),
+				24 | 28 | 30 => ipv6(frame.get(4..)?),
+				_            => None,
+			}
+		}
+		LINK_TYPE_RAW  => ip(frame),
+		LINK_TYPE_IPV4 => ipv4(frame),
+		LINK_TYPE_IPV6 => ipv6(frame),
+		_              => None,
+	}
+}
+
+fn ethernet(frame: &[u8]) -> Option<Segment<'_>> {
+	let mut position = 12;
+	let mut ether_type = read_u16(frame, position)?;
+
+	// Skip VLAN tags.
+	while ether_type == ETHER_TYPE_VLAN || ether_type == ETHER_TYPE_QINQ {
+		position  += 4;
+		ether_type = read_u16(frame, position)?;
+	}
+
+	ether_type_payload(ether_type, frame.get(position + 2..)?)
+}
+
+fn ether_type_payload(ether_type: u16, payload: &[u8]) -> Option<Segment<'_>> {
+	match ether_type {
+		ETHER_TYPE_IPV4 => ipv4(payload),
+		ETHER_TYPE_IPV6 => ipv6(payload),
+		_               => None,
+	}
+}
+
+fn ip(packet: &[u8]) -> Option<Segment<'_>> {
+	match packet.first()? >> 4 {
+		4 => ipv4(packet),
+		6 => ipv6(packet),
+		_ => None,
+	}
+}
+
+fn ipv4(packet: &[u8]) -> Option<Segment<'_>> {
+	let header_length   = usize::from(packet.first()? & 0x0f) * 4;
+	let total_length    = usize::from(read_u16(packet, 2)?);
+	let fragment        = read_u16(packet, 6)?;
+	let protocol        = *packet.get(9)?;
+	let more_fragments  = fragment & 0x2000 != 0;
+	let fragment_offset = fragment & 0x1fff;
+
+	if protocol != PROTOCOL_TCP || more_fragments || fragment_offset != 0 {
+		return None;
+	}
+
+	let source      = IpAddr::V4(Ipv4Addr::from(read_u32(packet, 12)?));
+	let destination = IpAddr::V4(Ipv4Addr::from(read_u32(packet, 16)?));
+	// The total length excludes any Ethernet padding.
+	tcp(source, destination, packet.get(header_length..total_length)?)
+}
+
+fn ipv6(packet: &[u8]) -> Option<Segment<'_>> {
+	let payload_length = usize::from(read_u16(packet, 4)?);
+	let next_header    = *packet.get(6)?;
+
+	if next_header != PROTOCOL_TCP {
+		return None;
+	}
+
+	let source: [u8; 16]      = packet.get(8..24)?.try_into().ok()?;
+	let destination: [u8; 16] = packet.get(24..40)?.try_into().ok()?;
+	tcp(IpAddr::V6(Ipv6Addr::from(source)), IpAddr::V6(Ipv6Addr::from(destination)), packet.get(40..40 + payload_length)?)
+}
+
+fn tcp(source: IpAddr, destination: IpAddr, segment: &[u8]) -> Option<Segment<'_>> {
+	let source_port      = read_u16(segment, 0)?;
+	let destination_port = read_u16(segment, 2)?;
+	let sequence         = read_u32(segment, 4)?;
+	let header_length    = usize::from(segment.get(12)? >> 4) * 4;
+	let flags            = *segment.get(13)?;
+
+	Some(Segment {
+		source:      SocketAddr::new(source, source_port),
+		destination: SocketAddr::new(destination, destination_port),
+		sequence,
+		syn:         flags & TCP_SYN != 0,
+		payload:     segment.get(header_length..)?,
+	})
+}
+
+fn read_u16(bytes: &[u8], position: usize) -> Option<u16> {
+	Some(u16::from_be_bytes(bytes.get(position..position + 2)?.try_into().ok()?))
+}
+
+fn read_u32(bytes: &[u8], position: usize) -> Option<u32> {
+	Some(u32::from_be_bytes(bytes.get(position..position + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+
+	/// Creates an Ethernet frame with an IPv4 TCP segment (with ACK flag and optional SYN).
+	pub fn frame(source: &str, destination: &str, sequence: u32, syn: bool, payload: &[u8]) -> Vec<u8> {
+		let source: SocketAddr      = source.parse().unwrap();
+		let destination: SocketAddr = destination.parse().unwrap();
+		let (IpAddr::V4(source_ip), IpAddr::V4(destination_ip)) = (source.ip(), destination.ip()) else {
+			panic!("Only IPv4 is supported");
+		};
+
+		let mut frame = vec![0; 12];
+		frame.extend(ETHER_TYPE_IPV4.to_be_bytes());
+
+		// IPv4 header.
+		frame.extend([0x45, 0]);
+		frame.extend(((20 + 20 + payload.len()) as u16).to_be_bytes());
+		frame.extend([0, 0, 0x40, 0, 64, PROTOCOL_TCP, 0, 0]);
+		frame.extend(source_ip.octets());
+		frame.extend(destination_ip.octets());
+
+		// TCP header.
+		frame.extend(source.port().to_be_bytes());
+		frame.extend(destination.port().to_be_bytes());
+		frame.extend(sequence.to_be_bytes());
+		frame.extend([0; 4]);
+		frame.extend([5 << 4, if syn { TCP_SYN | 0x10 } else { 0x10 }]);
+		frame.extend([0; 6]);
+
+		frame.extend(payload);
+		frame
+	}
+
+	#[test]
+	fn ethernet_ipv4_tcp() {
+		let mut frame = frame("10.0.0.1:5001", "10.0.0.2:9876", 1000, false, b"8=FIX");
+		// Ethernet padding is not part of the payload.
+		frame.extend([0; 4]);
+
+		assert_eq!(segment(LINK_TYPE_ETHERNET, &frame), Some(Segment {
+			source:      "10.0.0.1:5001".parse().unwrap(),
+			destination: "10.0.0.2:9876".parse().unwrap(),
+			sequence:    1000,
+			syn:         false,
+			payload:     b"8=FIX",
+		}));
+	}
+
+	#[test]
+	fn udp_is_ignored() {
+		let mut frame = frame("10.0.0.1:5001", "10.0.0.2:9876", 1000, false, b"8=FIX");
+		if let Some(protocol) = frame.get_mut(14 + 9) {
+			*protocol = 17;
+		}
+
+		assert_eq!(segment(LINK_TYPE_ETHERNET, &frame), None);
+	}
+}
@@ -0,0 +1,321 @@
+//! Module for reading packets from pcap and pcapng capture files.
+
+use std::{io::{Error, ErrorKind}, time::Duration};
+
+const PCAP_MICROS:       u32 = 0xa1b2_c3d4;
+const PCAP_NANOS:        u32 = 0xa1b2_3c4d;
+const PCAPNG_SECTION:    u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER: u32 = 0x1a2b_3c4d;
+
+const PCAP_HEADER_LENGTH:        usize = 24;
+const PCAP_RECORD_HEADER_LENGTH: usize = 16;
+
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 1;
+const SIMPLE_PACKET_BLOCK:         u32 = 3;
+const ENHANCED_PACKET_BLOCK:       u32 = 6;
+const OPTION_END:                  u16 = 0;
+const OPTION_TIMESTAMP_RESOLUTION: u16 = 9;
+
+/// Packet read from a capture.
+#[derive(Debug, PartialEq)]
+pub struct Packet<'a> {
+	/// Capture time since the Unix epoch.
+	pub timestamp: Duration,
+	pub link_type: u16,
+	pub data:      &'a [u8],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Endian {
+	Little,
+	Big,
+}
+
+#[derive(Debug)]
+struct Interface {
+	link_type: u16,
+	/// Number of timestamp units per second.
+	units_per_second: u64,
+}
+
+#[derive(Debug)]
+enum Format {
+	Pcap {
+		link_type:        u16,
+		units_per_second: u64,
+	},
+	PcapNg {
+		interfaces: Vec<Interface>,
+	},
+}
+
+/// Iterator over the packets in a pcap or pcapng capture.
+#[derive(Debug)]
+pub struct Packets<'a> {
+	input:    &'a [u8],
+	position: usize,
+	endian:   Endian,
+	format:   Format,
+}
+
+impl<'a> Packets<'a> {
+	pub fn new(input: &'a [u8]) -> Result<Self, Error> {
+		let magic = read_u32(input, 0, Endian::Little).ok_or_else(|| invalid("Capture is too short"))?;
+
+		match magic {
+			PCAPNG_SECTION => {
+				let mut packets = Self { input, position: 0, endian: Endian::Little, format: Format::PcapNg { interfaces: Vec::new() } };
+				// Reads the section header block to determine the byte order.
+				packets.next_block()?;
+				Ok(packets)
+			}
+			_ => {
+				let (endian, units_per_second) = match (magic, magic.swap_bytes()) {
+					(PCAP_MICROS, _) => (Endian::Little, 1_000_000),
+					(PCAP_NANOS,  _) => (Endian::Little, 1_000_000_000),
+					(_, PCAP_MICROS) => (Endian::Big,    1_000_000),
+					(_, PCAP_NANOS)  => (Endian::Big,    1_000_000_000),
+					(_, _)           => return Err(invalid("Not a pcap or pcapng capture")),
+				};
+				let link_type = read_u32(input, 20, endian).ok_or_else(|| invalid("Truncated pcap header"))?;
+
+				Ok(Self {
+					input,
+					position: PCAP_HEADER_LENGTH,
+					endian,
+					format: Format::Pcap { link_type: link_type as u16, units_per_second },
+				})
+			}
+		}
+	}
+
+	fn next_pcap_record(&mut self, link_type: u16, units_per_second: u64) -> Result<Option<Packet<'a>>, Error> {
+		if self.position >= self.input.len() {
+			return Ok(None);
+		}
+
+		let header       = self.position;
+		let seconds      = self.read_u32(header).ok_or_else(|| invalid("Truncated pcap record"))?;
+		let units        = self.read_u32(header + 4).ok_or_else(|| invalid("Truncated pcap record"))?;
+		let length       = self.read_u32(header + 8).ok_or_else(|| invalid("Truncated pcap record"))? as usize;
+		let data_start   = header + PCAP_RECORD_HEADER_LENGTH;
+		let data         = self.input.get(data_start..data_start + length).ok_or_else(|| invalid("Truncated pcap record"))?;
+		self.position    = data_start + length;
+
+		Ok(Some(Packet {
+			timestamp: Duration::from_secs(u64::from(seconds)) + duration(u64::from(units), units_per_second),
+			link_type,
+			data,
+		}))
+	}
+
+	/// Reads the next pcapng block and returns it if it is a packet.
+	fn next_block(&mut self) -> Result<Option<Packet<'a>>, Error> {
+		let start      = self.position;
+		let block_type = self.read_u32(start).ok_or_else(|| invalid("Truncated pcapng block"))?;
+
+		if block_type == PCAPNG_SECTION {
+			let byte_order = read_u32(self.input, start + 8, Endian::Little).ok_or_else(|| invalid("Truncated pcapng section header"))?;
+			self.endian = match (byte_order, byte_order.swap_bytes()) {
+				(PCAPNG_BYTE_ORDER, _) => Endian::Little,
+				(_, PCAPNG_BYTE_ORDER) => Endian::Big,
+				(_, _)                 => return Err(invalid("Invalid pcapng byte order")),
+			};
+			// Interface ids are local to a section.
+			self.format = Format::PcapNg { interfaces: Vec::new() };
+		}
+
+		let length = self.read_u32(start + 4).ok_or_else(|| invalid("Truncated pcapng block"))? as usize;
+		if length < 12 || !length.is_multiple_of(4) {
+			return Err(invalid("Invalid pcapng block length"));
+		}
+		let body   = self.input.get(start + 8..start + length - 4).ok_or_else(|| invalid("Truncated pcapng block"))?;
+		self.position = start + length;
+
+		let Format::PcapNg { interfaces } = &mut self.format else {
+			return Ok(None);
+		};
+
+		match block_type {
+			INTERFACE_DESCRIPTION_BLOCK => {
+				let link_type = read_u16(body, 0, self.endian).ok_or_else(|| invalid("Truncated interface description"))?;
+				let options   = body.get(8..).unwrap_or_default();
+				interfaces.push(Interface { link_type, units_per_second: timestamp_resolution(options, self.endian) });
+				Ok(None)
+			}
+			ENHANCED_PACKET_BLOCK => {
+				let interface = read_u32(body,  0, self.endian).ok_or_else(|| invalid("Truncated enhanced packet"))? as usize;
+				let high      = read_u32(body,  4, self.endian).ok_or_else(|| invalid("Truncated enhanced packet"))?;
+				let low       = read_u32(body,  8, self.endian).ok_or_else(|| invalid("Truncated enhanced packet"))?;
+				let length    = read_u32(body, 12, self.endian).ok_or_else(|| invalid("Truncated enhanced packet"))? as usize;
+				let data      = body.get(20..20 + length).ok_or_else(|| invalid("Truncated enhanced packet"))?;
+				let interface = interfaces.get(interface).ok_or_else(|| invalid("Unknown pcapng interface"))?;
+				let units     = (u64::from(high) << 32) | u64::from(low);
+
+				Ok(Some(Packet {
+					timestamp: duration(units, interface.units_per_second),
+					link_type: interface.link_type,
+					data,
+				}))
+			}
+			SIMPLE_PACKET_BLOCK => {
+				let interface = interfaces.first().ok_or_else(|| invalid("Unknown pcapng interface"))?;
+				let length    = read_u32(body, 0, self.endian).ok_or_else(|| invalid("Truncated simple packet"))? as usize;
+				let data      = body.get(4..).unwrap_or_default();
+
+				Ok(Some(Packet {
+					// Simple packets have no timestamp.
+					timestamp: Duration::ZERO,
+					link_type: interface.link_type,
+					data:      data.get(..length).unwrap_or(data),
+				}))
+			}
+			_ => Ok(None), // Other blocks are not relevant.
+		}
+	}
+
+	fn read_u32(&self, position: usize) -> Option<u32> {
+		read_u32(self.input, position, self.endian)
+	}
+}
+
+impl<'a> Iterator for Packets<'a> {
+	type Item = Result<Packet<'a>, Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		match self.format {
+			Format::Pcap { link_type, units_per_second } => {
+				let record = self.next_pcap_record(link_type, units_per_second);
+				if record.is_err() {
+					// Stop at the first error.
+					self.position = self.input.len();
+				}
+				record.transpose()
+			}
+			Format::PcapNg { .. } => {
+				while self.position < self.input.len() {
+					match self.next_block() {
+						Ok(None)         => {} // Not a packet block.
+						Ok(Some(packet)) => return Some(Ok(packet)),
+						Err(e)           => {
+							// Stop at the first error.
+							self.position = self.input.len();
+							return Some(Err(e));
+						}
+					}
+				}
+				None
+			}
+		}
+	}
+}
+
+/// Reads the `if_tsresol` option (defaults to microseconds).
+fn timestamp_resolution(options: &[u8], endian: Endian) -> u64 {
+	let mut position = 0;
+
+	while let (Some(code), Some(length)) = (read_u16(options, position, endian), read_u16(options, position + 2, endian)) {
+		if code == OPTION_END {
+			break;
+		}
+		if code == OPTION_TIMESTAMP_RESOLUTION && let Some(resolution) = options.get(position + 4) {
+			let exponent = u32::from(resolution & 0x7f);
+			return if resolution & 0x80 == 0 { 10u64.saturating_pow(exponent) } else { 2u64.saturating_pow(exponent) };
+		}
+		// Options are padded to 32 bits.
+		position += 4 + usize::from(length).next_multiple_of(4);
+	}
+
+	1_000_000
+}
+
+fn duration(units: u64, units_per_second: u64) -> Duration {
+	let seconds  = units / units_per_second;
+	let fraction = u128::from(units % units_per_second) * 1_000_000_000 / u128::from(units_per_second);
+	Duration::new(seconds, fraction as u32)
+}
+
+fn read_u16(bytes: &[u8], position: usize, endian: Endian) -> Option<u16> {
+	let bytes = bytes.get(position..position + 2)?.try_into().ok()?;
+	Some(match endian {
+		Endian::Little => u16::from_le_bytes(bytes),
+		Endian::Big    => u16::from_be_bytes(bytes),
+	})
+}
+
+fn read_u32(bytes: &[u8], position: usize, endian: Endian) -> Option<u32> {
+	let bytes = bytes.get(position..position + 4)?.try_into().ok()?;
+	Some(match endian {
+		Endian::Little => u32::from_le_bytes(bytes),
+		Endian::Big    => u32::from_be_bytes(bytes),
+	})
+}
+
+fn invalid(message: &str) -> Error {
+	Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+
+	/// Creates a classic pcap capture (little endian, microseconds) with Ethernet frames.
+	pub fn pcap(packets: &[(u32, u32, &[u8])]) -> Vec<u8> {
+		let mut capture = Vec::new();
+		capture.extend(PCAP_MICROS.to_le_bytes());
+		capture.extend(2u16.to_le_bytes());
+		capture.extend(4u16.to_le_bytes());
+		capture.extend([0; 8]);
+		capture.extend(65535u32.to_le_bytes());
+		capture.extend(1u32.to_le_bytes());
+
+		for (seconds, micros, data) in packets {
+			capture.extend(seconds.to_le_bytes());
+			capture.extend(micros.to_le_bytes());
+			capture.extend((data.len() as u32).to_le_bytes());
+			capture.extend((data.len() as u32).to_le_bytes());
+			capture.extend(*data);
+		}
+		capture
+	}
+
+	#[test]
+	fn read_pcap() {
+		let capture = pcap(&[(1, 500_000, b"first"), (2, 0, b"second")]);
+		let packets = Packets::new(&capture).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+
+		assert_eq!(packets, vec![
+			Packet { timestamp: Duration::from_millis(1500), link_type: 1, data: b"first" },
+			Packet { timestamp: Duration::from_secs(2),      link_type: 1, data: b"second" },
+		]);
+	}
+
+	#[test]
+	fn read_big_endian_pcapng_with_nanosecond_resolution() {
+		let mut capture = Vec::new();
+		block(&mut capture, PCAPNG_SECTION, &[&PCAPNG_BYTE_ORDER.to_be_bytes()[..], &[0, 1, 0, 0], &[0xff; 8]].concat());
+		// Interface with link type 1 and option if_tsresol = 9.
+		block(&mut capture, INTERFACE_DESCRIPTION_BLOCK, &[&1u16.to_be_bytes()[..], &[0; 2], &[0; 4], &9u16.to_be_bytes(), &1u16.to_be_bytes(), &[9, 0, 0, 0], &[0; 4]].concat());
+		// Statistics block is skipped.
+		block(&mut capture, 5, &[0; 12]);
+		let timestamp = 1_500_000_000u64;
+		block(&mut capture, ENHANCED_PACKET_BLOCK, &[&0u32.to_be_bytes()[..], &((timestamp >> 32) as u32).to_be_bytes(), &(timestamp as u32).to_be_bytes(), &5u32.to_be_bytes(), &5u32.to_be_bytes(), b"hello", &[0; 3]].concat());
+
+		let packets = Packets::new(&capture).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+
+		assert_eq!(packets, vec![Packet { timestamp: Duration::from_millis(1500), link_type: 1, data: b"hello" }]);
+	}
+
+	#[test]
+	fn not_a_capture() {
+		assert!(Packets::new(b"8=FIX.4.2\x019=5\x01").is_err());
+	}
+
+	fn block(capture: &mut Vec<u8>, block_type: u32, body: &[u8]) {
+		let length = (body.len() + 12) as u32;
+		capture.extend(block_type.to_be_bytes());
+		capture.extend(length.to_be_bytes());
+		capture.extend(body);
+		capture.extend(length.to_be_bytes());
+	}
+}
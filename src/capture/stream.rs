@@ -0,0 +1,137 @@
+//! Module for reassembling the data sent in one direction of a TCP connection.
+//! Retransmitted data is dropped and out-of-order segments are held back until the data before them
+//! has arrived. Data that was never captured is skipped when too many segments are waiting for it.
+
+/// Number of out-of-order segments to hold back before giving up on missing data.
+const MAX_PENDING_SEGMENTS: usize = 1024;
+
+#[derive(Debug, Default)]
+pub struct Stream {
+	/// Sequence number of the next byte in order.
+	next_sequence: Option<u32>,
+	/// Out-of-order segments (sequence number and payload).
+	pending:       Vec<(u32, Vec<u8>)>,
+}
+
+impl Stream {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds a segment and returns the data that is now in order.
+	pub fn push(&mut self, sequence: u32, syn: bool, payload: &[u8]) -> Vec<u8> {
+		// SYN takes up one sequence number.
+		let sequence = if syn { sequence.wrapping_add(1) } else { sequence };
+		let next     = *self.next_sequence.get_or_insert(sequence);
+		let mut data = Vec::new();
+
+		if offset(sequence, next) > 0 {
+			if !self.pending.iter().any(|(s, p)| *s == sequence && p.len() >= payload.len()) {
+				self.pending.push((sequence, payload.to_vec()));
+			}
+		}
+		else {
+			self.append(sequence, payload, &mut data);
+		}
+
+		self.drain_pending(&mut data);
+
+		if self.pending.len() > MAX_PENDING_SEGMENTS {
+			self.skip_gap();
+			self.drain_pending(&mut data);
+		}
+
+		data
+	}
+
+	/// Returns all data held back (skipping data that was never captured).
+	pub fn finish(&mut self) -> Vec<u8> {
+		let mut data = Vec::new();
+		while !self.pending.is_empty() {
+			self.skip_gap();
+			self.drain_pending(&mut data);
+		}
+		data
+	}
+
+	/// Appends the part of the payload that has not been seen before.
+	fn append(&mut self, sequence: u32, payload: &[u8], data: &mut Vec<u8>) {
+		let next = self.next_sequence.unwrap_or(sequence);
+		let seen = offset(sequence, next).unsigned_abs() as usize;
+
+		if let Some(new) = payload.get(seen..) {
+			data.extend_from_slice(new);
+			self.next_sequence = Some(next.wrapping_add(new.len() as u32));
+		}
+	}
+
+	/// Appends pending segments that are now in order.
+	fn drain_pending(&mut self, data: &mut Vec<u8>) {
+		while let Some(next) = self.next_sequence {
+			let Some(index) = self.pending.iter().position(|(sequence, _)| offset(*sequence, next) <= 0) else {
+				return;
+			};
+			let (sequence, payload) = self.pending.swap_remove(index);
+			self.append(sequence, &payload, data);
+		}
+	}
+
+	/// Continues from the first pending segment.
+	fn skip_gap(&mut self) {
+		let next  = self.next_sequence.unwrap_or_default();
+		let first = self.pending.iter().map(|(sequence, _)| *sequence).min_by_key(|sequence| offset(*sequence, next));
+		if first.is_some() {
+			self.next_sequence = first;
+		}
+	}
+}
+
+/// Distance from the next expected sequence number (handles wrap around).
+fn offset(sequence: u32, next: u32) -> i32 {
+	sequence.wrapping_sub(next) as i32
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn in_order() {
+		let mut stream = Stream::new();
+		assert_eq!(stream.push(100, true,  b""),      b"");
+		assert_eq!(stream.push(101, false, b"Hello"), b"Hello");
+		assert_eq!(stream.push(106, false, b" World"), b" World");
+	}
+
+	#[test]
+	fn out_of_order() {
+		let mut stream = Stream::new();
+		assert_eq!(stream.push(100, false, b"Hello"), b"Hello");
+		assert_eq!(stream.push(111, false, b"!"),      b"");
+		assert_eq!(stream.push(105, false, b" World"), b" World!");
+	}
+
+	#[test]
+	fn retransmits_are_dropped() {
+		let mut stream = Stream::new();
+		assert_eq!(stream.push(100, false, b"Hello"),  b"Hello");
+		assert_eq!(stream.push(100, false, b"Hello"),  b"");
+		// Partially retransmitted.
+		assert_eq!(stream.push(103, false, b"lo Wor"), b" Wor");
+	}
+
+	#[test]
+	fn sequence_number_wrap_around() {
+		let mut stream = Stream::new();
+		assert_eq!(stream.push(u32::MAX - 1, false, b"Hello"), b"Hello");
+		assert_eq!(stream.push(3,            false, b" World"), b" World");
+	}
+
+	#[test]
+	fn missing_data_is_skipped_at_end() {
+		let mut stream = Stream::new();
+		assert_eq!(stream.push(100, false, b"Hello"), b"Hello");
+		assert_eq!(stream.push(110, false, b"World"), b"");
+		assert_eq!(stream.finish(), b"World");
+	}
+}
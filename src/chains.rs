@@ -0,0 +1,361 @@
+//! Module for grouping messages in order chains to show the lifecycle of each order.
+//! Messages are linked by ClOrdID (11), OrigClOrdID (41), OrderID (37), ExecID (17) and ExecRefID (19)
+//! within a session (the pair of SenderCompID and TargetCompID in either direction).
+//!
+//! Each chain is printed with a summary line followed by its messages in the order they were logged.
+//! Violations of the order state machine found when replaying the chain are listed below the summary.
+
+use std::{collections::HashMap, io::{Error, Write}};
+
+use crate::{chains::order_state::OrdStatus, formatter::FixFormatter, parser::{field::Field, message::Message, tag::Tag}};
+
+pub(crate) mod order_state;
+
+const ACCOUNT:                    Tag = Tag::new(1);
+const AVG_PX:                     Tag = Tag::new(6);
+const CL_ORD_ID:                  Tag = Tag::new(11);
+const CUM_QTY:                    Tag = Tag::new(14);
+const EXEC_ID:                    Tag = Tag::new(17);
+const EXEC_REF_ID:                Tag = Tag::new(19);
+const ORDER_ID:                   Tag = Tag::new(37);
+const ORDER_QTY:                  Tag = Tag::new(38);
+const ORD_STATUS:                 Tag = Tag::new(39);
+const ORIG_CL_ORD_ID:             Tag = Tag::new(41);
+const PRICE:                      Tag = Tag::new(44);
+const SENDER_COMP_ID:             Tag = Tag::new(49);
+const SIDE:                       Tag = Tag::new(54);
+const SYMBOL:                     Tag = Tag::new(55);
+const TARGET_COMP_ID:             Tag = Tag::new(56);
+const LEAVES_QTY:                 Tag = Tag::new(151);
+const MASS_CANCEL_REQUEST_TYPE:   Tag = Tag::new(530);
+const MASS_CANCEL_RESPONSE:       Tag = Tag::new(531);
+const TOTAL_AFFECTED_ORDERS:      Tag = Tag::new(533);
+const LEG_SYMBOL:                 Tag = Tag::new(600);
+
+/// MsgTypes of mass cancel messages.
+/// Their OrigClOrdID refer to affected orders (in a group) and do not link the chain.
+const MASS_CANCEL_MSG_TYPES: [&[u8]; 2] = [b"q", b"r"];
+
+/// Order fields shown in the summary line (latest value).
+const SUMMARY_FIELDS: [(Tag, &str); 11] = [
+	(ORDER_ID,                 "OrderID"),
+	(ACCOUNT,                  "Account"),
+	(SIDE,                     "Side"),
+	(ORDER_QTY,                "OrderQty"),
+	(PRICE,                    "Price"),
+	(CUM_QTY,                  "CumQty"),
+	(LEAVES_QTY,               "LeavesQty"),
+	(AVG_PX,                   "AvgPx"),
+	(MASS_CANCEL_REQUEST_TYPE, "MassCancelRequestType"),
+	(MASS_CANCEL_RESPONSE,     "MassCancelResponse"),
+	(TOTAL_AFFECTED_ORDERS,    "TotalAffectedOrders"),
+];
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+enum Identifier {
+	ClOrd,
+	Order,
+	Exec,
+}
+
+/// Identifier linking messages in a chain.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+struct Key {
+	session: (Vec<u8>, Vec<u8>),
+	kind:    Identifier,
+	value:   Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+struct Chain {
+	/// Messages with their position in the input.
+	messages: Vec<(usize, Message)>,
+	keys:     Vec<Key>,
+}
+
+#[derive(Debug, Default)]
+pub struct Chains {
+	/// Chains in order of first appearance (`None` when merged into another chain).
+	chains: Vec<Option<Chain>>,
+	index:  HashMap<Key, usize>,
+	count:  usize,
+}
+
+impl Chains {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds the message to its chain.
+	/// Messages without order identifiers (e.g. session messages) are ignored.
+	pub fn add(&mut self, message: Message) {
+		let position = self.count;
+		self.count  += 1;
+
+		let keys = keys(&message);
+		if keys.is_empty() {
+			return;
+		}
+
+		let mut linked: Vec<usize> = keys.iter().filter_map(|key| self.index.get(key).copied()).collect();
+		linked.sort_unstable();
+		linked.dedup();
+
+		let target = match linked.first() {
+			Some(target) => *target,
+			None         => {
+				self.chains.push(Some(Chain::default()));
+				self.chains.len() - 1
+			}
+		};
+
+		// The message links chains that were separate so far - merge them.
+		for other in linked.iter().skip(1) {
+			if let Some(other) = self.chains.get_mut(*other).and_then(Option::take) {
+				for key in &other.keys {
+					self.index.insert(key.clone(), target);
+				}
+				if let Some(Some(chain)) = self.chains.get_mut(target) {
+					chain.messages.extend(other.messages);
+					chain.keys.extend(other.keys);
+				}
+			}
+		}
+
+		if let Some(Some(chain)) = self.chains.get_mut(target) {
+			for key in keys {
+				if self.index.insert(key.clone(), target).is_none() {
+					chain.keys.push(key);
+				}
+			}
+			chain.messages.push((position, message));
+		}
+	}
+
+	/// Writes each chain with a summary line followed by its messages.
+	pub fn write(self, formatter: &impl FixFormatter, output: &mut impl Write) -> Result<(), Error> {
+		for mut chain in self.chains.into_iter().flatten() {
+			chain.messages.sort_by_key(|(position, _)| *position);
+			let messages: Vec<Message> = chain.messages.into_iter().map(|(_, message)| message).collect();
+
+			output.write_all(&summary(&messages))?;
+			output.write_all(b"\n")?;
+			for violation in order_state::validate(&messages) {
+				writeln!(output, "! {}", violation)?;
+			}
+			for message in &messages {
+				output.write_all(b"\n")?;
+				formatter.format(message, output)?;
+			}
+			output.write_all(b"\n")?;
+		}
+		Ok(())
+	}
+}
+
+/// Identifiers in the message.
+fn keys(message: &Message) -> Vec<Key> {
+	let session = {
+		let sender = message.value(SENDER_COMP_ID).unwrap_or_default().to_vec();
+		let target = message.value(TARGET_COMP_ID).unwrap_or_default().to_vec();
+		if sender <= target { (sender, target) } else { (target, sender) }
+	};
+	let mut ids = vec![(CL_ORD_ID, Identifier::ClOrd), (ORDER_ID, Identifier::Order), (EXEC_ID, Identifier::Exec), (EXEC_REF_ID, Identifier::Exec)];
+	if !is_mass_cancel(message) {
+		ids.push((ORIG_CL_ORD_ID, Identifier::ClOrd));
+	}
+
+	ids.into_iter()
+		.filter_map(|(tag, kind)| message.value(tag).map(|value| (kind, value)))
+		// OrderID is "NONE" on some rejects and must not link all rejected orders.
+		.filter(|(kind, value)| !(*kind == Identifier::Order && *value == b"NONE"))
+		.map(|(kind, value)| Key { session: session.clone(), kind, value: value.to_vec() })
+		.collect()
+}
+
+/// Summary line with the ClOrdIDs of the chain and the latest order state.
+fn summary(messages: &[Message]) -> Vec<u8> {
+	let mut parts = Vec::new();
+
+	let mut cl_ord_ids: Vec<&[u8]> = Vec::new();
+	let linked_cl_ord_ids = messages.iter().flat_map(|message| {
+		let orig_cl_ord_id = if is_mass_cancel(message) { None } else { message.value(ORIG_CL_ORD_ID) };
+		[orig_cl_ord_id, message.value(CL_ORD_ID)]
+	});
+	for cl_ord_id in linked_cl_ord_ids.flatten() {
+		if !cl_ord_ids.contains(&cl_ord_id) {
+			cl_ord_ids.push(cl_ord_id);
+		}
+	}
+	if !cl_ord_ids.is_empty() {
+		parts.push([&b"ClOrdID "[..], &cl_ord_ids.join(&b" -> "[..])].concat());
+	}
+
+	let symbol = messages.iter().find_map(|message| message.value(SYMBOL));
+	let legs   = messages.iter()
+		.map(|message| message.get_all(LEG_SYMBOL).map(Field::value_bytes).collect::<Vec<_>>())
+		.find(|legs| !legs.is_empty())
+		.unwrap_or_default();
+	if let Some(symbol) = symbol {
+		parts.push([&b"Symbol "[..], symbol].concat());
+	}
+	if !legs.is_empty() {
+		parts.push([&b"Legs "[..], &legs.join(&b"/"[..])].concat());
+	}
+
+	for (tag, name) in SUMMARY_FIELDS {
+		if let Some(value) = messages.iter().rev().find_map(|message| message.value(tag)) {
+			parts.push([name.as_bytes(), b" ", value].concat());
+		}
+	}
+
+	if let Some(status) = messages.iter().rev().find_map(|message| message.value(ORD_STATUS)) {
+		match OrdStatus::parse(status) {
+			Some(ord_status) => parts.push([&b"OrdStatus "[..], status, b" (", ord_status.name().as_bytes(), b")"].concat()),
+			None             => parts.push([&b"OrdStatus "[..], status].concat()),
+		}
+	}
+
+	parts.push(format!("{} message{}", messages.len(), if messages.len() == 1 { "" } else { "s" }).into_bytes());
+	parts.join(&b", "[..])
+}
+
+fn is_mass_cancel(message: &Message) -> bool {
+	message.msg_type().is_some_and(|msg_type| MASS_CANCEL_MSG_TYPES.contains(&msg_type))
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{args::Args, dictionary::BaseDictionary, filter::BaseFilter, formatter::{FromArgs, SimpleFormatter}, parser::{COMMAND_NAME, scanner::{Scanned, Scanner}}};
+
+	#[test]
+	fn interleaved_orders_with_replace() {
+		let input = "\
+			8=FIX.4.4|35=D|49=CLIENT|56=BROKER|11=A1|55=VOD.L|54=1|38=100|44=1.5|10=000|\n\
+			8=FIX.4.4|35=D|49=CLIENT|56=BROKER|11=B1|55=BARC.L|54=2|38=50|10=000|\n\
+			8=FIX.4.4|35=0|49=CLIENT|56=BROKER|10=000|\n\
+			8=FIX.4.4|35=8|49=BROKER|56=CLIENT|11=A1|37=O-A|17=E1|39=0|14=0|151=100|10=000|\n\
+			8=FIX.4.4|35=8|49=BROKER|56=CLIENT|11=B1|37=O-B|17=E2|39=8|10=000|\n\
+			8=FIX.4.4|35=G|49=CLIENT|56=BROKER|11=A2|41=A1|55=VOD.L|54=1|38=200|10=000|\n\
+			8=FIX.4.4|35=8|49=BROKER|56=CLIENT|11=A2|41=A1|37=O-A|17=E3|39=5|14=0|151=200|10=000|\n\
+			8=FIX.4.4|35=8|49=BROKER|56=CLIENT|11=A2|37=O-A|17=E4|39=2|14=200|151=0|6=1.5|10=000|\n";
+
+		insta::assert_snapshot!(chains(input), @r"
+		ClOrdID A1 -> A2, Symbol VOD.L, OrderID O-A, Side 1, OrderQty 200, Price 1.5, CumQty 200, LeavesQty 0, AvgPx 1.5, OrdStatus 2 (Filled), 5 messages
+
+		    11 : ClOrdID      = A1
+		    35 : MsgType      = D
+		    38 : OrderQty     = 100
+		    44 : Price        = 1.5
+		    49 : SenderCompID = CLIENT
+		    54 : Side         = 1
+		    55 : Symbol       = VOD.L
+		    56 : TargetCompID = BROKER
+
+		    11 : ClOrdID      = A1
+		    14 : CumQty       = 0
+		    17 : ExecID       = E1
+		    35 : MsgType      = 8
+		    37 : OrderID      = O-A
+		    39 : OrdStatus    = 0
+		    49 : SenderCompID = BROKER
+		    56 : TargetCompID = CLIENT
+		   151 : LeavesQty    = 100
+
+		    11 : ClOrdID      = A2
+		    35 : MsgType      = G
+		    38 : OrderQty     = 200
+		    41 : OrigClOrdID  = A1
+		    49 : SenderCompID = CLIENT
+		    54 : Side         = 1
+		    55 : Symbol       = VOD.L
+		    56 : TargetCompID = BROKER
+
+		    11 : ClOrdID      = A2
+		    14 : CumQty       = 0
+		    17 : ExecID       = E3
+		    35 : MsgType      = 8
+		    37 : OrderID      = O-A
+		    39 : OrdStatus    = 5
+		    41 : OrigClOrdID  = A1
+		    49 : SenderCompID = BROKER
+		    56 : TargetCompID = CLIENT
+		   151 : LeavesQty    = 200
+
+		     6 : AvgPx        = 1.5
+		    11 : ClOrdID      = A2
+		    14 : CumQty       = 200
+		    17 : ExecID       = E4
+		    35 : MsgType      = 8
+		    37 : OrderID      = O-A
+		    39 : OrdStatus    = 2
+		    49 : SenderCompID = BROKER
+		    56 : TargetCompID = CLIENT
+		   151 : LeavesQty    = 0
+
+		ClOrdID B1, Symbol BARC.L, OrderID O-B, Side 2, OrderQty 50, OrdStatus 8 (Rejected), 2 messages
+
+		    11 : ClOrdID      = B1
+		    35 : MsgType      = D
+		    38 : OrderQty     = 50
+		    49 : SenderCompID = CLIENT
+		    54 : Side         = 2
+		    55 : Symbol       = BARC.L
+		    56 : TargetCompID = BROKER
+
+		    11 : ClOrdID      = B1
+		    17 : ExecID       = E2
+		    35 : MsgType      = 8
+		    37 : OrderID      = O-B
+		    39 : OrdStatus    = 8
+		    49 : SenderCompID = BROKER
+		    56 : TargetCompID = CLIENT
+		");
+	}
+
+	#[test]
+	fn mass_cancel_does_not_link_affected_orders() {
+		let input = "\
+			8=FIX.4.4|35=D|49=CLIENT|56=BROKER|11=A1|55=VOD.L|10=000|\n\
+			8=FIX.4.4|35=D|49=CLIENT|56=BROKER|11=B1|55=VOD.L|10=000|\n\
+			8=FIX.4.4|35=q|49=CLIENT|56=BROKER|11=M1|530=1|55=VOD.L|10=000|\n\
+			8=FIX.4.4|35=r|49=BROKER|56=CLIENT|11=M1|37=MC-1|530=1|531=1|533=2|534=2|41=A1|41=B1|10=000|\n\
+			8=FIX.4.4|35=8|49=BROKER|56=CLIENT|11=A1|37=O-A|17=E1|39=4|10=000|\n\
+			8=FIX.4.4|35=8|49=BROKER|56=CLIENT|11=B1|37=O-B|17=E2|39=4|10=000|\n";
+
+		let summaries: Vec<String> = chains(input).lines().filter(|line| line.starts_with("ClOrdID")).map(str::to_string).collect();
+		assert_eq!(summaries, vec![
+			"ClOrdID A1, Symbol VOD.L, OrderID O-A, OrdStatus 4 (Canceled), 2 messages",
+			"ClOrdID B1, Symbol VOD.L, OrderID O-B, OrdStatus 4 (Canceled), 2 messages",
+			"ClOrdID M1, Symbol VOD.L, OrderID MC-1, MassCancelRequestType 1, MassCancelResponse 1, TotalAffectedOrders 2, 2 messages",
+		]);
+	}
+
+	#[test]
+	fn multileg_order() {
+		let input = "\
+			8=FIX.4.4|35=AB|49=CLIENT|56=BROKER|11=L1|555=2|600=FGBL|624=1|600=FGBM|624=2|10=000|\n\
+			8=FIX.4.4|35=8|49=BROKER|56=CLIENT|11=L1|37=O-L|17=E1|39=0|10=000|\n";
+
+		let summaries: Vec<String> = chains(input).lines().filter(|line| line.starts_with("ClOrdID")).map(str::to_string).collect();
+		assert_eq!(summaries, vec!["ClOrdID L1, Legs FGBL/FGBM, OrderID O-L, OrdStatus 0 (New), 2 messages"]);
+	}
+
+	fn chains(input: &str) -> String {
+		let mut chains = Chains::new();
+		for scanned in Scanner::new(input.as_bytes(), b'|') {
+			if let Scanned::Message(message) = scanned {
+				chains.add(message.into_owned());
+			}
+		}
+
+		let args       = { use clap::Parser; Args::parse_from([COMMAND_NAME]) };
+		let formatter  = SimpleFormatter::<BaseDictionary, BaseFilter>::new(&args);
+		let mut output = Vec::new();
+		chains.write(&formatter, &mut output).unwrap();
+		String::from_utf8(output).unwrap()
+	}
+}
@@ -0,0 +1,366 @@
+//! Module for replaying an order chain through a model of the FIX order state machine.
+//! The execution reports of the chain are checked for illegal OrdStatus transitions (e.g. a fill
+//! after the order is Canceled) and inconsistent quantities and prices.
+//! Trade corrections and cancels (ExecType G and H) are allowed to change filled quantities.
+
+use std::fmt::Display;
+
+use crate::parser::{decimal::Decimal, message::Message, tag::Tag};
+
+const AVG_PX:     Tag = Tag::new(6);
+const CUM_QTY:    Tag = Tag::new(14);
+const LAST_PX:    Tag = Tag::new(31);
+const LAST_QTY:   Tag = Tag::new(32);
+const ORDER_QTY:  Tag = Tag::new(38);
+const ORD_STATUS: Tag = Tag::new(39);
+const EXEC_TYPE:  Tag = Tag::new(150);
+const LEAVES_QTY: Tag = Tag::new(151);
+
+const EXECUTION_REPORT: &[u8] = b"8";
+/// Decimals shown for the average fill price (at least).
+const AVG_PX_DECIMALS:  u32   = 6;
+
+/// ExecTypes of fills (FIX 4.2 partial fill and fill, FIX 4.4 trade).
+const FILL_EXEC_TYPES:       [&[u8]; 3] = [b"1", b"2", b"F"];
+/// ExecTypes of trade corrections and cancels.
+const CORRECTION_EXEC_TYPES: [&[u8]; 2] = [b"G", b"H"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrdStatus {
+	New,
+	PartiallyFilled,
+	Filled,
+	DoneForDay,
+	Canceled,
+	Replaced,
+	PendingCancel,
+	Stopped,
+	Rejected,
+	Suspended,
+	PendingNew,
+	Calculated,
+	Expired,
+	AcceptedForBidding,
+	PendingReplace,
+}
+
+impl OrdStatus {
+	pub fn parse(value: &[u8]) -> Option<Self> {
+		let status = match value {
+			b"0" => OrdStatus::New,
+			b"1" => OrdStatus::PartiallyFilled,
+			b"2" => OrdStatus::Filled,
+			b"3" => OrdStatus::DoneForDay,
+			b"4" => OrdStatus::Canceled,
+			b"5" => OrdStatus::Replaced,
+			b"6" => OrdStatus::PendingCancel,
+			b"7" => OrdStatus::Stopped,
+			b"8" => OrdStatus::Rejected,
+			b"9" => OrdStatus::Suspended,
+			b"A" => OrdStatus::PendingNew,
+			b"B" => OrdStatus::Calculated,
+			b"C" => OrdStatus::Expired,
+			b"D" => OrdStatus::AcceptedForBidding,
+			b"E" => OrdStatus::PendingReplace,
+			_    => return None,
+		};
+		Some(status)
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			OrdStatus::New                => "New",
+			OrdStatus::PartiallyFilled    => "PartiallyFilled",
+			OrdStatus::Filled             => "Filled",
+			OrdStatus::DoneForDay         => "DoneForDay",
+			OrdStatus::Canceled           => "Canceled",
+			OrdStatus::Replaced           => "Replaced",
+			OrdStatus::PendingCancel      => "PendingCancel",
+			OrdStatus::Stopped            => "Stopped",
+			OrdStatus::Rejected           => "Rejected",
+			OrdStatus::Suspended          => "Suspended",
+			OrdStatus::PendingNew         => "PendingNew",
+			OrdStatus::Calculated         => "Calculated",
+			OrdStatus::Expired            => "Expired",
+			OrdStatus::AcceptedForBidding => "AcceptedForBidding",
+			OrdStatus::PendingReplace     => "PendingReplace",
+		}
+	}
+
+	/// OrdStatus (39) value.
+	#[must_use]
+	pub fn value(self) -> &'static str {
+		match self {
+			OrdStatus::New                => "0",
+			OrdStatus::PartiallyFilled    => "1",
+			OrdStatus::Filled             => "2",
+			OrdStatus::DoneForDay         => "3",
+			OrdStatus::Canceled           => "4",
+			OrdStatus::Replaced           => "5",
+			OrdStatus::PendingCancel      => "6",
+			OrdStatus::Stopped            => "7",
+			OrdStatus::Rejected           => "8",
+			OrdStatus::Suspended          => "9",
+			OrdStatus::PendingNew         => "A",
+			OrdStatus::Calculated         => "B",
+			OrdStatus::Expired            => "C",
+			OrdStatus::AcceptedForBidding => "D",
+			OrdStatus::PendingReplace     => "E",
+		}
+	}
+
+	/// No further executions are possible.
+	fn terminal(self) -> bool {
+		matches!(self, OrdStatus::Filled | OrdStatus::Canceled | OrdStatus::Rejected | OrdStatus::Expired)
+	}
+
+	/// The order is done for now and has no open quantity.
+	fn closed(self) -> bool {
+		self.terminal() || self == OrdStatus::DoneForDay
+	}
+
+	fn allowed_after(self, previous: OrdStatus) -> bool {
+		match self {
+			// A terminal status can only be repeated (e.g. in status responses).
+			_ if previous.terminal()    => self == previous,
+			OrdStatus::PendingNew       => previous == OrdStatus::PendingNew,
+			// An order can still be rejected after it was acknowledged (e.g. by the exchange after the broker).
+			OrdStatus::Rejected         => matches!(previous, OrdStatus::PendingNew | OrdStatus::New),
+			OrdStatus::New
+			| OrdStatus::PartiallyFilled
+			| OrdStatus::Filled
+			| OrdStatus::DoneForDay
+			| OrdStatus::Canceled
+			| OrdStatus::Replaced
+			| OrdStatus::PendingCancel
+			| OrdStatus::Stopped
+			| OrdStatus::Suspended
+			| OrdStatus::Calculated
+			| OrdStatus::Expired
+			| OrdStatus::AcceptedForBidding
+			| OrdStatus::PendingReplace => true,
+		}
+	}
+}
+
+impl Display for OrdStatus {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(self.name())
+	}
+}
+
+/// Illegal transition or inconsistency found in an execution report.
+#[derive(Debug, PartialEq)]
+pub struct Violation {
+	/// Position of the message in the chain (starting from 1).
+	pub message: usize,
+	pub reason:  String,
+}
+
+impl Display for Violation {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "Message {}: {}", self.message, self.reason)
+	}
+}
+
+/// State of the order replayed so far.
+#[derive(Debug, Default)]
+struct OrderState {
+	status:        Option<OrdStatus>,
+	cum_qty:       Decimal,
+	/// Quantity and notional of the fills seen.
+	filled_qty:    Decimal,
+	filled_amount: Decimal,
+	/// The fills are out of range so the average price cannot be checked.
+	overflow:      bool,
+}
+
+/// Replays the execution reports of the chain and returns the violations found.
+pub fn validate(messages: &[Message]) -> Vec<Violation> {
+	let mut state      = OrderState::default();
+	let mut violations = Vec::new();
+
+	for (index, message) in messages.iter().enumerate() {
+		if message.msg_type() != Some(EXECUTION_REPORT) {
+			continue;
+		}
+		let mut violation = |reason: String| violations.push(Violation { message: index + 1, reason });
+
+		let exec_type  = message.value(EXEC_TYPE);
+		let correction = exec_type.is_some_and(|exec_type| CORRECTION_EXEC_TYPES.contains(&exec_type));
+		let last_qty   = message.get_decimal(LAST_QTY).unwrap_or_default();
+		let fill       = !correction && (exec_type.is_some_and(|exec_type| FILL_EXEC_TYPES.contains(&exec_type)) || last_qty > Decimal::ZERO);
+		let status     = message.value(ORD_STATUS).and_then(OrdStatus::parse);
+
+		if let Some(previous) = state.status {
+			if fill && previous.terminal() {
+				violation(format!("Fill after {}", previous));
+			}
+			else if let Some(status) = status && !correction && !status.allowed_after(previous) {
+				violation(format!("OrdStatus {} after {}", status, previous));
+			}
+		}
+
+		if fill {
+			let last_px = message.get_decimal(LAST_PX).unwrap_or_default();
+			let amount = last_qty.checked_mul(last_px).and_then(|amount| state.filled_amount.checked_add(amount));
+			match (state.filled_qty.checked_add(last_qty), amount) {
+				(Some(filled_qty), Some(filled_amount)) => (state.filled_qty, state.filled_amount) = (filled_qty, filled_amount),
+				(_, _)                                  => state.overflow = true,
+			}
+		}
+
+		let cum_qty    = message.get_decimal(CUM_QTY).ok();
+		let leaves_qty = message.get_decimal(LEAVES_QTY).ok();
+		let order_qty  = message.get_decimal(ORDER_QTY).ok();
+
+		if let Some(cum_qty) = cum_qty {
+			if cum_qty < state.cum_qty && !correction {
+				violation(format!("CumQty decreased from {} to {}", state.cum_qty, cum_qty));
+			}
+			state.cum_qty = cum_qty;
+		}
+
+		match (status, cum_qty, leaves_qty, order_qty) {
+			(Some(status), Some(_), Some(leaves_qty), _) if status.closed() => {
+				if !leaves_qty.is_zero() {
+					violation(format!("LeavesQty {} is not 0 when {}", leaves_qty, status));
+				}
+			}
+			(Some(_), Some(cum_qty), Some(leaves_qty), Some(order_qty)) => {
+				if leaves_qty.checked_add(cum_qty) != Some(order_qty) {
+					violation(format!("LeavesQty {} + CumQty {} is not OrderQty {}", leaves_qty, cum_qty, order_qty));
+				}
+			}
+			(_, _, _, _) => {}
+		}
+
+		match (status, cum_qty) {
+			(Some(OrdStatus::New), Some(cum_qty)) if cum_qty > Decimal::ZERO          => violation(format!("OrdStatus New with CumQty {}", cum_qty)),
+			(Some(OrdStatus::PartiallyFilled), Some(cum_qty)) if cum_qty.is_zero() => violation("OrdStatus PartiallyFilled with CumQty 0".to_string()),
+			(_, _)                                                                 => {}
+		}
+
+		// The average price can only be checked when all fills have been seen.
+		// It is compared rounded to the decimals of the reported AvgPx.
+		if let (Ok(avg_px), Some(cum_qty)) = (message.get_decimal(AVG_PX), cum_qty)
+		&& cum_qty > Decimal::ZERO && !state.overflow && state.filled_qty == cum_qty
+		&& state.filled_amount.checked_div(state.filled_qty, avg_px.scale()) != Some(avg_px) {
+			let expected = state.filled_amount.checked_div(state.filled_qty, avg_px.scale().max(AVG_PX_DECIMALS)).unwrap_or_default();
+			violation(format!("AvgPx {} is not the average fill price {}", avg_px, expected));
+		}
+
+		if let Some(status) = status && !correction {
+			state.status = Some(status);
+		}
+	}
+
+	violations
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{dictionary::{BaseDictionary, Dictionary}, parser::scanner::{Scanned, Scanner}};
+
+	#[test]
+	fn valid_lifecycle() {
+		let violations = validate(&messages("\
+			8=FIX.4.4|35=D|11=A|38=100|10=0|\
+			8=FIX.4.4|35=8|11=A|39=A|150=A|14=0|151=100|38=100|10=0|\
+			8=FIX.4.4|35=8|11=A|39=0|150=0|14=0|151=100|38=100|10=0|\
+			8=FIX.4.4|35=8|11=A|39=1|150=F|32=40|31=10|14=40|151=60|6=10|38=100|10=0|\
+			8=FIX.4.4|35=8|11=A|39=2|150=F|32=60|31=11|14=100|151=0|6=10.6|38=100|10=0|"));
+
+		assert_eq!(violations, vec![]);
+	}
+
+	#[test]
+	fn fill_after_canceled() {
+		let violations = validate(&messages("\
+			8=FIX.4.4|35=8|11=A|39=0|150=0|14=0|151=100|38=100|10=0|\
+			8=FIX.4.4|35=8|11=A|39=4|150=4|14=0|151=0|38=100|10=0|\
+			8=FIX.4.4|35=8|11=A|39=2|150=F|32=100|31=10|14=100|151=0|6=10|38=100|10=0|"));
+
+		assert_eq!(reasons(&violations), vec![
+			"Message 3: Fill after Canceled",
+		]);
+	}
+
+	#[test]
+	fn inconsistent_quantities_and_prices() {
+		let violations = validate(&messages("\
+			8=FIX.4.4|35=8|11=A|39=1|150=F|32=50|31=10|14=50|151=50|6=10|38=100|10=0|\
+			8=FIX.4.4|35=8|11=A|39=1|150=0|14=40|151=50|38=100|10=0|\
+			8=FIX.4.4|35=8|11=A|39=2|150=F|32=60|31=20|14=110|151=0|6=12|38=110|10=0|\
+			8=FIX.4.4|35=8|11=A|39=0|150=0|14=110|151=0|38=110|10=0|"));
+
+		assert_eq!(reasons(&violations), vec![
+			"Message 2: CumQty decreased from 50 to 40",
+			"Message 2: LeavesQty 50 + CumQty 40 is not OrderQty 100",
+			"Message 3: AvgPx 12 is not the average fill price 15.454545",
+			"Message 4: OrdStatus New after Filled",
+			"Message 4: OrdStatus New with CumQty 110",
+		]);
+	}
+
+	#[test]
+	fn rejected() {
+		let violations = validate(&messages("\
+			8=FIX.4.4|35=8|11=A|39=0|150=0|10=0|\
+			8=FIX.4.4|35=8|11=A|39=8|150=8|10=0|"));
+
+		assert_eq!(reasons(&violations), Vec::<String>::new());
+
+		let violations = validate(&messages("\
+			8=FIX.4.4|35=8|11=B|39=1|150=F|32=40|31=10|14=40|151=60|6=10|38=100|10=0|\
+			8=FIX.4.4|35=8|11=B|39=8|150=8|14=40|151=0|38=100|10=0|"));
+
+		assert_eq!(reasons(&violations), vec!["Message 2: OrdStatus Rejected after PartiallyFilled"]);
+	}
+
+	#[test]
+	fn same_as_dictionary() {
+		let dictionary = BaseDictionary::default();
+		let definition = dictionary.field(ORD_STATUS).unwrap();
+		for (value, name) in definition.values {
+			let status = OrdStatus::parse(value.as_bytes()).unwrap();
+			assert_eq!((status.value(), status.name()), (*value, *name));
+		}
+		for value in (b'0'..=b'9').chain(b'A'..=b'Z') {
+			let known = definition.values.iter().any(|(known, _)| known.as_bytes() == [value]);
+			assert_eq!(OrdStatus::parse(&[value]).is_some(), known, "OrdStatus {}", value as char);
+		}
+	}
+
+	#[test]
+	fn fills_out_of_range() {
+		let violations = validate(&messages("\
+			8=FIX.4.4|35=8|11=A|39=1|150=F|32=1|31=0.0000000000000000001|14=1|151=100000000000000000|38=100000000000000001|10=0|\
+			8=FIX.4.4|35=8|11=A|39=2|150=F|32=100000000000000000|31=100000000000000000|14=100000000000000001|151=0|6=1|38=100000000000000001|10=0|"));
+
+		// The average price of fills out of range is not checked.
+		assert_eq!(reasons(&violations), Vec::<String>::new());
+
+		let violations = validate(&messages("\
+			8=FIX.4.4|35=8|11=B|39=1|150=F|32=0.0000000000000000001|31=0.00000000000000000001|14=0.0000000000000000001|151=100000000000000000|38=100000000000000000|10=0|"));
+
+		assert_eq!(reasons(&violations), vec![
+			"Message 1: LeavesQty 100000000000000000 + CumQty 0.0000000000000000001 is not OrderQty 100000000000000000",
+		]);
+	}
+
+	fn messages(input: &str) -> Vec<Message> {
+		Scanner::new(input.as_bytes(), b'|')
+			.filter_map(|scanned| match scanned {
+				Scanned::Message(message) => Some(message.into_owned()),
+				Scanned::Passthrough(_)
+				| Scanned::Invalid(_)     => None,
+			})
+			.collect()
+	}
+
+	fn reasons(violations: &[Violation]) -> Vec<String> {
+		violations.iter().map(ToString::to_string).collect()
+	}
+}
@@ -0,0 +1,182 @@
+//! Module with a tokio codec framing FIX tag=value messages on a byte stream (e.g. a `TcpStream`).
+//! Messages are framed by BodyLength (9) and their CheckSum (10) is validated before they are parsed.
+//! Outgoing messages are completed with BodyLength and CheckSum (see `encoder::complete`).
+//!
+//! Only available with the `tokio` feature.
+
+use std::io::{Error, ErrorKind};
+
+use bytes::{BufMut, BytesMut};
+
+use crate::{encoder, parser::{field::SOH, message::Message, scanner::{Scanned, Scanner}}};
+
+/// Longest BeginString and BodyLength accepted before the start of a stream is considered not to be FIX.
+const MAX_HEADER_LENGTH: usize = 32;
+/// Length of the CheckSum field (`10=nnn` and SOH).
+const CHECK_SUM_LENGTH:  usize = 7;
+
+#[derive(Debug, Clone)]
+pub struct FixCodec {
+	/// Longest BodyLength accepted.
+	max_body_length: usize,
+}
+
+impl FixCodec {
+	#[must_use]
+	pub fn new() -> Self {
+		Self { max_body_length: 1 << 20 }
+	}
+
+	/// Rejects messages with a BodyLength above the maximum (1 MiB by default).
+	#[must_use]
+	pub fn with_max_body_length(max_body_length: usize) -> Self {
+		Self { max_body_length }
+	}
+}
+
+impl Default for FixCodec {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Decodes messages framed by BodyLength.
+/// Input that does not start with a message and garbled messages (wrong CheckSum or a CheckSum that is not
+/// where BodyLength says) are `InvalidData` errors. The frame of a garbled message is consumed before the error.
+impl tokio_util::codec::Decoder for FixCodec {
+	type Item  = Message;
+	type Error = Error;
+
+	fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Message>, Error> {
+		let Some((header_length, body_length)) = header(src)? else {
+			return Ok(None);
+		};
+		if body_length > self.max_body_length {
+			return Err(invalid(format!("BodyLength {} is above the maximum {}", body_length, self.max_body_length)));
+		}
+
+		let length = header_length + body_length + CHECK_SUM_LENGTH;
+		if src.len() < length {
+			src.reserve(length - src.len());
+			return Ok(None);
+		}
+		let frame = src.split_to(length);
+
+		let (body, trailer) = frame.split_at(header_length + body_length);
+		let check_sum       = trailer.strip_prefix(b"10=")
+			.and_then(|value| value.strip_suffix(&[SOH]))
+			.filter(|value| value.iter().all(u8::is_ascii_digit))
+			.and_then(|value| str::from_utf8(value).ok()?.parse::<u32>().ok())
+			.ok_or_else(|| invalid(format!("CheckSum (10) is not at the end of the BodyLength {} message", body_length)))?;
+		let expected        = body.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+		if check_sum != u32::from(expected) {
+			return Err(invalid(format!("CheckSum {:03} does not match the calculated {:03}", check_sum, expected)));
+		}
+
+		match Scanner::new(&frame, SOH).next() {
+			Some(Scanned::Message(message)) => Ok(Some(message.into_owned())),
+			Some(Scanned::Passthrough(_) | Scanned::Invalid(_))
+			| None                          => Err(invalid("Message could not be parsed".to_string())),
+		}
+	}
+}
+
+/// Encodes messages with BodyLength and CheckSum calculated.
+impl tokio_util::codec::Encoder<Message> for FixCodec {
+	type Error = Error;
+
+	fn encode(&mut self, message: Message, dst: &mut BytesMut) -> Result<(), Error> {
+		let message = encoder::complete(message)?;
+		dst.put_slice(&encoder::encode(&message, SOH));
+		Ok(())
+	}
+}
+
+/// Length of BeginString and BodyLength and the BodyLength at the start of the buffer (`None` if incomplete).
+fn header(src: &[u8]) -> Result<Option<(usize, usize)>, Error> {
+	let header     = src.get(..MAX_HEADER_LENGTH.min(src.len())).unwrap_or_default();
+	let incomplete = || if header.len() < MAX_HEADER_LENGTH { Ok(None) } else { Err(invalid("BeginString (8) and BodyLength (9) are too long".to_string())) };
+
+	if !starts_with(header, b"8=") {
+		return Err(invalid("Input does not start with BeginString (8)".to_string()));
+	}
+	let Some(begin_string_end) = memchr::memchr(SOH, header) else {
+		return incomplete();
+	};
+
+	let body_length = header.get(begin_string_end + 1..).unwrap_or_default();
+	if !starts_with(body_length, b"9=") {
+		return Err(invalid("BodyLength (9) does not follow BeginString (8)".to_string()));
+	}
+	let Some(body_length_end) = memchr::memchr(SOH, body_length) else {
+		return incomplete();
+	};
+
+	let value = body_length.get(2..body_length_end).unwrap_or_default();
+	match str::from_utf8(value).ok().filter(|_| value.iter().all(u8::is_ascii_digit)).and_then(|value| value.parse().ok()) {
+		Some(length) => Ok(Some((begin_string_end + 1 + body_length_end + 1, length))),
+		None         => Err(invalid(format!("BodyLength '{}' is not a number", String::from_utf8_lossy(value)))),
+	}
+}
+
+/// Whether the bytes received so far can be the start of `prefix` or start with it.
+fn starts_with(bytes: &[u8], prefix: &[u8]) -> bool {
+	let length = bytes.len().min(prefix.len());
+	bytes.get(..length) == prefix.get(..length)
+}
+
+fn invalid(message: String) -> Error {
+	Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+	use futures_util::{SinkExt, StreamExt};
+	use tokio_util::codec::{Framed, FramedRead};
+
+	use super::*;
+	use crate::parser::{field::Field, tag::Tag};
+
+	fn message(fields: &[(u32, &str)]) -> Message {
+		Message::new(fields.iter().map(|(tag, value)| Field::new(Tag::new(*tag), value.as_bytes().to_vec())).collect())
+	}
+
+	#[tokio::test]
+	async fn messages_over_duplex_stream() {
+		let (client, server) = tokio::io::duplex(64);
+		let mut client       = Framed::new(client, FixCodec::new());
+		let mut server       = Framed::new(server, FixCodec::new());
+
+		let heartbeat = message(&[(8, "FIX.4.4"), (35, "0"), (49, "CLIENT"), (56, "SERVER"), (34, "2")]);
+		let order     = message(&[(8, "FIX.4.4"), (35, "D"), (49, "CLIENT"), (56, "SERVER"), (34, "3"), (58, "longer than the duplex buffer of 64 bytes")]);
+		let expected  = [heartbeat.clone(), order.clone()].map(|message| encoder::complete(message).unwrap());
+
+		// The messages only fit the duplex buffer in parts so they are read while they are written.
+		let send    = async {
+			client.send(heartbeat).await.unwrap();
+			client.send(order).await.unwrap();
+		};
+		let receive = async {
+			let first  = server.next().await.unwrap().unwrap();
+			let second = server.next().await.unwrap().unwrap();
+			[first, second]
+		};
+		let ((), received) = tokio::join!(send, receive);
+		assert_eq!(received, expected);
+	}
+
+	#[tokio::test]
+	async fn garbled_messages() {
+		let inputs: [(&[u8], &str); 4] = [
+			(b"8=FIX.4.4\x019=5\x0135=0\x0110=162\x01", "CheckSum 162 does not match the calculated 163"),
+			(b"8=FIX.4.4\x019=4\x0135=0\x0110=163\x01", "CheckSum (10) is not at the end of the BodyLength 4 message"),
+			(b"8=FIX.4.4\x0135=0\x019=5\x0110=163\x01", "BodyLength (9) does not follow BeginString (8)"),
+			(b"GET / HTTP/1.1\r\n",                       "Input does not start with BeginString (8)"),
+		];
+
+		for (input, error) in inputs {
+			let mut stream = FramedRead::new(input, FixCodec::new());
+			assert_eq!(stream.next().await.unwrap().unwrap_err().to_string(), error);
+		}
+	}
+}
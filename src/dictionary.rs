@@ -4,68 +4,292 @@ use crate::parser::tag::Tag;
 
 pub trait Dictionary: Default {
 	fn tag_name(&self, tag: Tag) -> Option<&str>;
+
+	/// BeginString (8) of the FIX version described (any version when `None`).
+	fn begin_string(&self) -> Option<&str> {
+		None
+	}
+
+	/// Tag of the field with the name.
+	fn tag(&self, _name: &str) -> Option<Tag> {
+		None
+	}
+
+	/// Data type and allowed values of the field.
+	fn field(&self, _tag: Tag) -> Option<&FieldDefinition> {
+		None
+	}
+
+	/// Fields and groups of the message type.
+	fn message(&self, _msg_type: &[u8]) -> Option<&MessageDefinition> {
+		None
+	}
+
+	/// Fields in the standard header (in order).
+	fn header(&self) -> &[Member] {
+		&[]
+	}
+
+	/// Fields in the standard trailer (in order).
+	fn trailer(&self) -> &[Member] {
+		&[]
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldType {
+	/// Int, Length, SeqNum and NumInGroup.
+	Int,
+	/// Float, Qty, Price, PriceOffset, Amt and Percentage.
+	Float,
+	Char,
+	Boolean,
+	/// String and types based on it (e.g. Currency and Exchange).
+	String,
+	UtcTimestamp,
+	LocalMktDate,
+}
+
+#[derive(Debug)]
+pub struct FieldDefinition {
+	pub name:       &'static str,
+	pub field_type: FieldType,
+	/// Allowed values with their descriptions (any value is allowed when empty).
+	pub values:     &'static [(&'static str, &'static str)],
+}
+
+#[derive(Debug)]
+pub enum Member {
+	Field { tag: u32, required: bool },
+	/// Repeating group with the NumInGroup tag and the fields of each instance.
+	/// The first member is the delimiter that starts each instance.
+	Group { count: u32, required: bool, members: &'static [Member] },
 }
 
-/// Dictionary for FIX 5.0.
+impl Member {
+	/// Tag of the field or the NumInGroup tag of the group.
+	#[must_use]
+	pub fn tag(&self) -> u32 {
+		match self {
+			Member::Field { tag, .. }   => *tag,
+			Member::Group { count, .. } => *count,
+		}
+	}
+
+	#[must_use]
+	pub fn required(&self) -> bool {
+		match self {
+			Member::Field { required, .. }
+			| Member::Group { required, .. } => *required,
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct MessageDefinition {
+	pub name:    &'static str,
+	pub members: &'static [Member],
+}
+
+const fn required(tag: u32) -> Member {
+	Member::Field { tag, required: true }
+}
+
+const fn optional(tag: u32) -> Member {
+	Member::Field { tag, required: false }
+}
+
+const HEADER: &[Member] = &[
+	required(8), required(9), required(35), required(49), required(56), optional(115), optional(128),
+	required(34), optional(50), optional(57), optional(43), optional(97), required(52), optional(122),
+];
+
+const TRAILER: &[Member] = &[required(10)];
+
+const PARTIES: Member = Member::Group { count: 453, required: false, members: &[required(448), optional(447), optional(452)] };
+
+const HEARTBEAT:            &[Member] = &[optional(112)];
+const TEST_REQUEST:         &[Member] = &[required(112)];
+const RESEND_REQUEST:       &[Member] = &[required(7), required(16)];
+const REJECT:               &[Member] = &[required(45), optional(371), optional(372), optional(373), optional(58)];
+const SEQUENCE_RESET:       &[Member] = &[optional(123), required(36)];
+const LOGOUT:               &[Member] = &[optional(58)];
+const LOGON:                &[Member] = &[required(98), required(108), optional(141)];
+const NEW_ORDER_SINGLE:     &[Member] = &[
+	required(11), PARTIES, optional(1), optional(21), required(55), optional(48), optional(22), optional(167), optional(207),
+	required(54), required(60), optional(38), required(40), optional(44), optional(59), optional(15), optional(63), optional(64), optional(58),
+];
+const ORDER_CANCEL:         &[Member] = &[
+	required(41), optional(37), required(11), PARTIES, optional(1), required(55), optional(48), optional(22),
+	required(54), required(60), optional(38), optional(58),
+];
+const ORDER_CANCEL_REPLACE: &[Member] = &[
+	optional(37), required(41), required(11), PARTIES, optional(1), optional(21), required(55), optional(48), optional(22),
+	required(54), required(60), optional(38), required(40), optional(44), optional(59), optional(15), optional(58),
+];
+const EXECUTION_REPORT:     &[Member] = &[
+	required(37), optional(11), optional(41), required(17), optional(19), required(150), required(39), optional(103), PARTIES,
+	optional(1), required(55), optional(48), optional(22), optional(167), optional(207), required(54), optional(38), optional(40),
+	optional(44), optional(59), optional(32), optional(31), optional(30), required(151), required(14), required(6), optional(75),
+	optional(60), optional(12), optional(13), optional(15), optional(58),
+];
+const ORDER_CANCEL_REJECT:  &[Member] = &[
+	required(37), required(11), required(41), required(39), optional(1), optional(60), required(434), optional(102), optional(58),
+];
+
+const BOOLEAN:             &[(&str, &str)] = &[("Y", "Yes"), ("N", "No")];
+const COMM_TYPE:           &[(&str, &str)] = &[("1", "PerUnit"), ("2", "Percent"), ("3", "Absolute")];
+const HANDL_INST:          &[(&str, &str)] = &[("1", "AutomatedExecutionNoIntervention"), ("2", "AutomatedExecutionInterventionOK"), ("3", "ManualOrder")];
+const ORD_STATUS:          &[(&str, &str)] = &[
+	("0", "New"), ("1", "PartiallyFilled"), ("2", "Filled"), ("3", "DoneForDay"), ("4", "Canceled"), ("5", "Replaced"),
+	("6", "PendingCancel"), ("7", "Stopped"), ("8", "Rejected"), ("9", "Suspended"), ("A", "PendingNew"), ("B", "Calculated"),
+	("C", "Expired"), ("D", "AcceptedForBidding"), ("E", "PendingReplace"),
+];
+const ORD_TYPE:            &[(&str, &str)] = &[("1", "Market"), ("2", "Limit"), ("3", "Stop"), ("4", "StopLimit"), ("P", "Pegged")];
+const SIDE:                &[(&str, &str)] = &[("1", "Buy"), ("2", "Sell"), ("3", "BuyMinus"), ("4", "SellPlus"), ("5", "SellShort"), ("6", "SellShortExempt")];
+const TIME_IN_FORCE:       &[(&str, &str)] = &[
+	("0", "Day"), ("1", "GoodTillCancel"), ("2", "AtTheOpening"), ("3", "ImmediateOrCancel"), ("4", "FillOrKill"),
+	("5", "GoodTillCrossing"), ("6", "GoodTillDate"), ("7", "AtTheClose"),
+];
+const ENCRYPT_METHOD:      &[(&str, &str)] = &[("0", "None")];
+const EXEC_TYPE:           &[(&str, &str)] = &[
+	("0", "New"), ("3", "DoneForDay"), ("4", "Canceled"), ("5", "Replaced"), ("6", "PendingCancel"), ("7", "Stopped"),
+	("8", "Rejected"), ("9", "Suspended"), ("A", "PendingNew"), ("B", "Calculated"), ("C", "Expired"), ("D", "Restated"),
+	("E", "PendingReplace"), ("F", "Trade"), ("G", "TradeCorrect"), ("H", "TradeCancel"), ("I", "OrderStatus"),
+];
+const CXL_REJ_RESPONSE_TO: &[(&str, &str)] = &[("1", "OrderCancelRequest"), ("2", "OrderCancelReplaceRequest")];
+
+/// Dictionary for FIX 4.4: the fields, and the session messages and the core order messages.
 /// (Other dictionaries should be generated from FIX specs. This is future work.)
 #[derive(Debug)]
 pub struct BaseDictionary {
-	map: HashMap<Tag, String>,
+	fields:   HashMap<Tag, FieldDefinition>,
+	messages: HashMap<&'static [u8], MessageDefinition>,
 }
 
 impl Default for BaseDictionary {
 	fn default() -> Self {
 		let mut map = HashMap::new();
 
-		insert(&mut map,   1, "Account");
-		insert(&mut map,   6, "AvgPx");
-		insert(&mut map,   8, "BeginString");
-		insert(&mut map,   9, "BodyLength");
-		insert(&mut map,  10, "CheckSum");
-		insert(&mut map,  11, "ClOrdID");
-		insert(&mut map,  12, "Commission");
-		insert(&mut map,  13, "CommType");
-		insert(&mut map,  14, "CumQty");
-		insert(&mut map,  15, "Currency");
-		insert(&mut map,  17, "ExecID");
-		insert(&mut map,  21, "HandlInst");
-		insert(&mut map,  22, "IDSource");
-		insert(&mut map,  30, "LastMkt");
-		insert(&mut map,  31, "LastPx");
-		insert(&mut map,  32, "LastQty");
-		insert(&mut map,  34, "MsgSeqNum");
-		insert(&mut map,  35, "MsgType");
-		insert(&mut map,  37, "OrderID");
-		insert(&mut map,  38, "OrderQty");
-		insert(&mut map,  39, "OrdStatus");
-		insert(&mut map,  40, "OrdType");
-		insert(&mut map,  44, "Price");
-		insert(&mut map,  48, "SecurityID");
-		insert(&mut map,  49, "SenderCompID");
-		insert(&mut map,  50, "SenderSubID");
-		insert(&mut map,  52, "SendingTime");
-		insert(&mut map,  54, "Side");
-		insert(&mut map,  55, "Symbol");
-		insert(&mut map,  56, "TargetCompID");
-		insert(&mut map,  57, "TargetSubID");
-		insert(&mut map,  59, "TimeInForce");
-		insert(&mut map,  60, "TransactTime");
-		insert(&mut map,  63, "SettlmntTyp");
-		insert(&mut map, 115, "OnBehalfOfCompID");
-		insert(&mut map, 167, "SecurityType");
-		insert(&mut map, 207, "SecurityExchange");
-
-		Self { map }
+		insert(&mut map,   1, "Account",             FieldType::String,       &[]);
+		insert(&mut map,   6, "AvgPx",               FieldType::Float,        &[]);
+		insert(&mut map,   7, "BeginSeqNo",          FieldType::Int,          &[]);
+		insert(&mut map,   8, "BeginString",         FieldType::String,       &[]);
+		insert(&mut map,   9, "BodyLength",          FieldType::Int,          &[]);
+		insert(&mut map,  10, "CheckSum",            FieldType::String,       &[]);
+		insert(&mut map,  11, "ClOrdID",             FieldType::String,       &[]);
+		insert(&mut map,  12, "Commission",          FieldType::Float,        &[]);
+		insert(&mut map,  13, "CommType",            FieldType::Char,         COMM_TYPE);
+		insert(&mut map,  14, "CumQty",              FieldType::Float,        &[]);
+		insert(&mut map,  15, "Currency",            FieldType::String,       &[]);
+		insert(&mut map,  16, "EndSeqNo",            FieldType::Int,          &[]);
+		insert(&mut map,  17, "ExecID",              FieldType::String,       &[]);
+		insert(&mut map,  19, "ExecRefID",           FieldType::String,       &[]);
+		insert(&mut map,  21, "HandlInst",           FieldType::Char,         HANDL_INST);
+		insert(&mut map,  22, "IDSource",            FieldType::String,       &[]);
+		insert(&mut map,  30, "LastMkt",             FieldType::String,       &[]);
+		insert(&mut map,  31, "LastPx",              FieldType::Float,        &[]);
+		insert(&mut map,  32, "LastQty",             FieldType::Float,        &[]);
+		insert(&mut map,  34, "MsgSeqNum",           FieldType::Int,          &[]);
+		insert(&mut map,  35, "MsgType",             FieldType::String,       &[]);
+		insert(&mut map,  36, "NewSeqNo",            FieldType::Int,          &[]);
+		insert(&mut map,  37, "OrderID",             FieldType::String,       &[]);
+		insert(&mut map,  38, "OrderQty",            FieldType::Float,        &[]);
+		insert(&mut map,  39, "OrdStatus",           FieldType::Char,         ORD_STATUS);
+		insert(&mut map,  40, "OrdType",             FieldType::Char,         ORD_TYPE);
+		insert(&mut map,  41, "OrigClOrdID",         FieldType::String,       &[]);
+		insert(&mut map,  43, "PossDupFlag",         FieldType::Boolean,      BOOLEAN);
+		insert(&mut map,  44, "Price",               FieldType::Float,        &[]);
+		insert(&mut map,  45, "RefSeqNum",           FieldType::Int,          &[]);
+		insert(&mut map,  48, "SecurityID",          FieldType::String,       &[]);
+		insert(&mut map,  49, "SenderCompID",        FieldType::String,       &[]);
+		insert(&mut map,  50, "SenderSubID",         FieldType::String,       &[]);
+		insert(&mut map,  52, "SendingTime",         FieldType::UtcTimestamp, &[]);
+		insert(&mut map,  54, "Side",                FieldType::Char,         SIDE);
+		insert(&mut map,  55, "Symbol",              FieldType::String,       &[]);
+		insert(&mut map,  56, "TargetCompID",        FieldType::String,       &[]);
+		insert(&mut map,  57, "TargetSubID",         FieldType::String,       &[]);
+		insert(&mut map,  58, "Text",                FieldType::String,       &[]);
+		insert(&mut map,  59, "TimeInForce",         FieldType::Char,         TIME_IN_FORCE);
+		insert(&mut map,  60, "TransactTime",        FieldType::UtcTimestamp, &[]);
+		insert(&mut map,  63, "SettlmntTyp",         FieldType::String,       &[]);
+		insert(&mut map,  64, "SettlDate",           FieldType::LocalMktDate, &[]);
+		insert(&mut map,  75, "TradeDate",           FieldType::LocalMktDate, &[]);
+		insert(&mut map,  97, "PossResend",          FieldType::Boolean,      BOOLEAN);
+		insert(&mut map,  98, "EncryptMethod",       FieldType::Int,          ENCRYPT_METHOD);
+		insert(&mut map, 102, "CxlRejReason",        FieldType::Int,          &[]);
+		insert(&mut map, 103, "OrdRejReason",        FieldType::Int,          &[]);
+		insert(&mut map, 108, "HeartBtInt",          FieldType::Int,          &[]);
+		insert(&mut map, 109, "ClientID",            FieldType::String,       &[]);
+		insert(&mut map, 112, "TestReqID",           FieldType::String,       &[]);
+		insert(&mut map, 115, "OnBehalfOfCompID",    FieldType::String,       &[]);
+		insert(&mut map, 122, "OrigSendingTime",     FieldType::UtcTimestamp, &[]);
+		insert(&mut map, 123, "GapFillFlag",         FieldType::Boolean,      BOOLEAN);
+		insert(&mut map, 128, "DeliverToCompID",     FieldType::String,       &[]);
+		insert(&mut map, 141, "ResetSeqNumFlag",     FieldType::Boolean,      BOOLEAN);
+		insert(&mut map, 150, "ExecType",            FieldType::Char,         EXEC_TYPE);
+		insert(&mut map, 151, "LeavesQty",           FieldType::Float,        &[]);
+		insert(&mut map, 167, "SecurityType",        FieldType::String,       &[]);
+		insert(&mut map, 207, "SecurityExchange",    FieldType::String,       &[]);
+		insert(&mut map, 371, "RefTagID",            FieldType::Int,          &[]);
+		insert(&mut map, 372, "RefMsgType",          FieldType::String,       &[]);
+		insert(&mut map, 373, "SessionRejectReason", FieldType::Int,          &[]);
+		insert(&mut map, 434, "CxlRejResponseTo",    FieldType::Char,         CXL_REJ_RESPONSE_TO);
+		insert(&mut map, 447, "PartyIDSource",       FieldType::Char,         &[]);
+		insert(&mut map, 448, "PartyID",             FieldType::String,       &[]);
+		insert(&mut map, 452, "PartyRole",           FieldType::Int,          &[]);
+		insert(&mut map, 453, "NoPartyIDs",          FieldType::Int,          &[]);
+
+		let messages = HashMap::from([
+			(&b"0"[..], MessageDefinition { name: "Heartbeat",                 members: HEARTBEAT }),
+			(b"1",      MessageDefinition { name: "TestRequest",               members: TEST_REQUEST }),
+			(b"2",      MessageDefinition { name: "ResendRequest",             members: RESEND_REQUEST }),
+			(b"3",      MessageDefinition { name: "Reject",                    members: REJECT }),
+			(b"4",      MessageDefinition { name: "SequenceReset",             members: SEQUENCE_RESET }),
+			(b"5",      MessageDefinition { name: "Logout",                    members: LOGOUT }),
+			(b"8",      MessageDefinition { name: "ExecutionReport",           members: EXECUTION_REPORT }),
+			(b"9",      MessageDefinition { name: "OrderCancelReject",         members: ORDER_CANCEL_REJECT }),
+			(b"A",      MessageDefinition { name: "Logon",                     members: LOGON }),
+			(b"D",      MessageDefinition { name: "NewOrderSingle",            members: NEW_ORDER_SINGLE }),
+			(b"F",      MessageDefinition { name: "OrderCancelRequest",        members: ORDER_CANCEL }),
+			(b"G",      MessageDefinition { name: "OrderCancelReplaceRequest", members: ORDER_CANCEL_REPLACE }),
+		]);
+
+		Self { fields: map, messages }
 	}
 }
 
-fn insert(map: &mut HashMap<Tag, String>, tag_num: u32, tag_name: &'static str) {
-	let tag = Tag::try_from(tag_num).expect(&format!("Should be a valid tag {}", tag_num));
-	map.insert(tag, tag_name.to_string());
+fn insert(map: &mut HashMap<Tag, FieldDefinition>, tag_num: u32, tag_name: &'static str, field_type: FieldType, values: &'static [(&'static str, &'static str)]) {
+	let tag = Tag::try_from(tag_num).unwrap_or_else(|_| panic!("Should be a valid tag {}", tag_num));
+	map.insert(tag, FieldDefinition { name: tag_name, field_type, values });
 }
 
 impl Dictionary for BaseDictionary {
 	fn tag_name(&self, tag: Tag) -> Option<&str> {
-		self.map.get(&tag).map(|s| s.as_str())
+		self.fields.get(&tag).map(|field| field.name)
+	}
+
+	fn begin_string(&self) -> Option<&str> {
+		Some("FIX.4.4")
+	}
+
+	fn tag(&self, name: &str) -> Option<Tag> {
+		self.fields.iter().find(|(_, field)| field.name == name).map(|(tag, _)| *tag)
+	}
+
+	fn field(&self, tag: Tag) -> Option<&FieldDefinition> {
+		self.fields.get(&tag)
+	}
+
+	fn message(&self, msg_type: &[u8]) -> Option<&MessageDefinition> {
+		self.messages.get(msg_type)
+	}
+
+	fn header(&self) -> &[Member] {
+		HEADER
+	}
+
+	fn trailer(&self) -> &[Member] {
+		TRAILER
 	}
 }
@@ -0,0 +1,299 @@
+//! Module for comparing two messages field by field.
+//! Fields are lined up by tag and repeating groups (from the message definition in the dictionary)
+//! are compared instance by instance.
+
+use std::{collections::BTreeMap, io::{Error, ErrorKind, Write}, iter::Peekable};
+
+use crate::{args::{Args, DiffArgs}, dictionary::{Dictionary, Member}, filter::Filter, input, parser::{field::Field, message::Message}};
+
+/// Field with the instances of its repeating group if it is a NumInGroup field.
+#[derive(Debug)]
+struct Node<'a> {
+	field:     &'a Field,
+	instances: Vec<Vec<Node<'a>>>,
+}
+
+impl Node<'_> {
+	fn tag(&self) -> u32 {
+		self.field.tag().number()
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Change {
+	Added,
+	Removed,
+	Changed,
+}
+
+impl Change {
+	fn marker(self) -> char {
+		match self {
+			Change::Added   => '+',
+			Change::Removed => '-',
+			Change::Changed => '~',
+		}
+	}
+}
+
+/// Compares two messages with names and enum values from the dictionary.
+#[derive(Debug)]
+pub struct Differ<'d, D: Dictionary, F: Filter> {
+	dictionary: &'d D,
+	filter:     F,
+	show_all:   bool,
+	lines:      Vec<String>,
+	changes:    BTreeMap<char, usize>,
+	width:      usize,
+}
+
+impl<'d, D: Dictionary, F: Filter> Differ<'d, D, F> {
+	/// With `show_all` false the fields hidden by the filter (e.g. BodyLength and CheckSum) are not compared.
+	pub fn new(dictionary: &'d D, show_all: bool) -> Self {
+		Self { dictionary, filter: F::default(), show_all, lines: Vec::new(), changes: BTreeMap::new(), width: 0 }
+	}
+
+	/// Writes the fields that were added, removed or changed from `left` to `right`.
+	pub fn write(mut self, left: (usize, &Message), right: (usize, &Message), output: &mut impl Write) -> Result<(), Error> {
+		self.width = left.1.into_iter().chain(right.1)
+			.filter_map(|field| self.dictionary.tag_name(field.tag()).map(str::len))
+			.max()
+			.unwrap_or(0);
+
+		let (left_nodes, right_nodes) = (self.nodes(left.1), self.nodes(right.1));
+		self.diff(&left_nodes, &right_nodes, 0);
+
+		writeln!(output, "- Message {}{}", left.0, self.msg_type(left.1))?;
+		writeln!(output, "+ Message {}{}", right.0, self.msg_type(right.1))?;
+		for line in &self.lines {
+			writeln!(output, "{}", line)?;
+		}
+
+		if self.changes.is_empty() {
+			return writeln!(output, "Messages are identical");
+		}
+		let count = |change: Change| self.changes.get(&change.marker()).copied().unwrap_or_default();
+		writeln!(output, "{} added, {} removed, {} changed", count(Change::Added), count(Change::Removed), count(Change::Changed))
+	}
+
+	fn msg_type(&self, message: &Message) -> String {
+		let Some(msg_type) = message.msg_type() else {
+			return String::new();
+		};
+		match self.dictionary.message(msg_type) {
+			Some(definition) => format!(" (MsgType {} {})", String::from_utf8_lossy(msg_type), definition.name),
+			None             => format!(" (MsgType {})", String::from_utf8_lossy(msg_type)),
+		}
+	}
+
+	/// Splits the fields of the message in fields and repeating groups.
+	fn nodes<'m>(&self, message: &'m Message) -> Vec<Node<'m>> {
+		let members = message.msg_type()
+			.and_then(|msg_type| self.dictionary.message(msg_type))
+			.map_or(&[][..], |definition| definition.members);
+
+		let mut fields = message.into_iter().filter(|field| self.show_all || self.filter.relevant(field.tag())).peekable();
+		let mut nodes  = Vec::new();
+		while let Some(field) = fields.next() {
+			nodes.push(node(field, &mut fields, members));
+		}
+		nodes
+	}
+
+	/// Compares the nodes with the same tag in the order they occur.
+	fn diff(&mut self, left: &[Node], right: &[Node], depth: usize) {
+		let mut tags = BTreeMap::<u32, (Vec<&Node>, Vec<&Node>)>::new();
+		for node in left {
+			tags.entry(node.tag()).or_default().0.push(node);
+		}
+		for node in right {
+			tags.entry(node.tag()).or_default().1.push(node);
+		}
+
+		for (left, right) in tags.into_values() {
+			for index in 0..left.len().max(right.len()) {
+				let (left, right) = (left.get(index).copied(), right.get(index).copied());
+				match (left, right) {
+					(Some(left), Some(right)) if left.field.value_bytes() == right.field.value_bytes() => {}
+					(Some(left), Some(right)) => {
+						let value = format!("{} -> {}", self.value(left.field), self.value(right.field));
+						self.line(Change::Changed, left.field, &value, depth);
+					}
+					(Some(left), None)        => self.line(Change::Removed, left.field, &self.value(left.field), depth),
+					(None, Some(right))       => self.line(Change::Added, right.field, &self.value(right.field), depth),
+					(None, None)              => {}
+				}
+
+				// Groups are compared instance by instance.
+				let instances = |node: Option<&Node>| node.map_or(0, |node| node.instances.len());
+				for instance in 0..instances(left).max(instances(right)) {
+					let start = self.lines.len();
+					self.diff(instance_of(left, instance), instance_of(right, instance), depth + 1);
+					if self.lines.len() > start {
+						let count = left.or(right).map(|node| node.field.tag());
+						let name  = count.and_then(|tag| self.dictionary.tag_name(tag)).map_or_else(|| count.map(|tag| tag.to_string()).unwrap_or_default(), str::to_string);
+						self.lines.insert(start, format!("  {}{} #{}", "    ".repeat(depth + 1), name, instance + 1));
+					}
+				}
+			}
+		}
+	}
+
+	fn line(&mut self, change: Change, field: &Field, value: &str, depth: usize) {
+		let indent = "    ".repeat(depth);
+		let line   = match self.dictionary.tag_name(field.tag()) {
+			Some(name) => format!("{} {}{:>6} : {:<width$} = {}", change.marker(), indent, field.tag().number(), name, value, width = self.width),
+			None       => format!("{} {}{:>6}   {:>width$} = {}", change.marker(), indent, field.tag().number(), "", value, width = self.width),
+		};
+		self.lines.push(line);
+		*self.changes.entry(change.marker()).or_default() += 1;
+	}
+
+	/// Value of the field with the description of the enum value (e.g. `1 (Buy)`).
+	fn value(&self, field: &Field) -> String {
+		let value       = String::from_utf8_lossy(field.value_bytes()).into_owned();
+		let description = self.dictionary.field(field.tag())
+			.and_then(|definition| definition.values.iter().find(|(allowed, _)| *allowed == value))
+			.map(|(_, description)| *description);
+		match description {
+			Some(description) => format!("{} ({})", value, description),
+			None              => value,
+		}
+	}
+}
+
+/// Fields of the instance of the group (empty if the node has fewer instances).
+fn instance_of<'n, 'm>(node: Option<&'n Node<'m>>, instance: usize) -> &'n [Node<'m>] {
+	node.and_then(|node| node.instances.get(instance)).map_or(&[], Vec::as_slice)
+}
+
+/// Field with the instances of its group if `members` has a group starting with the field.
+fn node<'m>(field: &'m Field, fields: &mut Peekable<impl Iterator<Item = &'m Field>>, members: &[Member]) -> Node<'m> {
+	let group = members.iter().find_map(|member| match member {
+		Member::Group { count, members, .. } if *count == field.tag().number() => Some(*members),
+		Member::Group { .. }
+		| Member::Field { .. } => None,
+	});
+	let Some(group) = group else {
+		return Node { field, instances: Vec::new() };
+	};
+
+	// Each instance starts with the delimiter (the first member) and lasts until the next delimiter
+	// or a field that is not a member of the group.
+	let delimiter     = group.first().map(Member::tag);
+	let in_group      = |field: &&Field| group.iter().any(|member| member.tag() == field.tag().number());
+	let mut instances = Vec::new();
+	while let Some(first) = fields.next_if(|field| Some(field.tag().number()) == delimiter) {
+		let mut instance = vec![node(first, fields, group)];
+		while let Some(field) = fields.next_if(|field| Some(field.tag().number()) != delimiter && in_group(field)) {
+			instance.push(node(field, fields, group));
+		}
+		instances.push(instance);
+	}
+	Node { field, instances }
+}
+
+/// Compares two of the messages in the input (the first two unless others are chosen).
+pub fn run<D: Dictionary, F: Filter>(args: &Args, diff_args: &DiffArgs, output: &mut impl Write) -> Result<(), Error> {
+	let (left, right) = match diff_args.messages.as_slice() {
+		[]            => (1, 2),
+		[left, right] => (*left, *right),
+		_             => return Err(Error::new(ErrorKind::InvalidInput, "Choose two messages to compare (e.g. --messages 1,3)")),
+	};
+
+	let input        = Args { files: diff_args.files.clone(), ..args.clone() };
+	let mut position = 0;
+	let mut chosen   = (None, None);
+	input::messages(&input, |message, _| {
+		position += 1;
+		if position == left {
+			chosen.0 = Some(message.clone());
+		}
+		if position == right {
+			chosen.1 = Some(message);
+		}
+	})?;
+
+	let (Some(left_message), Some(right_message)) = chosen else {
+		return Err(Error::new(ErrorKind::InvalidInput, format!("Found {} message{} but messages {} and {} were chosen", position, if position == 1 { "" } else { "s" }, left, right)));
+	};
+
+	let dictionary = D::default();
+	Differ::<D, F>::new(&dictionary, diff_args.show_all_fields).write((left, &left_message), (right, &right_message), output)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{dictionary::BaseDictionary, filter::BaseFilter, parser::scanner::{Scanned, Scanner}};
+
+	fn diff(input: &str, show_all: bool) -> String {
+		let messages: Vec<Message> = Scanner::new(input.as_bytes(), b'|')
+			.filter_map(|scanned| match scanned {
+				Scanned::Message(message) => Some(message.into_owned()),
+				Scanned::Passthrough(_)
+				| Scanned::Invalid(_)     => None,
+			})
+			.collect();
+		let (Some(left), Some(right)) = (messages.first(), messages.get(1)) else {
+			panic!("Should be two messages");
+		};
+
+		let dictionary = BaseDictionary::default();
+		let mut output = Vec::new();
+		Differ::<BaseDictionary, BaseFilter>::new(&dictionary, show_all).write((1, left), (2, right), &mut output).unwrap();
+		String::from_utf8(output).unwrap()
+	}
+
+	#[test]
+	fn changed_fields() {
+		let input = "\
+			8=FIX.4.4|9=5|35=D|11=A1|55=VOD.L|54=1|38=100|58=first|5001=X|10=000|\n\
+			8=FIX.4.4|9=6|35=G|11=A2|41=A1|55=VOD.L|54=2|38=100|5001=Y|10=001|\n";
+
+		insta::assert_snapshot!(diff(input, false), @r"
+		- Message 1 (MsgType D NewOrderSingle)
+		+ Message 2 (MsgType G OrderCancelReplaceRequest)
+		~     11 : ClOrdID     = A1 -> A2
+		~     35 : MsgType     = D -> G
+		+     41 : OrigClOrdID = A1
+		~     54 : Side        = 1 (Buy) -> 2 (Sell)
+		-     58 : Text        = first
+		~   5001               = X -> Y
+		1 added, 1 removed, 4 changed
+		");
+	}
+
+	#[test]
+	fn groups_instance_by_instance() {
+		let input = "\
+			8=FIX.4.4|9=5|35=D|11=A1|453=2|448=P1|447=D|452=1|448=P2|447=D|452=3|55=VOD.L|10=000|\n\
+			8=FIX.4.4|9=5|35=D|11=A1|453=3|448=P1|447=D|452=1|448=P3|447=D|452=3|448=P4|55=VOD.L|10=000|\n";
+
+		insta::assert_snapshot!(diff(input, false), @r"
+		- Message 1 (MsgType D NewOrderSingle)
+		+ Message 2 (MsgType D NewOrderSingle)
+		~    453 : NoPartyIDs    = 2 -> 3
+		      NoPartyIDs #2
+		~        448 : PartyID       = P2 -> P3
+		      NoPartyIDs #3
+		+        448 : PartyID       = P4
+		1 added, 0 removed, 2 changed
+		");
+	}
+
+	#[test]
+	fn identical_messages() {
+		let input = "\
+			8=FIX.4.4|9=5|35=0|10=000|\n\
+			8=FIX.4.4|9=6|35=0|10=001|\n";
+
+		// BodyLength and CheckSum are only compared with `show_all`.
+		assert!(diff(input, true).ends_with("0 added, 0 removed, 2 changed\n"));
+		insta::assert_snapshot!(diff(input, false), @r"
+		- Message 1 (MsgType 0 Heartbeat)
+		+ Message 2 (MsgType 0 Heartbeat)
+		Messages are identical
+		");
+	}
+}
@@ -0,0 +1,167 @@
+//! Module for encoding messages as FIX tag=value (the reverse of parsing).
+//! BodyLength (9) and CheckSum (10) are calculated when the message is completed so the
+//! encoded message is well-formed.
+
+use std::{fmt, io::{Error, ErrorKind}};
+
+use crate::{dictionary::{Dictionary, Member}, parser::{field::Field, message::Message, state::CHECK_SUM, tag::Tag}};
+
+const BEGIN_STRING: u32 = 8;
+const BODY_LENGTH:  u32 = 9;
+const MSG_TYPE:     u32 = 35;
+
+/// Message that cannot be completed because a header field is missing.
+/// The message is handed back as it was.
+#[derive(Debug)]
+pub struct Incomplete {
+	pub message: Message,
+	missing:     &'static str,
+}
+
+impl fmt::Display for Incomplete {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} is missing", self.missing)
+	}
+}
+
+impl std::error::Error for Incomplete {}
+
+impl From<Incomplete> for Error {
+	fn from(incomplete: Incomplete) -> Self {
+		Error::new(ErrorKind::InvalidInput, incomplete.to_string())
+	}
+}
+
+/// Orders BeginString, BodyLength and MsgType first and calculates BodyLength and CheckSum.
+/// Any existing BodyLength and CheckSum are replaced.
+pub fn complete(message: Message) -> Result<Message, Incomplete> {
+	let message = check(message)?;
+
+	let mut begin_string = None;
+	let mut msg_type     = None;
+	let mut body         = Vec::new();
+
+	for field in message {
+		match field.tag().number() {
+			BEGIN_STRING            => begin_string = Some(field),
+			MSG_TYPE                => msg_type     = Some(field),
+			BODY_LENGTH | CHECK_SUM => {}
+			_                       => body.push(field),
+		}
+	}
+
+	// BodyLength is calculated from the fields after it, so a placeholder is enough to count them.
+	let fields: Vec<Field> = begin_string.into_iter().chain([field(BODY_LENGTH, "0")]).chain(msg_type).chain(body).collect();
+	let message     = Message::new(fields);
+	let body_length = message.body_length();
+
+	let mut fields: Vec<Field> = message.into_iter().collect();
+	if let Some(placeholder) = fields.get_mut(1) {
+		*placeholder = field(BODY_LENGTH, &body_length.to_string());
+	}
+
+	let mut message = Message::new(fields);
+	let check_sum   = message.check_sum();
+	message.push(field(CHECK_SUM, &format!("{:03}", check_sum)));
+
+	Ok(message)
+}
+
+/// Puts the header fields first and the trailer fields last (both in the order of the dictionary)
+/// and completes the message. Body fields keep their order.
+pub fn normalize(message: Message, dictionary: &impl Dictionary) -> Result<Message, Incomplete> {
+	let message  = check(message)?;
+	let position = |members: &[Member], tag| members.iter().position(|member| member.tag() == tag);
+
+	let mut fields: Vec<Field> = message.into_iter().collect();
+	fields.sort_by_key(|field| {
+		let tag = field.tag().number();
+		match (position(dictionary.header(), tag), position(dictionary.trailer(), tag)) {
+			(Some(index), _)    => (0, index),
+			(None, Some(index)) => (2, index),
+			(None, None)        => (1, 0),
+		}
+	});
+
+	complete(Message::new(fields))
+}
+
+/// Encodes the fields of the message with the delimiter.
+#[must_use]
+pub fn encode(message: &Message, delimiter: u8) -> Vec<u8> {
+	let mut bytes = Vec::new();
+	for field in message {
+		bytes.extend(field.bytes());
+		bytes.push(delimiter);
+	}
+	bytes
+}
+
+/// Hands the message back in the error if BeginString or MsgType is missing.
+fn check(message: Message) -> Result<Message, Incomplete> {
+	let has     = |tag| (&message).into_iter().any(|field| field.tag().number() == tag);
+	let missing = if !has(BEGIN_STRING) {
+		"BeginString (8)"
+	}
+	else if !has(MSG_TYPE) {
+		"MsgType (35)"
+	}
+	else {
+		return Ok(message);
+	};
+	Err(Incomplete { message, missing })
+}
+
+fn field(tag: u32, value: &str) -> Field {
+	let tag = Tag::try_from(tag).unwrap_or_else(|_| panic!("Should be a valid tag {}", tag));
+	Field::new(tag, value.as_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{dictionary::BaseDictionary, parser::{field::SOH, scanner::{Scanned, Scanner}}};
+
+	#[test]
+	fn complete_and_encode() {
+		let fields  = [(35, "A"), (8, "FIX.4.2"), (49, "SERVER"), (56, "CLIENT"), (34, "177"), (9, "1"), (52, "20090107-18:15:16"), (98, "0"), (108, "30"), (10, "1")];
+		let message = Message::new(fields.iter().map(|(tag, value)| field(*tag, value)).collect());
+
+		let encoded = encode(&complete(message).unwrap(), b'|');
+
+		assert_eq!(String::from_utf8(encoded).unwrap(), "8=FIX.4.2|9=65|35=A|49=SERVER|56=CLIENT|34=177|52=20090107-18:15:16|98=0|108=30|10=062|");
+	}
+
+	#[test]
+	fn encoded_message_is_parsed() {
+		let message = Message::new(vec![field(8, "FIX.4.4"), field(35, "0")]);
+		let encoded = encode(&complete(message).unwrap(), SOH);
+
+		let Some(Scanned::Message(parsed)) = Scanner::new(&encoded, SOH).next() else {
+			panic!("Should be a message");
+		};
+		assert_eq!(encode(&parsed.into_owned(), SOH), encoded);
+	}
+
+	#[test]
+	fn normalize_header_and_trailer() {
+		let fields  = [(8, "FIX.4.2"), (9, "1"), (35, "A"), (98, "0"), (34, "177"), (108, "30"), (10, "1"), (52, "20090107-18:15:16"), (56, "CLIENT"), (49, "SERVER")];
+		let message = Message::new(fields.iter().map(|(tag, value)| field(*tag, value)).collect());
+
+		let encoded = encode(&normalize(message, &BaseDictionary::default()).unwrap(), b'|');
+
+		assert_eq!(String::from_utf8(encoded).unwrap(), "8=FIX.4.2|9=65|35=A|49=SERVER|56=CLIENT|34=177|52=20090107-18:15:16|98=0|108=30|10=062|");
+	}
+
+	#[test]
+	fn missing_msg_type() {
+		let message    = Message::new(vec![field(8, "FIX.4.4"), field(58, "note")]);
+		let incomplete = complete(message).unwrap_err();
+		assert_eq!(incomplete.to_string(), "MsgType (35) is missing");
+		assert_eq!(encode(&incomplete.message, b'|'), b"8=FIX.4.4|58=note|");
+
+		let message = Message::new(vec![field(35, "0"), field(8, "FIX.4.4"), field(10, "1")]);
+		assert_eq!(encode(&normalize(Message::new(vec![field(35, "0")]), &BaseDictionary::default()).unwrap_err().message, b'|'), b"35=0|");
+		assert_eq!(encode(&normalize(message, &BaseDictionary::default()).unwrap(), b'|'), b"8=FIX.4.4|9=5|35=0|10=163|");
+	}
+}
@@ -1,7 +1,14 @@
-use crate::parser::tag::Tag;
+use crate::parser::{field::FieldRef, message::Fields, tag::Tag};
 
 pub trait Filter: Default {
 	fn relevant(&self, tag: Tag) -> bool;
+
+	/// Fields of the message that are relevant.
+	fn relevant_fields<'m>(&self, message: &'m impl Fields) -> impl Iterator<Item = FieldRef<'m>> {
+		message
+			.fields()
+			.filter(|f| self.relevant(f.tag()))
+	}
 }
 
 #[derive(Debug, Default)]
@@ -10,6 +17,6 @@ pub struct BaseFilter;
 impl Filter for BaseFilter {
 	fn relevant(&self, tag: Tag) -> bool {
 		// Expand on this.
-		!matches!(tag.number(), 8 | 9 | 10)
+		!matches!(tag.number(), 8..=10)
 	}
 }
@@ -0,0 +1,161 @@
+//! Module for following a file that is being appended to - similar to `tail -F`.
+//! The `Follow` reader never reaches end of input. When there is no more data it waits
+//! for the file to grow and reopens the file if it has been rotated or truncated.
+
+use std::{fs::{File, Metadata}, io::{Read, Seek, SeekFrom}, path::{Path, PathBuf}, thread, time::Duration};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+pub struct Follow {
+	path:     PathBuf,
+	file:     File,
+	identity: Option<(u64, u64)>,
+	position: u64,
+}
+
+impl Follow {
+	/// Opens the file and starts following from the current end of the file.
+	pub fn open(path: &Path) -> std::io::Result<Self> {
+		let mut file = File::open(path)?;
+		let position = file.seek(SeekFrom::End(0))?;
+		let identity = identity(&file.metadata()?);
+
+		Ok(Self {
+			path: path.to_path_buf(),
+			file,
+			identity,
+			position,
+		})
+	}
+
+	/// Reads whatever data is available without waiting.
+	/// Returns `Ok(0)` if there is currently no new data.
+	fn read_available(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+		let count = self.file.read(buffer)?;
+		if count > 0 {
+			self.position += count as u64;
+			return Ok(count);
+		}
+
+		// No more data in the current file - check if it has been rotated or truncated.
+		// (The file might be missing for a short while during rotation.)
+		let Ok(metadata) = std::fs::metadata(&self.path) else {
+			return Ok(0);
+		};
+
+		if identity(&metadata) != self.identity {
+			// Rotated - the old file has been read to the end so switch to the new file.
+			self.file     = File::open(&self.path)?;
+			self.identity = identity(&self.file.metadata()?);
+			self.position = 0;
+		}
+		else if metadata.len() < self.position {
+			// Truncated - start over from the beginning.
+			self.position = self.file.seek(SeekFrom::Start(0))?;
+		}
+		else {
+			return Ok(0);
+		}
+
+		let count = self.file.read(buffer)?;
+		self.position += count as u64;
+		Ok(count)
+	}
+}
+
+impl Read for Follow {
+	fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+		if buffer.is_empty() {
+			return Ok(0);
+		}
+
+		loop {
+			let count = self.read_available(buffer)?;
+			if count > 0 {
+				return Ok(count);
+			}
+			thread::sleep(POLL_INTERVAL);
+		}
+	}
+}
+
+#[cfg(unix)]
+fn identity(metadata: &Metadata) -> Option<(u64, u64)> {
+	use std::os::unix::fs::MetadataExt;
+	Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn identity(_metadata: &Metadata) -> Option<(u64, u64)> {
+	// Rotation can only be detected by truncation.
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Write;
+
+	use super::*;
+
+	#[test]
+	fn follow_appended_data() {
+		let path = test_file("appended");
+		std::fs::write(&path, b"old data\n").unwrap();
+
+		let mut follow = Follow::open(&path).unwrap();
+		assert_eq!(read_available(&mut follow), "");
+
+		append(&path, b"new data\n");
+		assert_eq!(read_available(&mut follow), "new data\n");
+		assert_eq!(read_available(&mut follow), "");
+
+		std::fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn follow_rotated_file() {
+		let path    = test_file("rotated");
+		let rotated = test_file("rotated.1");
+		std::fs::write(&path, b"old data\n").unwrap();
+
+		let mut follow = Follow::open(&path).unwrap();
+		append(&path, b"last line before rotation\n");
+		std::fs::rename(&path, &rotated).unwrap();
+		std::fs::write(&path, b"first line after rotation\n").unwrap();
+
+		assert_eq!(read_available(&mut follow), "last line before rotation\n");
+		assert_eq!(read_available(&mut follow), "first line after rotation\n");
+
+		std::fs::remove_file(&path).unwrap();
+		std::fs::remove_file(&rotated).unwrap();
+	}
+
+	#[test]
+	fn follow_truncated_file() {
+		let path = test_file("truncated");
+		std::fs::write(&path, b"old data that is long\n").unwrap();
+
+		let mut follow = Follow::open(&path).unwrap();
+		std::fs::write(&path, b"short\n").unwrap();
+
+		assert_eq!(read_available(&mut follow), "short\n");
+
+		std::fs::remove_file(&path).unwrap();
+	}
+
+	fn test_file(name: &str) -> PathBuf {
+		std::env::temp_dir().join(format!("nfix-follow-{}-{}", std::process::id(), name))
+	}
+
+	fn append(path: &Path, bytes: &[u8]) {
+		let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+		file.write_all(bytes).unwrap();
+	}
+
+	fn read_available(follow: &mut Follow) -> String {
+		let mut buffer = [0; 1024];
+		let count      = follow.read_available(&mut buffer).unwrap();
+		String::from_utf8(buffer.get(..count).unwrap().to_vec()).unwrap()
+	}
+}
@@ -1,32 +1,54 @@
-use std::io::Write;
+use std::{cell::RefCell, collections::HashMap, io::Write};
 
-use crate::{args::Args, dictionary::Dictionary, filter::Filter, parser::{field::Field, message::Message}};
+use crate::{args::Args, dictionary::{Dictionary, FieldType}, filter::Filter, parser::{field::FieldRef, message::Fields, tag::Tag}, timestamp::{self, Timestamp}, timezone::TimeZone};
+
+const SENDING_TIME:   Tag = Tag::new(52);
+const SENDER_COMP_ID: Tag = Tag::new(49);
+const TARGET_COMP_ID: Tag = Tag::new(56);
+
+/// Session as the pair of SenderCompID and TargetCompID (in either direction).
+type Session = (Vec<u8>, Vec<u8>);
 
 pub trait FixFormatter: Default {
+	fn format(&self, message: &impl Fields, output: &mut impl Write) -> std::io::Result<()>;
+}
+
+/// Formatter set up with the options on the command line.
+pub(crate) trait FromArgs {
 	fn new(args: &Args) -> Self;
-	fn format(&self, message: &Message, output: &mut impl Write) -> std::io::Result<()>;
 }
 
 #[derive(Debug, Default)]
 pub struct SimpleFormatter<D: Dictionary, F: Filter> {
 	show_all_fields:       bool,
 	original_tag_ordering: bool,
+	/// Time zone UTCTimestamp fields are shown in as well.
+	time_zone:             Option<TimeZone>,
+	delta:                 bool,
+	/// SendingTime of the previous message of each session.
+	previous:              RefCell<HashMap<Session, Timestamp>>,
 	dictionary:            D,
 	filter:                F,
 }
 
-impl<D: Dictionary, F: Filter> FixFormatter for SimpleFormatter<D, F> {
+impl<D: Dictionary, F: Filter> FromArgs for SimpleFormatter<D, F> {
 	fn new(args: &Args) -> Self {
 		Self {
 			show_all_fields:       args.show_all_fields,
 			original_tag_ordering: args.original_tag_ordering,
+			time_zone:             args.tz.clone(),
+			delta:                 args.delta,
+			previous:              RefCell::default(),
 			dictionary:            D::default(),
 			filter:                F::default(),
 		}
 	}
+}
 
-	fn format(&self, message: &Message, output: &mut impl Write) -> std::io::Result<()> {
+impl<D: Dictionary, F: Filter> FixFormatter for SimpleFormatter<D, F> {
+	fn format(&self, message: &impl Fields, output: &mut impl Write) -> std::io::Result<()> {
 		let mut fields = self.relevant_fields(message);
+		let delta      = if self.delta { self.delta(message) } else { None };
 
 		// Find max tag width for alignment of tag name.
 		let width = fields.iter()
@@ -35,18 +57,30 @@ impl<D: Dictionary, F: Filter> FixFormatter for SimpleFormatter<D, F> {
 			.unwrap_or(0);
 
 		if !self.original_tag_ordering {
-			fields.sort_by(|f1, f2| f1.tag().cmp(&f2.tag()));
+			fields.sort_by_key(|f| f.tag());
 		}
 
 		for field in fields {
-			let tag           = field.tag();
-			let formatted_tag = match self.dictionary.tag_name(tag) {
-				Some(name) => format!("{:>6} : {:<width$} = ", tag.number(), name),
-				None       => format!("{:>6}   {:>width$} = ", tag.number(),   ""),
-			};
+			let tag = field.tag();
+			match self.dictionary.tag_name(tag) {
+				Some(name) => write!(output, "{:>6} : {:<width$} = ", tag.number(), name)?,
+				None       => write!(output, "{:>6}   {:>width$} = ", tag.number(),   "")?,
+			}
 
-			output.write_all(formatted_tag.as_bytes())?;
 			output.write_all(field.value_bytes())?;
+
+			let mut notes = Vec::new();
+			if let Some(time_zone) = &self.time_zone
+			&& self.dictionary.field(tag).is_some_and(|definition| definition.field_type == FieldType::UtcTimestamp)
+			&& let Some(timestamp) = Timestamp::parse(field.value_bytes()) {
+				notes.push(timestamp.format(time_zone));
+			}
+			if tag == SENDING_TIME && let Some(delta) = &delta {
+				notes.push(delta.clone());
+			}
+			if !notes.is_empty() {
+				write!(output, " ({})", notes.join(", "))?;
+			}
 			output.write_all(b"\n")?;
 		}
 
@@ -55,11 +89,25 @@ impl<D: Dictionary, F: Filter> FixFormatter for SimpleFormatter<D, F> {
 }
 
 impl<D: Dictionary, F: Filter> SimpleFormatter<D, F> {
-	fn relevant_fields<'a>(&'a self, message: &'a Message) -> Vec<&'a Field> {
-		message
-			.into_iter()
-			.filter(|f| self.show_all_fields || self.filter.relevant(f.tag()))
-			.collect()
+	/// Time since the SendingTime of the previous message of the session (in either direction).
+	fn delta(&self, message: &impl Fields) -> Option<String> {
+		let value   = |tag| message.fields().find(|field| field.tag() == tag).map(|field| field.value_bytes().to_vec());
+		let time    = Timestamp::parse(&value(SENDING_TIME)?)?;
+		let sender  = value(SENDER_COMP_ID).unwrap_or_default();
+		let target  = value(TARGET_COMP_ID).unwrap_or_default();
+		let session = if sender <= target { (sender, target) } else { (target, sender) };
+
+		let previous = self.previous.borrow_mut().insert(session, time)?;
+		Some(timestamp::format_delta(time.since(&previous), time.precision().max(previous.precision())))
+	}
+
+	fn relevant_fields<'m>(&self, message: &'m impl Fields) -> Vec<FieldRef<'m>> {
+		if self.show_all_fields {
+			message.fields().collect()
+		}
+		else {
+			self.filter.relevant_fields(message).collect()
+		}
 	}
 }
 
@@ -70,6 +118,7 @@ mod tests {
 	use crate::filter::BaseFilter;
 	use crate::parser::COMMAND_NAME;
 	use crate::parser::field::Field;
+	use crate::parser::message::Message;
 	use crate::parser::tag::Tag;
 
 	/// Helper for creating a field from tag and str.
@@ -110,7 +159,7 @@ mod tests {
 	fn simple_formatter_with_all_fields() {
 		// Given:
 		use clap::Parser;
-		let args      = Args::parse_from(&[COMMAND_NAME, "-a"]);
+		let args      = Args::parse_from([COMMAND_NAME, "-a"]);
 		let formatter = SimpleFormatter::<BaseDictionary, BaseFilter>::new(&args);
 		let message   = Message::new( 
 			vec![
@@ -138,4 +187,41 @@ mod tests {
 		  56 : TargetCompID = TARGET
 		  ");
 	}
+
+	#[test]
+	fn time_zone_and_delta() {
+		// Given:
+		use clap::Parser;
+		let args      = Args::parse_from([COMMAND_NAME, "--tz", "+01:00", "--delta"]);
+		let formatter = SimpleFormatter::<BaseDictionary, BaseFilter>::new(&args);
+		let messages  = [
+			("CLIENT", "BROKER", "20200302-11:14:36.393"),
+			("BROKER", "CLIENT", "20200302-11:14:37.500"),
+			("CLIENT", "OTHER",  "20200302-11:14:38"),
+		];
+		let mut output = vec![];
+
+		// When:
+		for (sender, target, sending_time) in messages {
+			let message = Message::new(vec![to_field(35, "0"), to_field(49, sender), to_field(56, target), to_field(52, sending_time)]);
+			formatter.format(&message, &mut output).unwrap();
+		}
+
+		// Then:
+		let output_str = String::from_utf8(output).unwrap();
+		insta::assert_snapshot!(&output_str, @r"
+		35 : MsgType      = 0
+		49 : SenderCompID = CLIENT
+		52 : SendingTime  = 20200302-11:14:36.393 (2020-03-02 12:14:36.393 +01:00)
+		56 : TargetCompID = BROKER
+		35 : MsgType      = 0
+		49 : SenderCompID = BROKER
+		52 : SendingTime  = 20200302-11:14:37.500 (2020-03-02 12:14:37.500 +01:00, +1.107s)
+		56 : TargetCompID = CLIENT
+		35 : MsgType      = 0
+		49 : SenderCompID = CLIENT
+		52 : SendingTime  = 20200302-11:14:38 (2020-03-02 12:14:38 +01:00)
+		56 : TargetCompID = OTHER
+		");
+	}
 }
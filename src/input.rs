@@ -0,0 +1,160 @@
+//! Module for reading the messages in the input for modes that analyse messages instead of
+//! formatting them one by one. Bytes that are not part of a FIX message are dropped except for
+//! the start of the log line before each message (which usually holds a timestamp) - or passed on
+//! as is by `scan` for modes that rewrite the messages in the log.
+//!
+//! With `--from` and `--to` only the messages in the time window are read.
+
+use std::{fs::File, io::{BufRead, Error}, path::Path};
+
+use memmap2::Mmap;
+
+use crate::{args::Args, parser::{decoder::{Decoder, Event}, message::Message, scanner::{FIX_START, Scanned, Scanner}}, window::Window};
+
+/// Part of the input.
+#[derive(Debug)]
+pub enum Input<'a> {
+	/// Bytes that are not part of a FIX message.
+	Passthrough(&'a [u8]),
+	Message(Message),
+	/// Start of a message that is not valid FIX.
+	Invalid(&'a [u8]),
+}
+
+/// Calls `handle` for every message on stdin or in the files with the start of its log line
+/// (the bytes between the previous newline and the message).
+/// Returns the number of messages that could not be parsed.
+pub fn messages(args: &Args, mut handle: impl FnMut(Message, &[u8])) -> Result<usize, Error> {
+	let window      = Window::new(args);
+	let mut line    = Vec::new();
+	let mut invalid = 0;
+	read_input(args, window.as_ref(), &mut |input| match input {
+		Input::Message(message)   => {
+			if window.as_ref().is_none_or(|window| window.contains(&message, &line)) {
+				handle(message, &line);
+			}
+			line.clear();
+		}
+		Input::Passthrough(bytes) => passthrough(&mut line, bytes),
+		Input::Invalid(bytes)     => {
+			invalid += 1;
+			passthrough(&mut line, bytes);
+		}
+	})?;
+
+	Ok(invalid)
+}
+
+/// Calls `handle` for all of stdin or the files split in messages and the bytes between them.
+pub fn scan(args: &Args, mut handle: impl FnMut(Input)) -> Result<(), Error> {
+	read_input(args, None, &mut handle)
+}
+
+/// Reads the input (only the part of each file in the window).
+fn read_input(args: &Args, window: Option<&Window>, handle: &mut impl FnMut(Input)) -> Result<(), Error> {
+	if args.files.is_empty() {
+		return read(&mut std::io::stdin().lock(), args, handle);
+	}
+
+	let field_delimiter = args.field_separator as u8;
+	for path in &args.files {
+		let map   = map_file(path)?;
+		let range = window.map_or(0..map.len(), |window| window.range(&map, field_delimiter));
+		for scanned in Scanner::new(map.get(range).unwrap_or_default(), field_delimiter) {
+			match scanned {
+				Scanned::Message(message)   => handle(Input::Message(message.into_owned())),
+				Scanned::Passthrough(bytes) => handle(Input::Passthrough(bytes)),
+				Scanned::Invalid(bytes)     => handle(Input::Invalid(bytes)),
+			}
+		}
+	}
+
+	Ok(())
+}
+
+fn read(input: &mut impl BufRead, args: &Args, handle: &mut impl FnMut(Input)) -> Result<(), Error> {
+	let mut decoder = Decoder::new(args.field_separator as u8);
+	let mut buffer  = input.fill_buf()?;
+
+	while !buffer.is_empty() {
+		for event in decoder.feed(buffer) {
+			match event {
+				Event::Message(message)   => handle(Input::Message(message)),
+				Event::Passthrough(bytes) => handle(Input::Passthrough(&bytes)),
+				Event::Invalid(bytes)     => handle(Input::Invalid(&bytes)),
+			}
+		}
+
+		let len = buffer.len();
+		input.consume(len);
+		buffer = input.fill_buf()?;
+	}
+
+	// A message cut off at the end of the input is handed on like the scanner does.
+	let rest = decoder.finish();
+	if rest.starts_with(FIX_START) {
+		handle(Input::Invalid(&rest));
+	}
+	else if !rest.is_empty() {
+		handle(Input::Passthrough(&rest));
+	}
+
+	Ok(())
+}
+
+/// Adds bytes that are not part of a message to the log line (keeping the bytes after the last newline).
+pub fn passthrough(line: &mut Vec<u8>, bytes: &[u8]) {
+	match memchr::memrchr(b'\n', bytes) {
+		Some(newline) => {
+			line.clear();
+			line.extend_from_slice(bytes.get(newline + 1..).unwrap_or_default());
+		}
+		None          => line.extend_from_slice(bytes),
+	}
+}
+
+/// Memory-maps the file.
+pub fn map_file(path: &Path) -> Result<Mmap, Error> {
+	let map = File::open(path).and_then(|file| {
+		// SAFETY: The mapping is only read and the file is assumed not to be truncated while it is being scanned
+		// (the same assumption `grep` and friends make).
+		unsafe { Mmap::map(&file) }
+	});
+	map.map_err(|e| Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::parser::COMMAND_NAME;
+
+	#[test]
+	fn stdin_and_files_agree() {
+		let input = b"price 8=9 qty 18=20 val 8=Hello|x\n8=FIX.4.2|35=0|10=000|\n8=FIX.4.2|9=5|35|\n8=FIX.4.2|35=D|1=ACC";
+		let path  = std::env::temp_dir().join(format!("nfix-input-{}.log", std::process::id()));
+		std::fs::write(&path, input).unwrap();
+
+		let mut stdin = Vec::new();
+		read(&mut &input[..], &args(&[]), &mut |input| stdin.extend(describe(&input))).unwrap();
+
+		let mut file = Vec::new();
+		read_input(&args(&[path.to_str().unwrap()]), None, &mut |input| file.extend(describe(&input))).unwrap();
+		std::fs::remove_file(&path).unwrap();
+
+		assert_eq!(stdin, ["Message", "Invalid", "Invalid"]);
+		assert_eq!(file,  stdin);
+	}
+
+	fn describe(input: &Input) -> Option<&'static str> {
+		match input {
+			Input::Passthrough(_) => None,
+			Input::Message(_)     => Some("Message"),
+			Input::Invalid(_)     => Some("Invalid"),
+		}
+	}
+
+	fn args(files: &[&str]) -> Args {
+		use clap::Parser;
+		Args::parse_from([COMMAND_NAME, "-s", "|"].iter().chain(files))
+	}
+}
@@ -0,0 +1,195 @@
+//! Module for measuring how long counterparties take to respond to requests.
+//! Requests are paired with the first response in the opposite direction of the session:
+//!
+//! - NewOrderSingle (35=D) with an ExecutionReport with ExecType New or Rejected (same ClOrdID).
+//! - OrderCancelRequest (35=F) with an ExecutionReport with ExecType Canceled or an OrderCancelReject (same ClOrdID).
+//! - OrderCancelReplaceRequest (35=G) with an ExecutionReport with ExecType Replaced or an OrderCancelReject (same ClOrdID).
+//! - TestRequest (35=1) with a Heartbeat (same TestReqID).
+//!
+//! Latencies are reported with percentiles per session and request MsgType.
+
+use std::{collections::{BTreeMap, HashMap}, io::{Error, Write}};
+
+use crate::{parser::{message::Message, tag::Tag}, timestamp::Clock};
+
+const CL_ORD_ID:      Tag = Tag::new(11);
+const SENDER_COMP_ID: Tag = Tag::new(49);
+const TARGET_COMP_ID: Tag = Tag::new(56);
+const TEST_REQ_ID:    Tag = Tag::new(112);
+const EXEC_TYPE:      Tag = Tag::new(150);
+
+const HEARTBEAT:            &[u8] = b"0";
+const TEST_REQUEST:         &[u8] = b"1";
+const EXECUTION_REPORT:     &[u8] = b"8";
+const ORDER_CANCEL_REJECT:  &[u8] = b"9";
+const NEW_ORDER_SINGLE:     &[u8] = b"D";
+const ORDER_CANCEL_REQUEST: &[u8] = b"F";
+const ORDER_CANCEL_REPLACE: &[u8] = b"G";
+
+/// Percentiles shown in the report.
+const PERCENTILES: [u32; 3] = [50, 90, 99];
+
+/// Outstanding request (SenderCompID, TargetCompID, identifying tag and its value).
+type Request = (Vec<u8>, Vec<u8>, Tag, Vec<u8>);
+
+/// Session and MsgType of the requests in the report.
+type Group = (String, Vec<u8>);
+
+#[derive(Debug)]
+pub struct Latencies {
+	clock:     Clock,
+	/// Outstanding requests with their MsgType and time (nanoseconds since the epoch).
+	pending:   HashMap<Request, (Vec<u8>, i64)>,
+	/// Latencies in nanoseconds.
+	latencies: BTreeMap<Group, Vec<i64>>,
+}
+
+impl Latencies {
+	#[must_use]
+	pub fn new(clock: Clock) -> Self {
+		Self { clock, pending: HashMap::new(), latencies: BTreeMap::new() }
+	}
+
+	/// Records the message as a request or pairs it with its request.
+	/// `line` is the start of the log line before the message.
+	pub fn add(&mut self, message: &Message, line: &[u8]) {
+		let Some(msg_type) = message.msg_type() else {
+			return;
+		};
+		let Some(time) = self.clock.time(message, line) else {
+			return;
+		};
+		let sender = message.value(SENDER_COMP_ID).unwrap_or_default();
+		let target = message.value(TARGET_COMP_ID).unwrap_or_default();
+
+		if let Some(tag) = request_tag(msg_type) {
+			if let Some(id) = message.value(tag) {
+				self.pending.insert((sender.to_vec(), target.to_vec(), tag, id.to_vec()), (msg_type.to_vec(), time));
+			}
+			return;
+		}
+
+		let Some((tag, requests)) = response(msg_type, message.value(EXEC_TYPE)) else {
+			return;
+		};
+		let Some(id) = message.value(tag) else {
+			return;
+		};
+		let request = (target.to_vec(), sender.to_vec(), tag, id.to_vec());
+		if self.pending.get(&request).is_some_and(|(request_type, _)| requests.contains(&request_type.as_slice()))
+		&& let Some((request_type, request_time)) = self.pending.remove(&request) {
+			let session = format!("{} -> {}", String::from_utf8_lossy(&request.0), String::from_utf8_lossy(&request.1));
+			self.latencies.entry((session, request_type)).or_default().push(time - request_time);
+		}
+	}
+
+	/// Writes the latency percentiles per session and MsgType followed by the number of unanswered requests.
+	pub fn write(mut self, output: &mut impl Write) -> Result<(), Error> {
+		for ((session, msg_type), latencies) in &mut self.latencies {
+			latencies.sort_unstable();
+			write!(output, "{} {}: {} response{}", session, String::from_utf8_lossy(msg_type), latencies.len(), if latencies.len() == 1 { "" } else { "s" })?;
+			write!(output, ", min {}", milliseconds(latencies.first().copied().unwrap_or_default()))?;
+			for percentile in PERCENTILES {
+				write!(output, ", p{} {}", percentile, milliseconds(nearest_rank(latencies, percentile)))?;
+			}
+			writeln!(output, ", max {}", milliseconds(latencies.last().copied().unwrap_or_default()))?;
+		}
+
+		let mut unanswered = BTreeMap::<&[u8], usize>::new();
+		for (msg_type, _) in self.pending.values() {
+			*unanswered.entry(msg_type).or_default() += 1;
+		}
+		for (msg_type, count) in unanswered {
+			writeln!(output, "Unanswered {}: {}", String::from_utf8_lossy(msg_type), count)?;
+		}
+		Ok(())
+	}
+
+}
+
+/// Tag identifying the request if the MsgType is a request.
+fn request_tag(msg_type: &[u8]) -> Option<Tag> {
+	match msg_type {
+		NEW_ORDER_SINGLE | ORDER_CANCEL_REQUEST | ORDER_CANCEL_REPLACE => Some(CL_ORD_ID),
+		TEST_REQUEST                                                   => Some(TEST_REQ_ID),
+		_                                                              => None,
+	}
+}
+
+/// Tag identifying the request and the request MsgTypes answered if the message is a response.
+fn response(msg_type: &[u8], exec_type: Option<&[u8]>) -> Option<(Tag, &'static [&'static [u8]])> {
+	match (msg_type, exec_type) {
+		(EXECUTION_REPORT, Some(b"0" | b"8")) => Some((CL_ORD_ID, &[NEW_ORDER_SINGLE])),
+		(EXECUTION_REPORT, Some(b"4"))        => Some((CL_ORD_ID, &[ORDER_CANCEL_REQUEST])),
+		(EXECUTION_REPORT, Some(b"5"))        => Some((CL_ORD_ID, &[ORDER_CANCEL_REPLACE])),
+		(ORDER_CANCEL_REJECT, _)              => Some((CL_ORD_ID, &[ORDER_CANCEL_REQUEST, ORDER_CANCEL_REPLACE])),
+		(HEARTBEAT, _)                        => Some((TEST_REQ_ID, &[TEST_REQUEST])),
+		(_, _)                                => None,
+	}
+}
+
+/// Latency at the percentile (nearest-rank method) of the sorted latencies.
+fn nearest_rank(sorted: &[i64], percentile: u32) -> i64 {
+	let rank = (sorted.len() * percentile as usize).div_ceil(100);
+	sorted.get(rank.saturating_sub(1)).copied().unwrap_or_default()
+}
+
+fn milliseconds(nanoseconds: i64) -> String {
+	format!("{:.3} ms", nanoseconds as f64 / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{input::passthrough, parser::scanner::{Scanned, Scanner}, timestamp::TimeSource};
+
+	#[test]
+	fn requests_paired_with_responses() {
+		let input = "\
+			8=FIX.4.4|35=D|49=CLIENT|56=BROKER|52=20200302-11:14:36.100|11=A|10=000|\n\
+			8=FIX.4.4|35=D|49=CLIENT|56=BROKER|52=20200302-11:14:36.200|11=B|10=000|\n\
+			8=FIX.4.4|35=D|49=CLIENT|56=BROKER|52=20200302-11:14:36.300|11=C|10=000|\n\
+			8=FIX.4.4|35=8|49=BROKER|56=CLIENT|52=20200302-11:14:36.105|11=A|150=0|10=000|\n\
+			8=FIX.4.4|35=8|49=BROKER|56=CLIENT|52=20200302-11:14:36.230|11=B|150=8|10=000|\n\
+			8=FIX.4.4|35=8|49=BROKER|56=CLIENT|52=20200302-11:14:36.240|11=B|150=0|10=000|\n\
+			8=FIX.4.4|35=F|49=CLIENT|56=BROKER|52=20200302-11:14:37.000|11=A2|41=A|10=000|\n\
+			8=FIX.4.4|35=9|49=BROKER|56=CLIENT|52=20200302-11:14:37.002500|11=A2|10=000|\n\
+			8=FIX.4.4|35=1|49=BROKER|56=CLIENT|52=20200302-11:15:00.000|112=T1|10=000|\n\
+			8=FIX.4.4|35=0|49=CLIENT|56=BROKER|52=20200302-11:15:00.050|112=T1|10=000|\n";
+
+		insta::assert_snapshot!(latencies(input, TimeSource::SendingTime), @r"
+		BROKER -> CLIENT 1: 1 response, min 50.000 ms, p50 50.000 ms, p90 50.000 ms, p99 50.000 ms, max 50.000 ms
+		CLIENT -> BROKER D: 2 responses, min 5.000 ms, p50 5.000 ms, p90 30.000 ms, p99 30.000 ms, max 30.000 ms
+		CLIENT -> BROKER F: 1 response, min 2.500 ms, p50 2.500 ms, p90 2.500 ms, p99 2.500 ms, max 2.500 ms
+		Unanswered D: 1
+		");
+	}
+
+	#[test]
+	fn log_line_timestamps() {
+		let input = "\
+			2020/03/02-06:14:36,393 INFO :: In: 8=FIX.4.4|35=D|49=CLIENT|56=BROKER|11=A|10=000|\n\
+			2020/03/02-06:14:36,531 INFO :: Out: 8=FIX.4.4|35=8|49=BROKER|56=CLIENT|11=A|150=0|10=000|\n";
+
+		insta::assert_snapshot!(latencies(input, TimeSource::Log), @"CLIENT -> BROKER D: 1 response, min 138.000 ms, p50 138.000 ms, p90 138.000 ms, p99 138.000 ms, max 138.000 ms");
+	}
+
+	fn latencies(input: &str, source: TimeSource) -> String {
+		let mut latencies = Latencies::new(source.into());
+		let mut line      = Vec::new();
+		for scanned in Scanner::new(input.as_bytes(), b'|') {
+			match scanned {
+				Scanned::Message(message)   => {
+					latencies.add(&message.into_owned(), &line);
+					line.clear();
+				}
+				Scanned::Passthrough(bytes)
+				| Scanned::Invalid(bytes)   => passthrough(&mut line, bytes),
+			}
+		}
+
+		let mut output = Vec::new();
+		latencies.write(&mut output).unwrap();
+		String::from_utf8(output).unwrap()
+	}
+}
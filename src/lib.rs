@@ -0,0 +1,190 @@
+//! Library for parsing, formatting and analysing FIX messages.
+//! The `nfix` command is a thin layer on top of `run`; its options are not part of the library.
+//!
+//! Messages are decoded from input that arrives in chunks with a push-style `Decoder`
+//! (or from complete buffers with the faster `Scanner`) and formatted with a `FixFormatter`
+//! using the tag names of a `Dictionary`:
+//!
+//! ```
+//! use nfix::{Decoder, Event};
+//!
+//! let mut decoder = Decoder::new(b'|');
+//! let events: Vec<Event> = decoder.feed(b"log: 8=FIX.4.2|9=5|35=0|10=123|").collect();
+//!
+//! assert_eq!(events.len(), 2);
+//! assert!(matches!(events.get(1), Some(Event::Message(_))));
+//! ```
+
+use std::io::{BufReader, BufWriter, Write};
+
+use clap::Parser;
+
+use crate::{args::{Args, Command, Mode}, chains::Chains, formatter::FromArgs, follow::Follow, latency::Latencies, parser::{parallel, scanner}, sessions::Sessions, stats::Stats, timestamp::Clock, validate::Validator};
+
+mod parser;
+mod formatter;
+mod dictionary;
+mod filter;
+mod follow;
+mod capture;
+mod encoder;
+mod build;
+mod diff;
+mod normalize;
+mod redact;
+mod input;
+mod chains;
+mod sessions;
+mod latency;
+mod stats;
+mod validate;
+mod timestamp;
+mod timezone;
+mod window;
+mod session;
+mod simulate;
+mod replay;
+mod proxy;
+#[cfg(feature = "tokio")]
+mod codec;
+mod args;
+
+pub use crate::{args::SessionSettings, dictionary::{BaseDictionary, Dictionary, FieldDefinition, FieldType, Member, MessageDefinition}, filter::{BaseFilter, Filter}, formatter::{FixFormatter, SimpleFormatter}, parser::{FixError, Flush, decimal::Decimal, decoder::{Decoder, Event, Feed}, field::{Field, FieldRef}, message::{Fields, Message, MessageRef}, scanner::{Scanned, Scanner}, tag::Tag, value::{FieldError, LocalMktDate}}, session::Session, timestamp::Timestamp, timezone::TimeZone};
+#[cfg(feature = "tokio")]
+pub use crate::codec::FixCodec;
+
+/// Runs the command line: formats the messages in the input or runs the chosen mode or command.
+pub fn run() -> std::io::Result<()> {
+	let args   = Args::parse();
+	let stdout = std::io::stdout();
+
+	// Output to terminals and pipes is flushed per message so it shows up immediately, and so is the output
+	// of input that never ends (followed files and sessions). Other output redirected to a file is fully buffered.
+	let live  = args.follow.is_some() || matches!(args.command, Some(Command::Session(_) | Command::Simulate(_) | Command::Replay(_) | Command::Proxy(_)));
+	let flush = if live || !is_regular_file(&stdout) { Flush::EachMessage } else { Flush::EndOfInput };
+
+	match flush {
+		Flush::EachMessage => execute(args, &mut stdout.lock(), flush),
+		Flush::EndOfInput  => execute(args, &mut BufWriter::new(stdout.lock()), flush),
+	}
+}
+
+#[cfg(unix)]
+fn is_regular_file(stdout: &std::io::Stdout) -> bool {
+	use std::os::fd::AsFd;
+	stdout.as_fd()
+		.try_clone_to_owned()
+		.and_then(|fd| std::fs::File::from(fd).metadata())
+		.is_ok_and(|metadata| metadata.is_file())
+}
+
+#[cfg(not(unix))]
+fn is_regular_file(_stdout: &std::io::Stdout) -> bool {
+	false
+}
+
+fn execute(args: Args, output: &mut impl Write, flush: Flush) -> std::io::Result<()> {
+	if let Some(Command::Build(build_args)) = &args.command {
+		return build::run::<BaseDictionary>(build_args, args.field_separator as u8, output);
+	}
+	if let Some(Command::Diff(diff_args)) = &args.command {
+		return diff::run::<BaseDictionary, BaseFilter>(&args, diff_args, output);
+	}
+	if let Some(Command::Session(session_args)) = &args.command {
+		return session::run::<BaseDictionary, BaseFilter>(&args, session_args, output, flush);
+	}
+	if let Some(Command::Simulate(simulate_args)) = &args.command {
+		return simulate::run::<BaseDictionary, BaseFilter>(&args, simulate_args, output, flush);
+	}
+	if let Some(Command::Replay(replay_args)) = &args.command {
+		return replay::run::<BaseDictionary, BaseFilter>(&args, replay_args, output, flush);
+	}
+	if let Some(Command::Proxy(proxy_args)) = &args.command {
+		return proxy::run::<BaseDictionary, BaseFilter>(&args, proxy_args, output, flush);
+	}
+
+	if let Some(path) = args.follow.clone() {
+		return parser::process(&mut BufReader::new(Follow::open(&path)?), output, args, flush);
+	}
+
+	if args.mode == Some(Mode::Chains) {
+		let mut chains = Chains::new();
+		input::messages(&args, |message, _| chains.add(message))?;
+		return chains.write(&SimpleFormatter::<BaseDictionary, BaseFilter>::new(&args), output);
+	}
+
+	if args.mode == Some(Mode::Sessions) {
+		let mut sessions = Sessions::new();
+		input::messages(&args, |message, _| sessions.add(message))?;
+		return sessions.write(output);
+	}
+
+	if args.mode == Some(Mode::Latency) {
+		let mut latencies = Latencies::new(Clock::new(args.time_source, args.time_format.clone()));
+		input::messages(&args, |message, line| latencies.add(&message, line))?;
+		return latencies.write(output);
+	}
+
+	if args.mode == Some(Mode::Stats) {
+		let mut stats = Stats::new(Clock::new(args.time_source, args.time_format.clone()));
+		let invalid   = input::messages(&args, |message, line| stats.add(&message, line))?;
+		stats.add_parse_failures(invalid);
+		return stats.write(output);
+	}
+
+	if args.mode == Some(Mode::Validate) {
+		let mut validator = Validator::<BaseDictionary>::new();
+		input::messages(&args, |message, _| validator.add(&message))?;
+		return validator.write(output);
+	}
+
+	if args.mode == Some(Mode::Normalize) {
+		return normalize::run::<BaseDictionary>(&args, output);
+	}
+
+	if args.mode == Some(Mode::Redact) {
+		return redact::run::<BaseDictionary>(&args, output);
+	}
+
+	// Only the messages in the window are formatted, each after the start of its log line.
+	if args.from.is_some() || args.to.is_some() {
+		let formatter  = SimpleFormatter::<BaseDictionary, BaseFilter>::new(&args);
+		let mut result = Ok(());
+		input::messages(&args, |message, line| {
+			if result.is_ok() {
+				result = write_message(&formatter, output, flush, &message, line);
+			}
+		})?;
+		return result;
+	}
+
+	if args.files.is_empty() {
+		return parser::process(&mut std::io::stdin().lock(), output, args, flush);
+	}
+
+	for path in &args.files {
+		let map = input::map_file(path)?;
+		if args.pcap {
+			capture::process(&map, output, args.clone(), flush)?;
+		}
+		else if args.jobs.get() > 1 {
+			parallel::process(&map, output, args.clone(), flush)?;
+		}
+		else {
+			scanner::process(&map, output, args.clone(), flush)?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Writes the start of the log line and the formatted message.
+fn write_message(formatter: &impl FixFormatter, output: &mut impl Write, flush: Flush, message: &Message, line: &[u8]) -> std::io::Result<()> {
+	output.write_all(line)?;
+	output.write_all(b"\n")?;
+	formatter.format(message, output)?;
+	if flush == Flush::EachMessage {
+		output.flush()?;
+	}
+	Ok(())
+}
@@ -0,0 +1,28 @@
+//! Module for repairing messages copied from logs: each message is re-encoded as canonical tag=value
+//! with header and trailer fields in order and BodyLength (9) and CheckSum (10) recalculated.
+
+use std::io::{Error, Write};
+
+use crate::{args::Args, dictionary::Dictionary, encoder, input, parser::{field::SOH, message::Message}};
+
+/// Writes each message in the input normalised on its own line.
+/// Bytes that are not part of a message are dropped.
+pub fn run<D: Dictionary>(args: &Args, output: &mut impl Write) -> Result<(), Error> {
+	let dictionary = D::default();
+	let separator  = if args.keep_separator { args.field_separator as u8 } else { SOH };
+
+	let mut written = Ok(());
+	input::messages(args, |message, _| {
+		if written.is_ok() {
+			written = write(message, &dictionary, separator, output);
+		}
+	})?;
+	written
+}
+
+/// Messages that cannot be completed (e.g. without a MsgType) are written with their fields as they are.
+fn write(message: Message, dictionary: &impl Dictionary, separator: u8, output: &mut impl Write) -> Result<(), Error> {
+	let message = encoder::normalize(message, dictionary).unwrap_or_else(|incomplete| incomplete.message);
+	output.write_all(&encoder::encode(&message, separator))?;
+	writeln!(output)
+}
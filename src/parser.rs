@@ -1,26 +1,40 @@
-use std::{io::{BufRead, Error, Write}, vec};
+use std::io::{BufRead, Error, Write};
 
-use crate::{args::Args, dictionary::BaseDictionary, filter::BaseFilter, formatter::{FixFormatter, SimpleFormatter}, parser::{field::Field, message::Message, state::{CHECK_SUM, ParserState}}};
+use crate::{args::Args, dictionary::BaseDictionary, filter::BaseFilter, formatter::{FixFormatter, FromArgs, SimpleFormatter}, parser::decoder::{Decoder, Event}};
 
-pub(crate) mod field;
+pub mod field;
 pub(crate) mod state;
 pub(crate) mod begin_string;
-pub(crate) mod tag;
-pub(crate) mod message;
+pub mod tag;
+pub mod message;
+pub mod value;
+pub mod decimal;
+pub mod decoder;
+pub mod scanner;
+pub(crate) mod parallel;
 
 pub const COMMAND_NAME: &str  = "nfix";
 
-pub fn process(input: &mut impl BufRead, output: &mut impl Write, args: Args) -> Result<(), Error> {
-	let parser = Parser::<SimpleFormatter<BaseDictionary, BaseFilter>>::new(args);
+/// When formatted output is flushed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flush {
+	/// Flush as soon as a message has been formatted (for terminals, pipes and following files).
+	EachMessage,
+	/// Flush only at end of input.
+	EndOfInput,
+}
+
+pub fn process(input: &mut impl BufRead, output: &mut impl Write, args: Args, flush: Flush) -> Result<(), Error> {
+	let parser = Parser::<SimpleFormatter<BaseDictionary, BaseFilter>>::new(args, flush);
 	parser.process(input, output)
 }
 
+/// Parser formatting the messages in the input and passing other bytes through as is.
 #[derive(Debug)]
-struct Parser<F: FixFormatter> {
-	field_delimiter: u8,
-	parser_state:    ParserState,
-	parsed_fields:   Vec<Field>,
-	formatter:       F,
+pub struct Parser<F: FixFormatter + FromArgs> {
+	decoder:   Decoder,
+	flush:     Flush,
+	formatter: F,
 }
 
 #[derive(Debug, PartialEq)]
@@ -30,6 +44,7 @@ pub enum FixError {
 }
 
 impl FixError {
+	#[must_use]
 	pub fn bytes(self) -> Vec<u8> {
 		match self {
 			FixError::NotFixStart   => vec![],
@@ -38,38 +53,35 @@ impl FixError {
 	}
 }
 
-impl<F: FixFormatter> Parser<F> {
-	fn new(args: Args) -> Self {
-		let field_delimiter = args.field_separator as u8;
+impl<F: FixFormatter + FromArgs> Parser<F> {
+	#[must_use]
+	pub fn new(args: Args, flush: Flush) -> Self {
 		Self {
-			field_delimiter,
-			parser_state:  ParserState::new(),
-			parsed_fields: Vec::new(),
-			formatter:     F::new(&args),
+			decoder:   Decoder::new(args.field_separator as u8),
+			flush,
+			formatter: F::new(&args),
 		}
 	}
 
-	fn process(mut self, input: &mut impl BufRead, output: &mut impl Write) -> Result<(), Error> {
-		// Read bytes and process one byte at a time as a FIX message can be split across multiple reads.
+	pub fn process(mut self, input: &mut impl BufRead, output: &mut impl Write) -> Result<(), Error> {
+		// Read bytes and feed them to the decoder as a FIX message can be split across multiple reads.
 		// Note also that a single read can also contain multiple FIX messages.
 
 		let mut buffer = input.fill_buf()?;
 
 		while !buffer.is_empty() {
-			for byte in buffer.iter() {
-				match self.consume(*byte) {
-					Ok(None)                     => {} // Parser consumed byte.
-					Ok(Some(message))            => {
+			for event in self.decoder.feed(buffer) {
+				match event {
+					Event::Message(message)   => {
 						// Write message on new line.
 						output.write_all(b"\n")?;
 						self.formatter.format(&message, output)?;
+						if self.flush == Flush::EachMessage {
+							output.flush()?;
+						}
 					}
-					Err(FixError::NotFixStart)   => {
-						output.write_all(&[*byte])?;
-					}
-					Err(FixError::NotFix(bytes)) => {
-						output.write_all(&bytes)?;
-					}
+					Event::Passthrough(bytes)
+					| Event::Invalid(bytes)   => output.write_all(&bytes)?,
 				}
 			}
 
@@ -78,88 +90,12 @@ impl<F: FixFormatter> Parser<F> {
 			buffer = input.fill_buf()?;
 		}
 
-		self.end_of_input(output)?;
-
-		Ok(())
-	}
-
-	fn unwind_fields(&mut self) -> Vec<u8> {
-		// Unwind parsed fields.
-		let mut bytes = vec![];
-		for field in self.parsed_fields.drain(..) {
-			let mut field_bytes = field.bytes();
-			bytes.append(&mut field_bytes);
-			bytes.push(self.field_delimiter);
-		}
-		bytes
-	}
-
-	fn end_of_input(mut self, output: &mut impl Write) -> Result<(), Error> {
-		// Unwind any parsed fields.
-		let mut bytes = self.unwind_fields();
-		// Unwind current parser state.
-		let ongoing_bytes = self.parser_state.unwind();
-		bytes.extend(ongoing_bytes);
-
-		output.write_all(&bytes)?;
+		// Unwind any message that was not completed.
+		output.write_all(&self.decoder.finish())?;
+		output.flush()?;
 
 		Ok(())
 	}
-
-	#[inline]
-	fn consume(&mut self, byte: u8) -> Result<Option<Message>, FixError> {
-		if byte != self.field_delimiter {
-			self.parse_field(byte)
-		}
-		else {
-			self.finish_field()
-		}
-	}
-
-	#[inline]
-	fn parse_field(&mut self, byte: u8) -> Result<Option<Message>, FixError> {
-		match self.parser_state.consume(byte) {
-			Ok(()) => Ok(None),
-			Err(e) => {
-				if self.parsed_fields.is_empty() {
-					// No parsed fields yet - just return parser_state's error.
-					Err(e)
-				}
-				else {
-					// Unwind all parsed fields so far.
-					let mut bytes = self.unwind_fields();
-					bytes.extend(e.bytes());
-
-					Err(FixError::NotFix(bytes))
-				}
-			}
-		}
-	}
-
-	fn finish_field(&mut self) -> Result<Option<Message>, FixError> {
-		match self.parser_state.finish_field() {
-			Ok(field) => {
-				let tag = field.tag();
-				self.parsed_fields.push(field);
-
-				if tag.number() == CHECK_SUM {
-					let message = Message::new(self.parsed_fields.drain(..).collect());
-					Ok(Some(message))
-				}
-				else {
-					Ok(None)
-				}
-			}
-			Err(e) => {
-				// Unwind all parsed fields so far.
-				let mut bytes = self.unwind_fields();
-				bytes.extend(e.bytes());
-				bytes.push(self.field_delimiter); // Include the delimiter that caused the error.
-
-				Err(FixError::NotFix(bytes))
-			}
-		}
-	}
 }
 
 #[cfg(test)]
@@ -185,7 +121,7 @@ mod tests {
 			let parser     = create_default_parser();
 			let mut output = Vec::new();
 			parser.process(&mut &input[..], &mut output).unwrap();
-			assert_eq!(to_str(&output), to_str(&input));
+			assert_eq!(to_str(&output), to_str(input));
 		}
 	}
 
@@ -283,6 +219,37 @@ mod tests {
 		    56 : TargetCompID = TARGET
 		");
 	}
+
+	#[test]
+	fn flush_each_message() {
+		let input      = b"8=FIX.4.2\x019=45\x0135=D\x0149=SENDER\x0156=TARGET\x0110=123\x01 trailing";
+		let args       = { use clap::Parser; Args::parse_from([COMMAND_NAME]) };
+		let parser     = Parser::<SimpleFormatter<BaseDictionary, BaseFilter>>::new(args, Flush::EachMessage);
+		let mut output = FlushRecorder::default();
+		parser.process(&mut &input[..], &mut output).unwrap();
+
+		// Flushed once after the message and once at end of input.
+		assert_eq!(output.flushed_lengths, vec![output.bytes.len() - b" trailing".len(), output.bytes.len()]);
+	}
+
+	/// Records the amount of written bytes at every flush.
+	#[derive(Default)]
+	struct FlushRecorder {
+		bytes:           Vec<u8>,
+		flushed_lengths: Vec<usize>,
+	}
+
+	impl Write for FlushRecorder {
+		fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+			self.bytes.write(buf)
+		}
+
+		fn flush(&mut self) -> std::io::Result<()> {
+			self.flushed_lengths.push(self.bytes.len());
+			Ok(())
+		}
+	}
+
 	fn create_default_parser() -> Parser<SimpleFormatter<BaseDictionary, BaseFilter>> {
 		create_parser_with_args(&[COMMAND_NAME])
 	}
@@ -294,7 +261,7 @@ mod tests {
 	}
 
 	fn create_parser(args: Args) -> Parser<SimpleFormatter<BaseDictionary, BaseFilter>> {
-		Parser::<SimpleFormatter<BaseDictionary, BaseFilter>>::new(args)
+		Parser::<SimpleFormatter<BaseDictionary, BaseFilter>>::new(args, Flush::EndOfInput)
 	}
 
 	fn to_str(bytes: &[u8]) -> &str {
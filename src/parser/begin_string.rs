@@ -4,8 +4,8 @@
 
 use crate::parser::{FixError, field::{Field, FieldParser}};
 
-const BEGIN_STRING:            u8    = b'8';
-const MAX_BEGIN_STRING_LENGTH: usize = 20;
+const BEGIN_STRING:                u8    = b'8';
+pub const MAX_BEGIN_STRING_LENGTH: usize = 20;
 
 #[derive(Debug)]
 pub struct BeginStringParser {
@@ -0,0 +1,205 @@
+//! Module for exact decimal numbers (FIX Float, Qty, Price, Amt ...).
+//! A decimal keeps the digits and the number of decimals of the value it was parsed from,
+//! so `1.50` is shown as `1.50` but compares equal to `1.5`.
+
+use std::{cmp::Ordering, fmt::Display};
+
+/// Most significant digits in a parsed value.
+/// The product of two values then fits the mantissa exactly.
+const MAX_DIGITS: usize = 18;
+/// Most decimals of a value (`10^38` is the largest power of 10 that fits the mantissa).
+const MAX_SCALE:  u32   = 38;
+
+/// Decimal number with up to 38 decimals.
+/// Arithmetic is checked: sums and products of parsed values can still overflow (e.g. many decimals times many decimals).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Decimal {
+	mantissa: i128,
+	/// Number of decimals.
+	scale:    u32,
+}
+
+impl Decimal {
+	pub const ZERO: Decimal = Decimal { mantissa: 0, scale: 0 };
+
+	/// Parses a FIX Float like `-1.5`, `10` or `.25` (no exponent and at most 18 significant digits).
+	#[must_use]
+	pub fn parse(value: &[u8]) -> Option<Decimal> {
+		let (negative, unsigned) = match value.strip_prefix(b"-") {
+			Some(unsigned) => (true, unsigned),
+			None           => (false, value),
+		};
+		let mut parts = unsigned.splitn(2, |byte| *byte == b'.');
+		let integer   = parts.next().unwrap_or_default();
+		let fraction  = parts.next().unwrap_or_default();
+		if integer.is_empty() && fraction.is_empty() {
+			return None;
+		}
+
+		let mut digits  = integer.iter().chain(fraction);
+		let significant = digits.clone().skip_while(|digit| **digit == b'0').count();
+		if significant > MAX_DIGITS {
+			return None;
+		}
+		let mantissa = digits.try_fold(0i128, |mantissa, digit| digit.is_ascii_digit().then(|| mantissa * 10 + i128::from(digit - b'0')))?;
+		let scale    = u32::try_from(fraction.len()).ok().filter(|scale| *scale <= MAX_SCALE)?;
+
+		Some(Decimal { mantissa: if negative { -mantissa } else { mantissa }, scale })
+	}
+
+	/// Number of decimals.
+	#[must_use]
+	pub fn scale(&self) -> u32 {
+		self.scale
+	}
+
+	#[must_use]
+	pub fn is_zero(&self) -> bool {
+		self.mantissa == 0
+	}
+
+	#[must_use]
+	pub fn checked_add(self, other: Decimal) -> Option<Decimal> {
+		let scale = self.scale.max(other.scale);
+		Some(Decimal { mantissa: self.rescale(scale)?.checked_add(other.rescale(scale)?)?, scale })
+	}
+
+	#[must_use]
+	pub fn checked_sub(self, other: Decimal) -> Option<Decimal> {
+		self.checked_add(Decimal { mantissa: other.mantissa.checked_neg()?, scale: other.scale })
+	}
+
+	#[must_use]
+	pub fn checked_mul(self, other: Decimal) -> Option<Decimal> {
+		let scale = self.scale + other.scale;
+		(scale <= MAX_SCALE).then_some(Decimal { mantissa: self.mantissa.checked_mul(other.mantissa)?, scale })
+	}
+
+	/// Quotient rounded half away from zero to `scale` decimals (`None` when dividing by zero).
+	#[must_use]
+	pub fn checked_div(self, other: Decimal, scale: u32) -> Option<Decimal> {
+		if other.is_zero() || scale > MAX_SCALE {
+			return None;
+		}
+		// self / other = (self.mantissa * 10^(scale + other.scale - self.scale) / other.mantissa) / 10^scale
+		let exponent            = i64::from(scale) + i64::from(other.scale) - i64::from(self.scale);
+		let (dividend, divisor) = if exponent >= 0 {
+			(self.mantissa.checked_mul(power(u32::try_from(exponent).ok()?)?)?, other.mantissa)
+		}
+		else {
+			(self.mantissa, other.mantissa.checked_mul(power(u32::try_from(-exponent).ok()?)?)?)
+		};
+
+		let mut mantissa = dividend / divisor;
+		if (dividend % divisor).unsigned_abs() * 2 >= divisor.unsigned_abs() {
+			mantissa += dividend.signum() * divisor.signum();
+		}
+		Some(Decimal { mantissa, scale })
+	}
+
+	/// Mantissa for the value with more decimals.
+	fn rescale(&self, scale: u32) -> Option<i128> {
+		self.mantissa.checked_mul(power(scale.checked_sub(self.scale)?)?)
+	}
+}
+
+fn power(exponent: u32) -> Option<i128> {
+	10i128.checked_pow(exponent)
+}
+
+impl From<i64> for Decimal {
+	fn from(value: i64) -> Self {
+		Decimal { mantissa: i128::from(value), scale: 0 }
+	}
+}
+
+impl PartialEq for Decimal {
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == Ordering::Equal
+	}
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Decimal {
+	fn cmp(&self, other: &Self) -> Ordering {
+		// The integer parts are compared first so the fractions can be aligned without overflowing.
+		let scale = self.scale.max(other.scale);
+		let split = |decimal: &Decimal| power(decimal.scale).map_or((0, decimal.mantissa), |unit| (decimal.mantissa / unit, decimal.mantissa % unit));
+		let align = |fraction: i128, decimal: &Decimal| power(scale - decimal.scale).map_or(fraction, |unit| fraction * unit);
+
+		let (integer, fraction)             = split(self);
+		let (other_integer, other_fraction) = split(other);
+		integer.cmp(&other_integer).then_with(|| align(fraction, self).cmp(&align(other_fraction, other)))
+	}
+}
+
+impl Display for Decimal {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let digits = self.mantissa.unsigned_abs().to_string();
+		let scale  = self.scale as usize;
+		let sign   = if self.mantissa < 0 { "-" } else { "" };
+		if scale == 0 {
+			return write!(f, "{}{}", sign, digits);
+		}
+
+		let digits = format!("{:0>width$}", digits, width = scale + 1);
+		let (integer, fraction) = digits.split_at(digits.len() - scale);
+		write!(f, "{}{}.{}", sign, integer, fraction)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn decimal(value: &str) -> Decimal {
+		Decimal::parse(value.as_bytes()).unwrap()
+	}
+
+	#[test]
+	fn parse_and_display() {
+		for value in ["0", "10", "-1.5", "1.50", "0.001", "-0.05", "123456789012345678", "0.000000000000000000000000000001"] {
+			assert_eq!(decimal(value).to_string(), value);
+		}
+		assert_eq!(decimal(".25").to_string(), "0.25");
+		assert_eq!(decimal("7.").to_string(),  "7");
+
+		for value in ["", "-", ".", "1e3", "1,5", "+1", "1.2.3", "1234567890123456789"] {
+			assert_eq!(Decimal::parse(value.as_bytes()), None, "{}", value);
+		}
+	}
+
+	#[test]
+	fn arithmetic_is_exact() {
+		let text = |decimal: Option<Decimal>| decimal.map(|decimal| decimal.to_string());
+		assert_eq!(decimal("0.1").checked_add(decimal("0.2")),        Some(decimal("0.3")));
+		assert_eq!(text(decimal("1.50").checked_add(decimal("0.25"))), Some("1.75".to_string()));
+		assert_eq!(text(decimal("10").checked_sub(decimal("10.5"))),   Some("-0.5".to_string()));
+		assert_eq!(text(decimal("1.5").checked_mul(decimal("-0.25"))), Some("-0.375".to_string()));
+
+		// Out of range: more than 38 decimals and a mantissa beyond i128.
+		assert_eq!(decimal("0.0000000000000000001").checked_mul(decimal("0.00000000000000000001")), None);
+		assert_eq!(decimal("100000000000000000").checked_add(decimal("0.0000000000000000000000000000000000001")), None);
+
+		assert_eq!(decimal("1060").checked_div(decimal("100"), 2).map(|quotient| quotient.to_string()), Some("10.60".to_string()));
+		assert_eq!(decimal("2").checked_div(decimal("3"), 3).map(|quotient| quotient.to_string()),      Some("0.667".to_string()));
+		assert_eq!(decimal("-1").checked_div(decimal("8"), 2).map(|quotient| quotient.to_string()),     Some("-0.13".to_string()));
+		assert_eq!(decimal("1").checked_div(Decimal::ZERO, 2),                                          None);
+	}
+
+	#[test]
+	fn comparison() {
+		assert_eq!(decimal("1.50"), decimal("1.5"));
+		assert!(decimal("-2") < decimal("-1.99"));
+		assert!(decimal("0.000000000000000001") > Decimal::ZERO);
+		assert!(decimal("100000000000000000") > decimal("99999999999999999.9"));
+		assert_eq!(decimal("-0.0"), Decimal::ZERO);
+	}
+}
@@ -0,0 +1,210 @@
+//! Module with a push-style decoder for input that arrives in chunks (e.g. reads from a socket).
+//! Bytes are fed as they arrive and the decoder hands out the messages and the bytes between them.
+//! A message can be split across any number of chunks.
+
+use crate::parser::{FixError, field::Field, message::Message, scanner::FIX_START, state::{CHECK_SUM, ParserState}};
+
+/// Decoded part of the input.
+#[derive(Debug, PartialEq)]
+pub enum Event {
+	/// Bytes that are not part of a FIX message.
+	Passthrough(Vec<u8>),
+	/// A complete FIX message.
+	Message(Message),
+	/// Start of a message (`8=FIX`) that is not valid FIX.
+	Invalid(Vec<u8>),
+}
+
+#[derive(Debug)]
+pub struct Decoder {
+	field_delimiter: u8,
+	parser_state:    ParserState,
+	parsed_fields:   Vec<Field>,
+}
+
+impl Decoder {
+	#[must_use]
+	pub fn new(field_delimiter: u8) -> Self {
+		Self {
+			field_delimiter,
+			parser_state:  ParserState::new(),
+			parsed_fields: Vec::new(),
+		}
+	}
+
+	/// Decodes the bytes.
+	/// Bytes of a message that is not complete yet are kept until the rest of the message is fed.
+	/// The returned iterator has to be consumed to the end, or the bytes after the last event taken are lost.
+	pub fn feed<'d, 'b>(&'d mut self, bytes: &'b [u8]) -> Feed<'d, 'b> {
+		Feed { decoder: self, bytes, position: 0, passthrough: Vec::new(), next: None }
+	}
+
+	/// Ends the input and returns the bytes of any message that was not completed.
+	#[must_use]
+	pub fn finish(mut self) -> Vec<u8> {
+		let mut bytes = self.unwind_fields();
+		bytes.extend(self.parser_state.unwind());
+		bytes
+	}
+
+	/// Parses one byte. Returns the message when its CheckSum field has been parsed and the bytes
+	/// when they turned out not to be part of a FIX message.
+	#[inline]
+	pub(crate) fn consume(&mut self, byte: u8) -> Result<Option<Message>, FixError> {
+		if byte != self.field_delimiter {
+			self.parse_field(byte)
+		}
+		else {
+			self.finish_field()
+		}
+	}
+
+	#[inline]
+	fn parse_field(&mut self, byte: u8) -> Result<Option<Message>, FixError> {
+		match self.parser_state.consume(byte) {
+			Ok(()) => Ok(None),
+			Err(e) => {
+				if self.parsed_fields.is_empty() {
+					// No parsed fields yet - just return parser_state's error.
+					Err(e)
+				}
+				else {
+					// Unwind all parsed fields so far.
+					let mut bytes = self.unwind_fields();
+					bytes.extend(e.bytes());
+
+					Err(FixError::NotFix(bytes))
+				}
+			}
+		}
+	}
+
+	fn finish_field(&mut self) -> Result<Option<Message>, FixError> {
+		match self.parser_state.finish_field() {
+			Ok(field) => {
+				let tag = field.tag();
+				self.parsed_fields.push(field);
+
+				if tag.number() == CHECK_SUM {
+					let message = Message::new(self.parsed_fields.drain(..).collect());
+					Ok(Some(message))
+				}
+				else {
+					Ok(None)
+				}
+			}
+			Err(e) => {
+				// Unwind all parsed fields so far.
+				let mut bytes = self.unwind_fields();
+				bytes.extend(e.bytes());
+				bytes.push(self.field_delimiter); // Include the delimiter that caused the error.
+
+				Err(FixError::NotFix(bytes))
+			}
+		}
+	}
+
+	fn unwind_fields(&mut self) -> Vec<u8> {
+		// Unwind parsed fields.
+		let mut bytes = vec![];
+		for field in self.parsed_fields.drain(..) {
+			let mut field_bytes = field.bytes();
+			bytes.append(&mut field_bytes);
+			bytes.push(self.field_delimiter);
+		}
+		bytes
+	}
+}
+
+/// Iterator over the events of the bytes fed to a `Decoder`.
+/// Consecutive passthrough bytes are handed out together.
+/// The bytes are decoded as the iterator is advanced: bytes not reached when it is dropped are lost.
+#[derive(Debug)]
+#[must_use = "bytes are only decoded as the iterator is advanced"]
+pub struct Feed<'d, 'b> {
+	decoder:     &'d mut Decoder,
+	bytes:       &'b [u8],
+	position:    usize,
+	passthrough: Vec<u8>,
+	/// Event found while passthrough bytes before it were still to be handed out.
+	next:        Option<Event>,
+}
+
+impl Iterator for Feed<'_, '_> {
+	type Item = Event;
+
+	fn next(&mut self) -> Option<Event> {
+		if let Some(event) = self.next.take() {
+			return Some(event);
+		}
+
+		while let Some(byte) = self.bytes.get(self.position) {
+			self.position += 1;
+			let event = match self.decoder.consume(*byte) {
+				Ok(None)                     => continue,
+				Ok(Some(message))            => Event::Message(message),
+				Err(FixError::NotFixStart)   => {
+					self.passthrough.push(*byte);
+					continue;
+				}
+				Err(FixError::NotFix(bytes)) => {
+					// Like the `Scanner`, only count a start with `8=FIX` as an invalid message.
+					if !bytes.starts_with(FIX_START) {
+						self.passthrough.extend(bytes);
+						continue;
+					}
+					Event::Invalid(bytes)
+				}
+			};
+			if self.passthrough.is_empty() {
+				return Some(event);
+			}
+			self.next = Some(event);
+			return Some(Event::Passthrough(std::mem::take(&mut self.passthrough)));
+		}
+
+		(!self.passthrough.is_empty()).then(|| Event::Passthrough(std::mem::take(&mut self.passthrough)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn describe(events: impl Iterator<Item = Event>) -> Vec<String> {
+		events
+			.map(|event| match event {
+				Event::Message(message)   => format!("Message {}", message.into_iter().map(|field| String::from_utf8_lossy(&field.bytes()).into_owned()).collect::<Vec<_>>().join("|")),
+				Event::Passthrough(bytes) => format!("Passthrough {:?}", String::from_utf8_lossy(&bytes)),
+				Event::Invalid(bytes)     => format!("Invalid {:?}", String::from_utf8_lossy(&bytes)),
+			})
+			.collect()
+	}
+
+	#[test]
+	fn messages_split_across_chunks() {
+		let mut decoder = Decoder::new(b'|');
+
+		assert_eq!(describe(decoder.feed(b"log: 8=FIX.4.2|9=5|35")), ["Passthrough \"log: \""]);
+		assert_eq!(describe(decoder.feed(b"=0|10=1")), Vec::<String>::new());
+		assert_eq!(describe(decoder.feed(b"23| end\n8=FIX.4.2|9=5|35=1|10=000|")), [
+			"Message 8=FIX.4.2|9=5|35=0|10=123",
+			"Passthrough \" end\\n\"",
+			"Message 8=FIX.4.2|9=5|35=1|10=000",
+		]);
+	}
+
+	#[test]
+	fn invalid_messages_are_passed_through() {
+		let mut decoder = Decoder::new(b'|');
+
+		assert_eq!(describe(decoder.feed(b"8=FIX.4.2|9=5|35| 8=FIX.4.2|35=0|10=000|")), [
+			"Invalid \"8=FIX.4.2|9=5|35|\"",
+			"Passthrough \" \"",
+			"Message 8=FIX.4.2|35=0|10=000",
+		]);
+		assert_eq!(describe(decoder.feed(b"price 8=9 val 8=Hello|x")), ["Passthrough \"price 8=9 val 8=Hello|x\""]);
+		assert_eq!(describe(decoder.feed(b"8=FIX.4.4|35=")), Vec::<String>::new());
+		assert_eq!(decoder.finish(), b"8=FIX.4.4|35=");
+	}
+}
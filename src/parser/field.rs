@@ -1,9 +1,11 @@
 use crate::parser::{FixError, tag::Tag};
 
-const TAG_DELIMITER:  u8    = b'=';
-const MAX_TAG_LENGTH: usize = 6;
+pub const TAG_DELIMITER:  u8    = b'=';
+/// Field delimiter on the wire.
+pub const SOH:            u8    = b'\x01';
+pub const MAX_TAG_LENGTH: usize = 6;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Field {
 	tag: Tag,
 	/// Bytes for the value.
@@ -12,12 +14,14 @@ pub struct Field {
 }
 
 impl Field {
+	#[must_use]
 	pub fn new(tag: Tag, value_bytes: Vec<u8>) -> Self {
 		Self {
 			tag, value_bytes
 		}
 	}
 
+	#[must_use]
 	pub fn bytes(&self) -> Vec<u8> {
 		let mut bytes = self.tag.to_string().into_bytes();
 		bytes.push(TAG_DELIMITER);
@@ -25,13 +29,53 @@ impl Field {
 		bytes
 	}
 
+	#[must_use]
 	pub fn value_bytes(&self) -> &[u8] {
 		&self.value_bytes
 	}
 	
+	#[must_use]
 	pub fn tag(&self) -> Tag {
 		self.tag
 	}
+
+	#[must_use]
+	pub fn to_ref(&self) -> FieldRef<'_> {
+		FieldRef::new(self.tag, &self.value_bytes)
+	}
+}
+
+/// Field borrowing its value from an input buffer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldRef<'a> {
+	tag: Tag,
+	/// Bytes for the value.
+	/// (Does not include the field delimiter.)
+	value_bytes: &'a [u8],
+}
+
+impl<'a> FieldRef<'a> {
+	#[must_use]
+	pub fn new(tag: Tag, value_bytes: &'a [u8]) -> Self {
+		Self {
+			tag, value_bytes
+		}
+	}
+
+	#[must_use]
+	pub fn value_bytes(&self) -> &'a [u8] {
+		self.value_bytes
+	}
+
+	#[must_use]
+	pub fn tag(&self) -> Tag {
+		self.tag
+	}
+
+	#[must_use]
+	pub fn into_owned(self) -> Field {
+		Field::new(self.tag, self.value_bytes.to_vec())
+	}
 }
 
 #[derive(Debug, PartialEq)]
@@ -54,10 +98,12 @@ impl Default for FieldParser {
 }
 
 impl FieldParser {
+	#[must_use]
 	pub fn new () -> Self {
 		Self::default()
 	}
 
+	#[must_use]
 	pub fn fresh(&self) -> bool {
 		match self {
 			FieldParser::ParseTag   { tag } => tag.is_empty(),
@@ -89,6 +135,7 @@ impl FieldParser {
 		Ok(())
 	}
 
+	#[must_use]
 	pub fn tag_bytes_count(&self) -> usize {
 		match self {
 			FieldParser::ParseTag   { tag     } => tag.len(),
@@ -96,6 +143,7 @@ impl FieldParser {
 		}
 	}
 
+	#[must_use]
 	pub fn value_bytes_count(&self) -> usize {
 		match self {
 			FieldParser::ParseTag   { ..        } => 0,
@@ -103,6 +151,7 @@ impl FieldParser {
 		}
 	}
 
+	#[must_use]
 	pub fn bytes(self) -> Vec<u8> {
 		match self {
 			FieldParser::ParseTag   { tag        } => tag,
@@ -1,14 +1,114 @@
-use crate::parser::field::Field;
+use crate::{dictionary::FieldType, parser::{decimal::Decimal, field::{Field, FieldRef, SOH}, state::CHECK_SUM, tag::Tag, value::{self, FieldError, LocalMktDate}}, timestamp::Timestamp};
 
-#[derive(Debug)]
+const BODY_LENGTH: u32 = 9;
+const MSG_TYPE:    Tag = Tag::new(35);
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Message {
 	fields: Vec<Field>,
 }
 
 impl Message {
+	#[must_use]
 	pub fn new(fields: Vec<Field>) -> Self {
 		Self { fields }
 	}
+
+	/// Borrowed view of the message.
+	pub fn to_ref(&self) -> MessageRef<'_> {
+		MessageRef::new(self.fields.iter().map(Field::to_ref).collect())
+	}
+
+	/// BodyLength (9) calculated from the fields between BodyLength and CheckSum.
+	#[must_use]
+	pub fn body_length(&self) -> usize {
+		self.fields.iter()
+			.skip_while(|field| field.tag().number() != BODY_LENGTH)
+			.skip(1)
+			.take_while(|field| field.tag().number() != CHECK_SUM)
+			.map(|field| field.bytes().len() + 1)
+			.sum()
+	}
+
+	/// CheckSum (10) calculated from the fields before CheckSum.
+	/// Fields are delimited by SOH as on the wire - even if the log used another separator.
+	pub fn check_sum(&self) -> u8 {
+		self.fields.iter()
+			.take_while(|field| field.tag().number() != CHECK_SUM)
+			.flat_map(|field| field.bytes().into_iter().chain([SOH]))
+			.fold(0u8, u8::wrapping_add)
+	}
+
+	pub fn push(&mut self, field: Field) {
+		self.fields.push(field);
+	}
+
+	/// First field with the tag.
+	#[must_use]
+	pub fn get(&self, tag: Tag) -> Option<&Field> {
+		self.fields.iter().find(|field| field.tag() == tag)
+	}
+
+	/// All fields with the tag (e.g. in the instances of a repeating group).
+	pub fn get_all(&self, tag: Tag) -> impl Iterator<Item = &Field> {
+		self.fields.iter().filter(move |field| field.tag() == tag)
+	}
+
+	/// Value of the first field with the tag.
+	#[must_use]
+	pub fn value(&self, tag: Tag) -> Option<&[u8]> {
+		self.get(tag).map(Field::value_bytes)
+	}
+
+	/// MsgType (35).
+	#[must_use]
+	pub fn msg_type(&self) -> Option<&[u8]> {
+		self.value(MSG_TYPE)
+	}
+
+	pub fn get_str(&self, tag: Tag) -> Result<&str, FieldError> {
+		self.typed(tag, FieldType::String, value::string)
+	}
+
+	pub fn get_int(&self, tag: Tag) -> Result<i64, FieldError> {
+		self.typed(tag, FieldType::Int, value::int)
+	}
+
+	/// Float, Qty, Price, Amt ... as an exact decimal.
+	pub fn get_decimal(&self, tag: Tag) -> Result<Decimal, FieldError> {
+		self.typed(tag, FieldType::Float, value::decimal)
+	}
+
+	pub fn get_char(&self, tag: Tag) -> Result<char, FieldError> {
+		self.typed(tag, FieldType::Char, value::char)
+	}
+
+	pub fn get_bool(&self, tag: Tag) -> Result<bool, FieldError> {
+		self.typed(tag, FieldType::Boolean, value::boolean)
+	}
+
+	pub fn get_utc_timestamp(&self, tag: Tag) -> Result<Timestamp, FieldError> {
+		self.typed(tag, FieldType::UtcTimestamp, value::utc_timestamp)
+	}
+
+	pub fn get_local_mkt_date(&self, tag: Tag) -> Result<LocalMktDate, FieldError> {
+		self.typed(tag, FieldType::LocalMktDate, value::local_mkt_date)
+	}
+
+	/// Value of the first field with the tag parsed as the data type.
+	fn typed<'a, T>(&'a self, tag: Tag, field_type: FieldType, parse: impl Fn(&'a [u8]) -> Option<T>) -> Result<T, FieldError> {
+		let value = self.value(tag).ok_or(FieldError::Missing(tag))?;
+		parse(value).ok_or_else(|| FieldError::Format { tag, field_type, value: value.to_vec() })
+	}
+}
+
+impl IntoIterator for Message {
+	type IntoIter = std::vec::IntoIter<Field>;
+	type Item     = Field;
+
+	fn into_iter(self) -> std::vec::IntoIter<Field> {
+		self.fields.into_iter()
+	}
 }
 
 impl<'a> IntoIterator for &'a Message {
@@ -19,3 +119,96 @@ impl<'a> IntoIterator for &'a Message {
 		self.fields.iter()
 	}
 }
+
+/// Fields of an owned or a borrowed message, so that both can be formatted as they are.
+pub trait Fields {
+	fn fields(&self) -> impl Iterator<Item = FieldRef<'_>>;
+}
+
+impl Fields for Message {
+	fn fields(&self) -> impl Iterator<Item = FieldRef<'_>> {
+		self.fields.iter().map(Field::to_ref)
+	}
+}
+
+impl Fields for MessageRef<'_> {
+	fn fields(&self) -> impl Iterator<Item = FieldRef<'_>> {
+		self.fields.iter().copied()
+	}
+}
+
+/// Message with fields borrowing their values from an input buffer.
+/// Only the list of fields is allocated - convert to an owned `Message` when it must outlive the buffer.
+#[derive(Debug, PartialEq)]
+pub struct MessageRef<'a> {
+	fields: Vec<FieldRef<'a>>,
+}
+
+impl<'a> MessageRef<'a> {
+	#[must_use]
+	pub fn new(fields: Vec<FieldRef<'a>>) -> Self {
+		Self { fields }
+	}
+
+	pub fn into_owned(self) -> Message {
+		Message::new(self.fields.into_iter().map(FieldRef::into_owned).collect())
+	}
+}
+
+impl<'m, 'a> IntoIterator for &'m MessageRef<'a> {
+	type IntoIter = std::slice::Iter<'m, FieldRef<'a>>;
+	type Item     = &'m FieldRef<'a>;
+
+	fn into_iter(self) -> std::slice::Iter<'m, FieldRef<'a>> {
+		self.fields.iter()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::parser::tag::Tag;
+
+	#[test]
+	fn borrowed_message_round_trip() {
+		let input   = b"FIX.4.2D";
+		let fields  = vec![
+			FieldRef::new(Tag::try_from(8).unwrap(), input.get(..7).unwrap()),
+			FieldRef::new(Tag::try_from(35).unwrap(), input.get(7..).unwrap()),
+		];
+		let message = MessageRef::new(fields.clone()).into_owned();
+
+		assert_eq!(message.to_ref(), MessageRef::new(fields));
+	}
+
+	#[test]
+	fn body_length_and_check_sum() {
+		let fields  = [(8, "FIX.4.2"), (9, "65"), (35, "A"), (49, "SERVER"), (56, "CLIENT"), (34, "177"), (52, "20090107-18:15:16"), (98, "0"), (108, "30"), (10, "062")];
+		let message = Message::new(fields.iter().map(|(tag, value)| Field::new(Tag::try_from(*tag).unwrap(), value.as_bytes().to_vec())).collect());
+
+		assert_eq!(message.body_length(), 65);
+		assert_eq!(message.check_sum(),   62);
+	}
+
+	#[test]
+	fn typed_accessors() {
+		let fields  = [(35, "8"), (44, "-1.25"), (453, "2"), (448, "P1"), (448, "P2"), (54, "1"), (43, "Y"), (60, "20200302-11:14:36.500"), (52, "20200302-11:14:36.5"), (64, "20200304"), (38, "1e3"), (75, "20200230")];
+		let message = Message::new(fields.iter().map(|(tag, value)| Field::new(Tag::new(*tag), value.as_bytes().to_vec())).collect());
+
+		assert_eq!(message.msg_type(),                        Some(&b"8"[..]));
+		assert_eq!(message.get_decimal(Tag::new(44)),         Ok(Decimal::parse(b"-1.25").unwrap()));
+		assert_eq!(message.get_int(Tag::new(453)),            Ok(2));
+		assert_eq!(message.get_str(Tag::new(448)),            Ok("P1"));
+		assert_eq!(message.get_all(Tag::new(448)).count(),    2);
+		assert_eq!(message.get_char(Tag::new(54)),            Ok('1'));
+		assert_eq!(message.get_bool(Tag::new(43)),            Ok(true));
+		assert_eq!(message.get_utc_timestamp(Tag::new(60)).map(|time| time.nanoseconds()), Ok(1_583_147_676_500_000_000));
+		assert_eq!(message.get_local_mkt_date(Tag::new(64)),  Ok(LocalMktDate { year: 2020, month: 3, day: 4 }));
+
+		assert_eq!(message.get_int(Tag::new(58)).unwrap_err().to_string(),             "Tag 58 is missing");
+		assert_eq!(message.get_decimal(Tag::new(38)).unwrap_err().to_string(),         "Tag 38 value '1e3' is not a valid Float");
+		assert_eq!(message.get_int(Tag::new(44)).unwrap_err().to_string(),             "Tag 44 value '-1.25' is not a valid Int");
+		assert_eq!(message.get_local_mkt_date(Tag::new(75)).unwrap_err().to_string(),  "Tag 75 value '20200230' is not a valid LocalMktDate");
+		assert_eq!(message.get_utc_timestamp(Tag::new(52)).unwrap_err().to_string(),   "Tag 52 value '20200302-11:14:36.5' is not a valid UtcTimestamp");
+	}
+}
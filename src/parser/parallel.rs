@@ -0,0 +1,208 @@
+//! Module for processing large input buffers on multiple threads.
+//! The input is split into chunks at line boundaries and each chunk is scanned and formatted by
+//! its own `Scanner` on a separate thread. The formatted chunks are written in the original order.
+//!
+//! A chunk boundary is never placed inside a message: if the last `8=FIX` before a boundary that starts
+//! a valid message starts one that ends after it, the boundary is moved to the end of the line holding
+//! the end of the message. An `8=FIX` that does not start a valid message (e.g. in `58=FIXED`) may be
+//! inside a message starting before it, so the search goes on before it - but only for `MAX_MESSAGE_LENGTH`
+//! bytes, so that input full of such starts is still split in linear time.
+
+use std::{io::{Error, Write}, thread};
+
+use memchr::{memchr, memmem};
+
+use crate::{args::Args, parser::{Flush, scanner::{self, FIX_START, Scanner}}};
+
+/// Size of the chunks processed by one thread.
+const CHUNK_SIZE: usize = 16 << 20;
+/// Longest message looked for across a chunk boundary (a longer one is split at the end of the line).
+const MAX_MESSAGE_LENGTH: usize = 64 << 10;
+
+pub fn process(input: &[u8], output: &mut impl Write, args: Args, flush: Flush) -> Result<(), Error> {
+	process_chunks(input, output, args, flush, CHUNK_SIZE)
+}
+
+fn process_chunks(input: &[u8], output: &mut impl Write, args: Args, flush: Flush, chunk_size: usize) -> Result<(), Error> {
+	let field_delimiter = args.field_separator as u8;
+	let jobs            = args.jobs;
+	let chunks          = chunks(input, field_delimiter, chunk_size);
+
+	// Process `jobs` chunks at a time to bound the amount of buffered output.
+	for batch in chunks.chunks(jobs.get()) {
+		let outputs = thread::scope(|scope| {
+			let handles: Vec<_> = batch.iter()
+				.map(|chunk| {
+					let args = args.clone();
+					scope.spawn(move || {
+						let mut output = ChunkOutput::default();
+						scanner::process(chunk, &mut output, args, flush).map(|()| output)
+					})
+				})
+				.collect();
+
+			handles.into_iter()
+				.map(|handle| handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+				.collect::<Result<Vec<_>, Error>>()
+		})?;
+
+		for chunk_output in outputs {
+			chunk_output.write_to(output, flush)?;
+		}
+	}
+
+	output.flush()
+}
+
+/// Formatted output of a chunk with the positions at which the scanner flushed it.
+#[derive(Default)]
+struct ChunkOutput {
+	bytes:   Vec<u8>,
+	flushes: Vec<usize>,
+}
+
+impl ChunkOutput {
+	/// Writes the output, flushing after each message with `Flush::EachMessage`.
+	fn write_to(&self, output: &mut impl Write, flush: Flush) -> Result<(), Error> {
+		if flush == Flush::EndOfInput {
+			return output.write_all(&self.bytes);
+		}
+
+		let mut written = 0;
+		for &flushed in &self.flushes {
+			output.write_all(self.bytes.get(written..flushed).unwrap_or_default())?;
+			output.flush()?;
+			written = flushed;
+		}
+		output.write_all(self.bytes.get(written..).unwrap_or_default())
+	}
+}
+
+impl Write for ChunkOutput {
+	fn write(&mut self, bytes: &[u8]) -> Result<usize, Error> {
+		self.bytes.write(bytes)
+	}
+
+	fn flush(&mut self) -> Result<(), Error> {
+		self.flushes.push(self.bytes.len());
+		Ok(())
+	}
+}
+
+/// Splits the input in chunks of roughly `chunk_size` bytes ending at line boundaries.
+fn chunks(input: &[u8], field_delimiter: u8, chunk_size: usize) -> Vec<&[u8]> {
+	let mut chunks = Vec::new();
+	let mut start  = 0;
+
+	while start < input.len() {
+		let end = chunk_end(input, start, field_delimiter, chunk_size);
+		chunks.extend(input.get(start..end));
+		start = end;
+	}
+
+	chunks
+}
+
+fn chunk_end(input: &[u8], start: usize, field_delimiter: u8, chunk_size: usize) -> usize {
+	let mut end = line_end(input, start.saturating_add(chunk_size));
+
+	// Move the end past any message spanning it.
+	'extend: loop {
+		// Only starts and messages within `MAX_MESSAGE_LENGTH` of the end are looked at.
+		let window_start = end.saturating_sub(MAX_MESSAGE_LENGTH).max(start);
+		let Some(window) = input.get(window_start..end) else {
+			return input.len();
+		};
+		let scanner = Scanner::new(input.get(..end.saturating_add(MAX_MESSAGE_LENGTH)).unwrap_or(input), field_delimiter);
+
+		// The last start of a message decides: other starts may be inside a message starting before them.
+		for message_start in memmem::rfind_iter(window, FIX_START) {
+			match scanner.message_end(window_start + message_start) {
+				Some(message_end) if message_end > end => {
+					end = line_end(input, message_end);
+					continue 'extend;
+				}
+				Some(_) => return end,
+				None    => {}
+			}
+		}
+
+		return end;
+	}
+}
+
+/// Position after the first newline at or after `position` (or end of input).
+fn line_end(input: &[u8], position: usize) -> usize {
+	input.get(position..)
+		.and_then(|remaining| memchr(b'\n', remaining))
+		.map_or(input.len(), |offset| position + offset + 1)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::parser::COMMAND_NAME;
+
+	#[test]
+	fn chunks_at_line_boundaries() {
+		let input  = b"line 1\nline 2\nline 3\n";
+		let chunks = chunks(input, b'|', 8);
+		assert_eq!(chunks, vec![&b"line 1\nline 2\n"[..], b"line 3\n"]);
+	}
+
+	#[test]
+	fn message_spanning_chunks() {
+		let input  = b"line 1\n8=FIX.4.2|35=0|58=multi\nline|10=1|\nline 3\nline 4\n";
+		let chunks = chunks(input, b'|', 10);
+		assert_eq!(chunks, vec![&b"line 1\n8=FIX.4.2|35=0|58=multi\nline|10=1|\n"[..], b"line 3\nline 4\n"]);
+	}
+
+	#[test]
+	fn fix_start_inside_field() {
+		let input  = b"line 1\n8=FIX.4.2|35=0|58=FIXED 8=FIX\nline|10=1|\nline 3\nline 4\n";
+		let chunks = chunks(input, b'|', 10);
+		assert_eq!(chunks, vec![&b"line 1\n8=FIX.4.2|35=0|58=FIXED 8=FIX\nline|10=1|\n"[..], b"line 3\nline 4\n"]);
+	}
+
+	#[test]
+	fn long_message_split_at_line() {
+		let text   = "x\n".repeat(MAX_MESSAGE_LENGTH);
+		let input  = format!("8=FIX.4.2|35=0|58={}|10=1|\n", text);
+		let chunks = chunks(input.as_bytes(), b'|', 10);
+		assert_eq!(chunks.first().map(|chunk| chunk.len()), Some("8=FIX.4.2|35=0|58=x\n".len()));
+	}
+
+	#[test]
+	fn flush_each_message() {
+		let input      = b"8=FIX.4.2;35=0;10=1;\nline\n8=FIX.4.2;35=1;10=2;\n";
+		let args       = { use clap::Parser; Args::parse_from([COMMAND_NAME, "-s", ";", "-j", "2", "messages.txt"]) };
+		let mut output = ChunkOutput::default();
+		process_chunks(input, &mut output, args, Flush::EachMessage, 10).unwrap();
+
+		let mut expected = ChunkOutput::default();
+		scanner::process(input, &mut expected, self::args(), Flush::EachMessage).unwrap();
+
+		assert_eq!(String::from_utf8(output.bytes).unwrap(), String::from_utf8(expected.bytes).unwrap());
+		// Flushed after each message (and at the end of each chunk).
+		assert!(expected.flushes.iter().all(|flushed| output.flushes.contains(flushed)));
+	}
+
+	#[test]
+	fn same_output_as_sequential() {
+		let input = std::fs::read("test-data/messages.txt").unwrap();
+
+		let mut expected = Vec::new();
+		scanner::process(&input, &mut expected, args(), Flush::EndOfInput).unwrap();
+
+		let mut output = Vec::new();
+		let args       = { use clap::Parser; Args::parse_from([COMMAND_NAME, "-s", ";", "-j", "3", "messages.txt"]) };
+		process_chunks(&input, &mut output, args, Flush::EndOfInput, 1000).unwrap();
+
+		assert_eq!(String::from_utf8(output).unwrap(), String::from_utf8(expected).unwrap());
+	}
+
+	fn args() -> Args {
+		use clap::Parser;
+		Args::parse_from([COMMAND_NAME, "-s", ";"])
+	}
+}
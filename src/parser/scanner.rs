@@ -0,0 +1,217 @@
+//! Module with a fast path for scanning complete input buffers, e.g. memory-mapped files.
+//! The `Scanner` uses SIMD accelerated search (`memchr`) for the start of FIX messages and for
+//! field delimiters and parses messages as `MessageRef`s borrowing from the input buffer.
+//!
+//! Messages must start with `8=FIX`. For well-formed input the output is identical to the
+//! byte-by-byte `Parser` but malformed messages are passed through as is.
+
+use std::io::{Error, Write};
+
+use memchr::{memchr, memmem::Finder};
+
+use crate::{args::Args, dictionary::BaseDictionary, filter::BaseFilter, formatter::{FixFormatter, FromArgs, SimpleFormatter}, parser::{Flush, begin_string::MAX_BEGIN_STRING_LENGTH, field::{FieldRef, MAX_TAG_LENGTH, TAG_DELIMITER}, message::MessageRef, state::CHECK_SUM, tag::Tag}};
+
+pub const FIX_START: &[u8] = b"8=FIX";
+
+pub fn process(input: &[u8], output: &mut impl Write, args: Args, flush: Flush) -> Result<(), Error> {
+	let formatter = SimpleFormatter::<BaseDictionary, BaseFilter>::new(&args);
+	let scanner   = Scanner::new(input, args.field_separator as u8);
+
+	for scanned in scanner {
+		match scanned {
+			Scanned::Passthrough(bytes)
+			| Scanned::Invalid(bytes)   => output.write_all(bytes)?,
+			Scanned::Message(message)   => {
+				// Write message on new line.
+				output.write_all(b"\n")?;
+				formatter.format(&message, output)?;
+				if flush == Flush::EachMessage {
+					output.flush()?;
+				}
+			}
+		}
+	}
+
+	output.flush()
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Scanned<'a> {
+	/// Bytes that are not part of a FIX message.
+	Passthrough(&'a [u8]),
+	/// A complete FIX message.
+	Message(MessageRef<'a>),
+	/// Start of a message that is not valid FIX (passed through as is).
+	Invalid(&'a [u8]),
+}
+
+pub struct Scanner<'a> {
+	input:           &'a [u8],
+	position:        usize,
+	field_delimiter: u8,
+	fix_start:       Finder<'static>,
+}
+
+impl<'a> Scanner<'a> {
+	#[must_use]
+	pub fn new(input: &'a [u8], field_delimiter: u8) -> Self {
+		Self {
+			input,
+			position: 0,
+			field_delimiter,
+			fix_start: Finder::new(FIX_START),
+		}
+	}
+
+	/// Position after the message starting at `start` if it is a valid message.
+	#[must_use]
+	pub fn message_end(&self, start: usize) -> Option<usize> {
+		self.message(start).map(|(_, end)| end)
+	}
+
+	/// Parses a message starting at `start`.
+	/// Returns the message and the position after the message.
+	fn message(&self, start: usize) -> Option<(MessageRef<'a>, usize)> {
+		let mut fields   = Vec::new();
+		let mut position = start;
+
+		loop {
+			// Like the `Decoder`, the tag and the BeginString are only searched for up to their longest length
+			// so that a start without delimiters (e.g. in a log with another separator) is not scanned to the end.
+			let remaining    = self.input.get(position..)?;
+			let tag_length   = memchr(TAG_DELIMITER, prefix(remaining, MAX_TAG_LENGTH + 1))?;
+			let value        = remaining.get(tag_length + 1..)?;
+			let value_length = if fields.is_empty() {
+				memchr(self.field_delimiter, prefix(value, MAX_BEGIN_STRING_LENGTH + 1))?
+			}
+			else {
+				memchr(self.field_delimiter, value)?
+			};
+			let length       = tag_length + 1 + value_length;
+			let field        = parse_field(remaining.get(..length)?)?;
+
+			fields.push(field);
+			position += length + 1;
+
+			if field.tag().number() == CHECK_SUM {
+				return Some((MessageRef::new(fields), position));
+			}
+		}
+	}
+}
+
+impl<'a> Iterator for Scanner<'a> {
+	type Item = Scanned<'a>;
+
+	fn next(&mut self) -> Option<Scanned<'a>> {
+		let remaining = self.input.get(self.position..).filter(|r| !r.is_empty())?;
+
+		let Some(offset) = self.fix_start.find(remaining) else {
+			self.position = self.input.len();
+			return Some(Scanned::Passthrough(remaining));
+		};
+
+		if offset > 0 {
+			self.position += offset;
+			return remaining.get(..offset).map(Scanned::Passthrough);
+		}
+
+		match self.message(self.position) {
+			Some((message, end)) => {
+				self.position = end;
+				Some(Scanned::Message(message))
+			}
+			None => {
+				// Not a valid message - pass through the '8' and resume the search after it.
+				self.position += 1;
+				remaining.get(..1).map(Scanned::Invalid)
+			}
+		}
+	}
+}
+
+/// First `length` bytes (or all of them).
+fn prefix(bytes: &[u8], length: usize) -> &[u8] {
+	bytes.get(..length).unwrap_or(bytes)
+}
+
+/// Splits a field in tag and value.
+fn parse_field(field: &[u8]) -> Option<FieldRef<'_>> {
+	let split     = memchr(TAG_DELIMITER, field)?;
+	let tag_bytes = field.get(..split)?;
+	let value     = field.get(split + 1..)?;
+
+	if tag_bytes.is_empty() || tag_bytes.len() > MAX_TAG_LENGTH || value.is_empty() || !tag_bytes.iter().all(u8::is_ascii_digit) {
+		return None;
+	}
+
+	let number = tag_bytes.iter().fold(0u32, |number, digit| number * 10 + u32::from(digit - b'0'));
+	Tag::try_from(number).ok().map(|tag| FieldRef::new(tag, value))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::parser::COMMAND_NAME;
+
+	#[test]
+	fn passthrough_without_fix() {
+		let scanned: Vec<_> = Scanner::new(b"Hello, World! 8=FIY", b'\x01').collect();
+		assert_eq!(scanned, vec![Scanned::Passthrough(b"Hello, World! 8=FIY")]);
+	}
+
+	#[test]
+	fn embedded_message() {
+		let input           = b"INFO: 8=FIX.4.2|9=5|35=0|10=123|\n";
+		let scanned: Vec<_> = Scanner::new(input, b'|').collect();
+		assert_eq!(scanned, vec![
+			Scanned::Passthrough(b"INFO: "),
+			Scanned::Message(message(&[(8, "FIX.4.2"), (9, "5"), (35, "0"), (10, "123")])),
+			Scanned::Passthrough(b"\n"),
+		]);
+	}
+
+	#[test]
+	fn resynchronise_after_truncated_message() {
+		let input           = b"8=FIX.4.2|9=5|35\n8=FIX.4.2|35=0|10=1|";
+		let scanned: Vec<_> = Scanner::new(input, b'|').collect();
+		assert_eq!(scanned, vec![
+			Scanned::Invalid(b"8"),
+			Scanned::Passthrough(b"=FIX.4.2|9=5|35\n"),
+			Scanned::Message(message(&[(8, "FIX.4.2"), (35, "0"), (10, "1")])),
+		]);
+	}
+
+	#[test]
+	fn other_separator() {
+		// Without SOH delimiters no start is a message, and each is given up after the BeginString.
+		let input   = "8=FIX.4.2|9=5|35=0|49=SERVER|56=CLIENT|10=123|\n".repeat(20_000);
+		let scanned = Scanner::new(input.as_bytes(), b'\x01').filter(|scanned| matches!(scanned, Scanned::Invalid(_))).count();
+		assert_eq!(scanned, 20_000);
+
+		let input = b"8=FIX.4.2\x01999999999=1\x0110=1\x01 8=FIX.4.2.3.4.5.6.7.8.9.0.1\x0110=1\x01";
+		assert!(Scanner::new(input, b'\x01').all(|scanned| !matches!(scanned, Scanned::Message(_))));
+	}
+
+	#[test]
+	fn same_output_as_parser() {
+		let input = std::fs::read("test-data/messages.txt").unwrap();
+
+		let mut expected = Vec::new();
+		crate::parser::process(&mut &input[..], &mut expected, args(), Flush::EndOfInput).unwrap();
+
+		let mut output = Vec::new();
+		process(&input, &mut output, args(), Flush::EndOfInput).unwrap();
+
+		assert_eq!(String::from_utf8(output).unwrap(), String::from_utf8(expected).unwrap());
+	}
+
+	fn args() -> Args {
+		use clap::Parser;
+		Args::parse_from([COMMAND_NAME, "-s", ";"])
+	}
+
+	fn message(fields: &[(u32, &'static str)]) -> MessageRef<'static> {
+		MessageRef::new(fields.iter().map(|(tag, value)| FieldRef::new(Tag::try_from(*tag).unwrap(), value.as_bytes())).collect())
+	}
+}
@@ -35,6 +35,18 @@ impl TryFrom<Vec<u8>> for Tag {
 }
 
 impl Tag {
+	/// Tag for a known tag number (e.g. in constants).
+	///
+	/// # Panics
+	///
+	/// Panics if the number is 0 (at compile time when used for a constant).
+	#[must_use]
+	pub const fn new(number: u32) -> Self {
+		assert!(number > 0, "Tag numbers start at 1");
+		Tag(number)
+	}
+
+	#[must_use]
 	pub fn number(&self) -> u32 {
 		self.0
 	}
@@ -0,0 +1,107 @@
+//! Module for parsing field values according to their FIX data types.
+//! Values are parsed strictly: e.g. a Float has no exponent and a Boolean is `Y` or `N`.
+
+use std::fmt::Display;
+
+use crate::{dictionary::FieldType, parser::{decimal::Decimal, tag::Tag}, timestamp::Timestamp};
+
+/// Error when a field is looked up with one of the typed accessors of `Message`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldError {
+	/// The message has no field with the tag.
+	Missing(Tag),
+	/// The value is not valid for the data type.
+	Format { tag: Tag, field_type: FieldType, value: Vec<u8> },
+}
+
+impl Display for FieldError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			FieldError::Missing(tag)                      => write!(f, "Tag {} is missing", tag),
+			FieldError::Format { tag, field_type, value } => write!(f, "Tag {} value '{}' is not a valid {:?}", tag, String::from_utf8_lossy(value), field_type),
+		}
+	}
+}
+
+impl std::error::Error for FieldError {}
+
+/// LocalMktDate (`YYYYMMDD`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LocalMktDate {
+	pub year:  u32,
+	pub month: u32,
+	pub day:   u32,
+}
+
+fn digits(value: &[u8]) -> bool {
+	!value.is_empty() && value.iter().all(u8::is_ascii_digit)
+}
+
+/// Int (optionally negative).
+#[must_use]
+pub fn int(value: &[u8]) -> Option<i64> {
+	digits(value.strip_prefix(b"-").unwrap_or(value)).then(|| str::from_utf8(value).ok()?.parse().ok())?
+}
+
+/// Float, Qty, Price, Amt ... (e.g. `-1.5`, `10` or `.25`) as an exact decimal.
+#[must_use]
+pub fn decimal(value: &[u8]) -> Option<Decimal> {
+	Decimal::parse(value)
+}
+
+/// Char (a single character).
+#[must_use]
+pub fn char(value: &[u8]) -> Option<char> {
+	match value {
+		[byte] => Some(char::from(*byte)),
+		_      => None,
+	}
+}
+
+/// Boolean (`Y` or `N`).
+#[must_use]
+pub fn boolean(value: &[u8]) -> Option<bool> {
+	match value {
+		b"Y" => Some(true),
+		b"N" => Some(false),
+		_    => None,
+	}
+}
+
+/// String (not empty).
+#[must_use]
+pub fn string(value: &[u8]) -> Option<&str> {
+	str::from_utf8(value).ok().filter(|value| !value.is_empty())
+}
+
+/// UTCTimestamp (`YYYYMMDD-HH:MM:SS` with optional milli-, micro-, nano- or picoseconds).
+#[must_use]
+pub fn utc_timestamp(value: &[u8]) -> Option<Timestamp> {
+	let layout = value.get(..8).is_some_and(digits) && value.get(8) == Some(&b'-') && value.get(17).is_none_or(|separator| *separator == b'.');
+	layout.then(|| Timestamp::parse(value))?
+}
+
+/// LocalMktDate (`YYYYMMDD`).
+#[must_use]
+pub fn local_mkt_date(value: &[u8]) -> Option<LocalMktDate> {
+	if value.len() != 8 || !digits(value) || Timestamp::parse(&[value, b"-00:00:00"].concat()).is_none() {
+		return None;
+	}
+	let number = |range| str::from_utf8(value.get(range)?).ok()?.parse().ok();
+	Some(LocalMktDate { year: number(0..4)?, month: number(4..6)?, day: number(6..8)? })
+}
+
+/// Whether the value is valid for the data type.
+#[must_use]
+pub fn valid(field_type: FieldType, value: &[u8]) -> bool {
+	match field_type {
+		FieldType::Int          => int(value).is_some(),
+		FieldType::Float        => decimal(value).is_some(),
+		FieldType::Char         => char(value).is_some(),
+		FieldType::Boolean      => boolean(value).is_some(),
+		// Strings are not necessarily UTF-8 (e.g. Latin-1 text).
+		FieldType::String       => !value.is_empty(),
+		FieldType::UtcTimestamp => utc_timestamp(value).is_some(),
+		FieldType::LocalMktDate => local_mkt_date(value).is_some(),
+	}
+}
@@ -0,0 +1,364 @@
+//! Module for a proxy between FIX engines (`nfix proxy`) that prints the messages going each way.
+//! Connections accepted on `--listen` are forwarded to `--upstream`, and the bytes of each direction are
+//! decoded with their own `Decoder` as they pass.
+//!
+//! Without rules the traffic is forwarded unchanged as it arrives. Rules (see `rule::Rule`) drop, delay or
+//! change matching messages for fault injection: messages are then forwarded once complete. Messages no rule
+//! changed are forwarded as received, and changed messages get their BodyLength and CheckSum calculated again.
+//! A delayed message holds back the messages after it in the same direction, so their order is kept.
+
+pub mod rule;
+
+use std::{fs::File, io::{Error, IsTerminal, Read, Write}, net::{Shutdown, SocketAddr, TcpStream}, sync::{Arc, mpsc::{self, Sender}}, thread, time::{Duration, SystemTime}};
+
+use crate::{args::{Args, ProxyArgs}, dictionary::Dictionary, encoder, filter::Filter, formatter::{FixFormatter, FromArgs, SimpleFormatter}, parser::{FixError, Flush, decoder::Decoder, field::SOH, message::Message}, proxy::rule::{Outcome, Rule}, session, timestamp::Timestamp};
+
+const GREEN: &str = "\x1b[32m";
+const CYAN:  &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Message that passed the proxy (as forwarded, or as received if dropped).
+#[derive(Debug)]
+pub struct Traffic {
+	/// When the message was received (since the epoch).
+	pub time:        Duration,
+	pub source:      SocketAddr,
+	pub destination: SocketAddr,
+	/// Whether the message went from the client to the upstream.
+	pub to_upstream: bool,
+	pub message:     Message,
+	pub outcome:     Outcome,
+}
+
+#[derive(Debug)]
+pub enum Report {
+	Traffic(Traffic),
+	/// Connection opened or closed (at the time since the epoch).
+	Note(Duration, String),
+}
+
+#[derive(Debug)]
+pub struct Proxy {
+	upstream: String,
+	rules:    Vec<Rule>,
+}
+
+impl Proxy {
+	#[must_use]
+	pub fn new(args: &ProxyArgs) -> Proxy {
+		Proxy { upstream: args.upstream.clone(), rules: args.rules.clone() }
+	}
+
+	/// Connects the client to the upstream and forwards the traffic both ways until both directions are closed.
+	pub fn forward(self: &Arc<Self>, client: TcpStream, reports: &Sender<Report>) -> Result<(), Error> {
+		let client_address = client.peer_addr()?;
+		let upstream       = match TcpStream::connect(self.upstream.as_str()) {
+			Ok(upstream) => upstream,
+			Err(error)   => {
+				note(reports, format!("{} could not connect to {}: {}", client_address, self.upstream, error));
+				return Err(error);
+			}
+		};
+		let upstream_address = upstream.peer_addr()?;
+		client.set_nodelay(true)?;
+		upstream.set_nodelay(true)?;
+		note(reports, format!("{} connected to {}", client_address, upstream_address));
+
+		let inbound = {
+			let (proxy, reports)      = (Arc::clone(self), reports.clone());
+			let (source, destination) = (upstream.try_clone()?, client.try_clone()?);
+			thread::spawn(move || proxy.pump(source, destination, (upstream_address, client_address), false, &reports))
+		};
+		self.pump(client, upstream, (client_address, upstream_address), true, reports);
+		inbound.join().ok();
+		Ok(())
+	}
+
+	/// Forwards one direction until its source is closed, then closes the destination for writing.
+	fn pump(&self, mut source: TcpStream, mut destination: TcpStream, addresses: (SocketAddr, SocketAddr), to_upstream: bool, reports: &Sender<Report>) {
+		let (from, to) = addresses;
+		match self.copy(&mut source, &mut destination, addresses, to_upstream, reports) {
+			Ok(())     => {
+				destination.shutdown(Shutdown::Write).ok();
+				note(reports, format!("{} -> {} closed", from, to));
+			}
+			Err(error) => {
+				source.shutdown(Shutdown::Both).ok();
+				destination.shutdown(Shutdown::Both).ok();
+				note(reports, format!("{} -> {} closed: {}", from, to, error));
+			}
+		}
+	}
+
+	fn copy(&self, source: &mut TcpStream, destination: &mut TcpStream, (from, to): (SocketAddr, SocketAddr), to_upstream: bool, reports: &Sender<Report>) -> Result<(), Error> {
+		let mut decoder   = Decoder::new(SOH);
+		let mut buffer    = [0; 4096];
+		// Bytes of the message in progress as received.
+		let mut held      = Vec::new();
+		let mut forwarded = Vec::new();
+		loop {
+			let count = source.read(&mut buffer)?;
+			if count == 0 {
+				break;
+			}
+			let bytes = buffer.get(..count).unwrap_or_default();
+			if self.rules.is_empty() {
+				destination.write_all(bytes)?;
+			}
+
+			for byte in bytes {
+				match decoder.consume(*byte) {
+					Ok(None)                   => held.push(*byte),
+					Ok(Some(message))          => {
+						held.push(*byte);
+						let time               = now();
+						let (message, outcome) = rule::apply(&self.rules, message);
+						let message            = if outcome.changed { encoder::complete(message).unwrap_or_else(|incomplete| incomplete.message) } else { message };
+						if !self.rules.is_empty() && !outcome.dropped {
+							if !outcome.delay.is_zero() {
+								destination.write_all(&forwarded)?;
+								forwarded.clear();
+								thread::sleep(outcome.delay);
+							}
+							if outcome.changed { forwarded.extend(encoder::encode(&message, SOH)) } else { forwarded.extend_from_slice(&held) }
+						}
+						held.clear();
+						send(reports, Traffic { time, source: from, destination: to, to_upstream, message, outcome });
+					}
+					Err(FixError::NotFixStart) => forwarded.push(*byte),
+					Err(FixError::NotFix(_))   => {
+						// The decoder has given up the bytes it held, so they are passed through.
+						held.push(*byte);
+						forwarded.append(&mut held);
+					}
+				}
+			}
+			if !self.rules.is_empty() {
+				destination.write_all(&forwarded)?;
+			}
+			forwarded.clear();
+		}
+
+		if !self.rules.is_empty() {
+			destination.write_all(&held)?;
+		}
+		Ok(())
+	}
+}
+
+fn send(reports: &Sender<Report>, traffic: Traffic) {
+	// The reports are only gone once the proxy is shutting down.
+	reports.send(Report::Traffic(traffic)).ok();
+}
+
+fn note(reports: &Sender<Report>, text: String) {
+	reports.send(Report::Note(now(), text)).ok();
+}
+
+fn now() -> Duration {
+	SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default()
+}
+
+/// Writes the time and direction of the message (in colour if `colour`) and the formatted message.
+pub fn write_report(formatter: &impl FixFormatter, output: &mut impl Write, flush: Flush, colour: bool, report: &Report) -> Result<(), Error> {
+	match report {
+		Report::Traffic(traffic) => {
+			let mut notes = Vec::new();
+			if traffic.outcome.dropped {
+				notes.push("dropped".to_string());
+			}
+			if !traffic.outcome.delay.is_zero() {
+				notes.push(format!("delayed {} ms", traffic.outcome.delay.as_millis()));
+			}
+			if traffic.outcome.changed {
+				notes.push("changed".to_string());
+			}
+			let notes = if notes.is_empty() { String::new() } else { format!(" ({})", notes.join(", ")) };
+
+			let line = format!("{} {} -> {}{}", Timestamp::from_duration(traffic.time, 6).format_utc(), traffic.source, traffic.destination, notes);
+			match (colour, traffic.to_upstream) {
+				(false, _)    => writeln!(output, "{}", line)?,
+				(true, true)  => writeln!(output, "{}{}{}", GREEN, line, RESET)?,
+				(true, false) => writeln!(output, "{}{}{}", CYAN, line, RESET)?,
+			}
+			formatter.format(&traffic.message, output)?;
+		}
+		Report::Note(time, text) => writeln!(output, "{} {}", Timestamp::from_duration(*time, 6).format_utc(), text)?,
+	}
+	if flush == Flush::EachMessage {
+		output.flush()?;
+	}
+	Ok(())
+}
+
+/// Accepts connections and forwards each to the upstream, printing the messages (to the `--log` file if given).
+pub fn run<D: Dictionary, F: Filter>(args: &Args, proxy_args: &ProxyArgs, output: &mut impl Write, flush: Flush) -> Result<(), Error> {
+	let listener          = session::bind(&proxy_args.listen)?;
+	let formatter         = SimpleFormatter::<D, F>::new(args);
+	let proxy             = Arc::new(Proxy::new(proxy_args));
+	let (sender, reports) = mpsc::channel();
+
+	thread::spawn(move || {
+		for client in listener.incoming().flatten() {
+			let (proxy, sender) = (Arc::clone(&proxy), sender.clone());
+			// Failed connections have been reported.
+			thread::spawn(move || proxy.forward(client, &sender).ok());
+		}
+	});
+
+	let mut log = proxy_args.log.as_ref().map(File::create).transpose()?;
+	let colour  = log.is_none() && std::io::stdout().is_terminal();
+	for report in reports {
+		match &mut log {
+			Some(file) => write_report(&formatter, file, Flush::EachMessage, false, &report)?,
+			None       => write_report(&formatter, output, flush, colour, &report)?,
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::TcpListener;
+
+	use clap::Parser;
+
+	use super::*;
+	use crate::{args::Command, dictionary::BaseDictionary, filter::BaseFilter, parser::{COMMAND_NAME, field::Field, tag::Tag}};
+
+	/// Proxy between a client and an upstream listening on loopback sockets.
+	struct Loopback {
+		client:   TcpStream,
+		upstream: TcpStream,
+		reports:  mpsc::Receiver<Report>,
+	}
+
+	fn loopback(rules: &[&str]) -> Loopback {
+		let upstream_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let proxy_listener    = TcpListener::bind("127.0.0.1:0").unwrap();
+		let upstream_address  = upstream_listener.local_addr().unwrap().to_string();
+
+		let mut arguments = vec![COMMAND_NAME, "proxy", "--listen", "127.0.0.1:0", "--upstream", &upstream_address];
+		arguments.extend(rules.iter().flat_map(|rule| ["--rule", rule]));
+		let args = Args::parse_from(arguments);
+		let Some(Command::Proxy(proxy_args)) = &args.command else {
+			panic!("Should be the proxy command");
+		};
+		let proxy             = Arc::new(Proxy::new(proxy_args));
+		let (sender, reports) = mpsc::channel();
+		let proxy_address     = proxy_listener.local_addr().unwrap();
+		thread::spawn(move || proxy.forward(proxy_listener.accept().unwrap().0, &sender).unwrap());
+
+		let client        = TcpStream::connect(proxy_address).unwrap();
+		let (upstream, _) = upstream_listener.accept().unwrap();
+		Loopback { client, upstream, reports }
+	}
+
+	fn message(fields: &[(u32, &str)]) -> Vec<u8> {
+		let message = Message::new(fields.iter().map(|(tag, value)| Field::new(Tag::new(*tag), value.as_bytes().to_vec())).collect());
+		encoder::encode(&encoder::complete(message).unwrap(), SOH)
+	}
+
+	fn read(stream: &mut TcpStream, length: usize) -> Vec<u8> {
+		let mut bytes = vec![0; length];
+		stream.read_exact(&mut bytes).unwrap();
+		bytes
+	}
+
+	/// Reports without the times and addresses (which change from run to run).
+	/// Notes come last as the directions report independently of each other.
+	fn reports(reports: mpsc::Receiver<Report>) -> String {
+		let formatter  = SimpleFormatter::<BaseDictionary, BaseFilter>::default();
+		let mut output = Vec::new();
+		let mut notes  = String::new();
+		for report in reports {
+			match report {
+				Report::Traffic(mut traffic) => {
+					traffic.time        = Duration::ZERO;
+					traffic.source      = SocketAddr::from(([127, 0, 0, 1], if traffic.to_upstream { 1 } else { 2 }));
+					traffic.destination = SocketAddr::from(([127, 0, 0, 1], if traffic.to_upstream { 2 } else { 1 }));
+					write_report(&formatter, &mut output, Flush::EndOfInput, false, &Report::Traffic(traffic)).unwrap();
+				}
+				Report::Note(_, text)        => notes.push_str(if text.contains("connected") { "connected\n" } else { "closed\n" }),
+			}
+		}
+		String::from_utf8(output).unwrap() + &notes
+	}
+
+	#[test]
+	fn forwards_unchanged() {
+		let Loopback { mut client, mut upstream, reports: received } = loopback(&[]);
+		let order = [b"garbage ".as_slice(), &message(&[(8, "FIX.4.4"), (35, "D"), (49, "CLIENT"), (56, "EXCHANGE"), (55, "VOD.L")])].concat();
+		let (start, end) = order.split_at(20);
+		client.write_all(start).unwrap();
+		client.write_all(end).unwrap();
+		assert_eq!(read(&mut upstream, order.len()), order);
+
+		let report = message(&[(8, "FIX.4.4"), (35, "8"), (49, "EXCHANGE"), (56, "CLIENT"), (150, "0")]);
+		upstream.write_all(&report).unwrap();
+		assert_eq!(read(&mut client, report.len()), report);
+
+		client.shutdown(Shutdown::Write).unwrap();
+		assert_eq!(upstream.read(&mut [0; 16]).unwrap(), 0);
+		drop(upstream);
+		assert_eq!(client.read(&mut [0; 16]).unwrap(), 0);
+
+		insta::assert_snapshot!(reports(received), @"
+		1970-01-01 00:00:00.000000 127.0.0.1:1 -> 127.0.0.1:2
+		    35 : MsgType      = D
+		    49 : SenderCompID = CLIENT
+		    55 : Symbol       = VOD.L
+		    56 : TargetCompID = EXCHANGE
+		1970-01-01 00:00:00.000000 127.0.0.1:2 -> 127.0.0.1:1
+		    35 : MsgType      = 8
+		    49 : SenderCompID = EXCHANGE
+		    56 : TargetCompID = CLIENT
+		   150 : ExecType     = 0
+		connected
+		closed
+		closed
+		");
+	}
+
+	#[test]
+	fn applies_rules() {
+		let Loopback { mut client, mut upstream, reports: received } = loopback(&["drop if 35=0", "set 44=9.5 if 35=D", "delay 50 if 35=D"]);
+		let heartbeat = message(&[(8, "FIX.4.4"), (35, "0"), (49, "CLIENT")]);
+		let order     = message(&[(8, "FIX.4.4"), (35, "D"), (49, "CLIENT"), (44, "10")]);
+		client.write_all(&[heartbeat, order].concat()).unwrap();
+
+		let changed = message(&[(8, "FIX.4.4"), (35, "D"), (49, "CLIENT"), (44, "9.5")]);
+		assert_eq!(read(&mut upstream, changed.len()), changed);
+		drop(client);
+		assert_eq!(upstream.read(&mut [0; 16]).unwrap(), 0);
+		drop(upstream);
+
+		insta::assert_snapshot!(reports(received), @"
+		1970-01-01 00:00:00.000000 127.0.0.1:1 -> 127.0.0.1:2 (dropped)
+		    35 : MsgType      = 0
+		    49 : SenderCompID = CLIENT
+		1970-01-01 00:00:00.000000 127.0.0.1:1 -> 127.0.0.1:2 (delayed 50 ms, changed)
+		    35 : MsgType      = D
+		    44 : Price        = 9.5
+		    49 : SenderCompID = CLIENT
+		connected
+		closed
+		closed
+		");
+	}
+
+	#[test]
+	fn forwards_unmatched_messages_as_received() {
+		let Loopback { mut client, mut upstream, reports: received } = loopback(&["drop if 35=0"]);
+		// Leading zeros and a wrong CheckSum would not survive decoding and encoding again.
+		let order = b"log 8=FIX.4.4\x019=5\x01035=D\x01049=CLIENT\x0110=999\x01 8=FIX.4.4\x019=5\x01".to_vec();
+		client.write_all(&[message(&[(8, "FIX.4.4"), (35, "0")]), order.clone()].concat()).unwrap();
+		drop(client);
+
+		let mut forwarded = Vec::new();
+		upstream.read_to_end(&mut forwarded).unwrap();
+		assert_eq!(forwarded, order);
+		drop(upstream);
+		assert_eq!(received.iter().filter(|report| matches!(report, Report::Traffic(_))).count(), 2);
+	}
+}
@@ -0,0 +1,142 @@
+//! Module with the fault injection rules of the proxy, e.g. `drop if 35=0`, `delay 500 if 35=8`,
+//! `set 44=0 if 35=D 55=VOD.L` or `remove 58`. A rule applies to the messages that have all the fields
+//! after `if` (or to all messages without `if`). Fields and tags are given by number or by name.
+
+use std::{io::Error, time::Duration};
+
+use crate::{build, dictionary::{BaseDictionary, Dictionary}, parser::{field::Field, message::Message, tag::Tag}};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+	/// The message is not forwarded.
+	Drop,
+	/// The message (and the messages after it in the same direction) is forwarded later.
+	Delay(Duration),
+	/// The fields with the tag get the value (or the field is added).
+	Set(Field),
+	/// The fields with the tag are removed.
+	Remove(Tag),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+	action:     Action,
+	conditions: Vec<Field>,
+}
+
+/// What the rules did to a message.
+#[derive(Debug, Default, PartialEq)]
+pub struct Outcome {
+	pub dropped: bool,
+	pub delay:   Duration,
+	pub changed: bool,
+}
+
+impl Rule {
+	/// Parses `ACTION [if FIELD...]` with the action `drop`, `delay MILLISECONDS`, `set TAG=VALUE` or `remove TAG`.
+	pub fn parse(rule: &str) -> Result<Rule, String> {
+		let dictionary           = BaseDictionary::default();
+		let (action, conditions) = rule.split_once(" if ").unwrap_or((rule, ""));
+
+		let mut words = action.split_whitespace();
+		let action    = match (words.next(), words.next(), words.next()) {
+			(Some("drop"), None, None)          => Action::Drop,
+			(Some("delay"), Some(delay), None)  => Action::Delay(Duration::from_millis(delay.parse().map_err(|_| format!("Delay '{}' is not a number of milliseconds", delay))?)),
+			(Some("set"), Some(field), None)    => Action::Set(build::parse(field, &dictionary).map_err(|error| error.to_string())?),
+			(Some("remove"), Some(tag), None)   => Action::Remove(match tag.parse::<u32>() {
+				Ok(number) => Tag::try_from(number).map_err(|_| format!("Tag {} must be positive", number))?,
+				Err(_)     => dictionary.tag(tag).ok_or_else(|| format!("Unknown tag name '{}'", tag))?,
+			}),
+			_                                   => return Err(format!("'{}' is not drop, delay MILLISECONDS, set TAG=VALUE or remove TAG", action.trim())),
+		};
+		let conditions = conditions.split_whitespace()
+			.map(|condition| build::parse(condition, &dictionary))
+			.collect::<Result<Vec<Field>, Error>>()
+			.map_err(|error| error.to_string())?;
+
+		Ok(Rule { action, conditions })
+	}
+
+	#[must_use]
+	pub fn matches(&self, message: &Message) -> bool {
+		self.conditions.iter().all(|condition| message.value(condition.tag()) == Some(condition.value_bytes()))
+	}
+}
+
+/// Applies the matching rules in order (each to the message as changed by the rules before it).
+/// Rules after a matching `drop` are not applied.
+#[must_use]
+pub fn apply(rules: &[Rule], mut message: Message) -> (Message, Outcome) {
+	let mut outcome = Outcome::default();
+	for rule in rules {
+		if !rule.matches(&message) {
+			continue;
+		}
+		match &rule.action {
+			Action::Drop         => {
+				outcome.dropped = true;
+				break;
+			}
+			Action::Delay(delay) => outcome.delay += *delay,
+			Action::Set(field)   => {
+				message         = set(message, field);
+				outcome.changed = true;
+			}
+			Action::Remove(tag)  => {
+				message         = Message::new(message.into_iter().filter(|field| field.tag() != *tag).collect());
+				outcome.changed = true;
+			}
+		}
+	}
+	(message, outcome)
+}
+
+fn set(message: Message, field: &Field) -> Message {
+	let mut found  = false;
+	let mut fields = Vec::new();
+	for original in message {
+		if original.tag() == field.tag() {
+			found = true;
+			fields.push(field.clone());
+		}
+		else {
+			fields.push(original);
+		}
+	}
+	if !found {
+		fields.push(field.clone());
+	}
+	Message::new(fields)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn message(fields: &str) -> Message {
+		Message::new(fields.split('|').map(|field| build::parse(field, &BaseDictionary::default()).unwrap()).collect())
+	}
+
+	fn fields(message: &Message) -> String {
+		message.into_iter().map(|field| String::from_utf8_lossy(&field.bytes()).into_owned()).collect::<Vec<String>>().join("|")
+	}
+
+	#[test]
+	fn parse_and_apply() {
+		let rules = ["set Price=9.5 if MsgType=D", "remove 58", "delay 500 if 35=D 55=VOD.L", "drop if 35=0", "set 58=never"]
+			.map(|rule| Rule::parse(rule).unwrap());
+
+		let (order, outcome) = apply(&rules, message("35=D|55=VOD.L|44=10|58=note"));
+		assert_eq!(fields(&order), "35=D|55=VOD.L|44=9.5|58=never");
+		assert_eq!(outcome, Outcome { dropped: false, delay: Duration::from_millis(500), changed: true });
+
+		let (heartbeat, outcome) = apply(&rules, message("35=0|58=note"));
+		assert_eq!(fields(&heartbeat), "35=0");
+		assert_eq!(outcome, Outcome { dropped: true, delay: Duration::ZERO, changed: true });
+
+		assert_eq!(Rule::parse("delay soon").unwrap_err(), "Delay 'soon' is not a number of milliseconds");
+		assert_eq!(Rule::parse("remove Unknown").unwrap_err(), "Unknown tag name 'Unknown'");
+		assert_eq!(Rule::parse("duplicate if 35=D").unwrap_err(), "'duplicate' is not drop, delay MILLISECONDS, set TAG=VALUE or remove TAG");
+		assert_eq!(Rule::parse("drop if 35").unwrap_err(), "Invalid field '35': expected tag=value");
+	}
+}
@@ -0,0 +1,216 @@
+//! Module for redacting sensitive fields (e.g. client identifiers) before logs are shared.
+//! Values are either masked or replaced with tokens - the same value of a tag always gets the same
+//! token so order chains still line up. BodyLength (9) and CheckSum (10) are recalculated so the
+//! redacted messages are still valid FIX. Tokens are numbered per run unless a key is given: then
+//! they are derived from a keyed hash (HMAC-SHA256) of the value and match across files and runs.
+//! The values of redacted tags are masked in bytes that are not a valid message as well.
+
+use std::{collections::HashMap, fmt::Write as _, io::{Error, ErrorKind, Write}};
+
+use hmac::{Hmac, KeyInit, Mac};
+use memchr::memchr;
+use sha2::Sha256;
+
+use crate::{args::Args, dictionary::Dictionary, encoder, input::{self, Input}, parser::{field::{Field, TAG_DELIMITER}, message::Message, tag::Tag}};
+
+const MASK:        &[u8] = b"***";
+/// Bytes of the keyed hash shown in a token.
+const TOKEN_BYTES: usize = 8;
+
+#[derive(Debug)]
+pub struct Redactor<D: Dictionary> {
+	dictionary: D,
+	tags:       Vec<u32>,
+	mask:       bool,
+	/// Key of the hash tokens are derived from (tokens are numbered without it).
+	key:        Option<Vec<u8>>,
+	/// Token for each value of a tag.
+	tokens:     HashMap<(u32, Vec<u8>), Vec<u8>>,
+	/// Number of tokens handed out per tag.
+	counts:     HashMap<u32, usize>,
+}
+
+impl<D: Dictionary> Redactor<D> {
+	#[must_use]
+	pub fn new(tags: Vec<u32>, mask: bool, key: Option<Vec<u8>>) -> Self {
+		Self {
+			dictionary: D::default(),
+			tags,
+			mask,
+			key,
+			tokens:     HashMap::new(),
+			counts:     HashMap::new(),
+		}
+	}
+
+	/// Replaces the values of the redacted tags and recalculates BodyLength and CheckSum.
+	/// Messages without redacted tags are returned as they are.
+	pub fn redact(&mut self, message: Message) -> Message {
+		if !(&message).into_iter().any(|field| self.tags.contains(&field.tag().number())) {
+			return message;
+		}
+
+		let message = Message::new(message.into_iter()
+			.map(|field| {
+				let tag = field.tag().number();
+				if self.tags.contains(&tag) { Field::new(field.tag(), self.token(tag, field.value_bytes())) } else { field }
+			})
+			.collect());
+
+		// Messages without MsgType cannot be completed so they are only redacted.
+		encoder::complete(message).unwrap_or_else(|incomplete| incomplete.message)
+	}
+
+	/// Replaces the values of the redacted tags in bytes that are not a valid message (e.g. a message
+	/// with an empty field or cut off at the end of the log). A value ends at the separator or newline.
+	pub fn redact_bytes(&mut self, bytes: &[u8], separator: u8) -> Vec<u8> {
+		let mut redacted = Vec::with_capacity(bytes.len());
+		let mut rest     = bytes;
+		while let Some(delimiter) = memchr(TAG_DELIMITER, rest) {
+			let (before, after) = (rest.get(..delimiter).unwrap_or_default(), rest.get(delimiter + 1..).unwrap_or_default());
+			let digits          = before.iter().rev().take_while(|byte| byte.is_ascii_digit()).count();
+			let tag             = before.get(before.len() - digits..).and_then(|digits| std::str::from_utf8(digits).ok()?.parse::<u32>().ok());
+			redacted.extend_from_slice(before);
+			redacted.push(TAG_DELIMITER);
+
+			match tag {
+				Some(tag) if self.tags.contains(&tag) => {
+					let length = after.iter().position(|byte| *byte == separator || *byte == b'\n').unwrap_or(after.len());
+					let value  = after.get(..length).unwrap_or_default();
+					if !value.is_empty() {
+						redacted.extend(self.token(tag, value));
+					}
+					rest = after.get(length..).unwrap_or_default();
+				}
+				Some(_) | None => rest = after,
+			}
+		}
+		redacted.extend_from_slice(rest);
+		redacted
+	}
+
+	fn token(&mut self, tag: u32, value: &[u8]) -> Vec<u8> {
+		if self.mask {
+			return MASK.to_vec();
+		}
+		if let Some(token) = self.tokens.get(&(tag, value.to_vec())) {
+			return token.clone();
+		}
+
+		let name  = Tag::try_from(tag).ok().and_then(|tag| self.dictionary.tag_name(tag)).map_or_else(|| tag.to_string(), str::to_string);
+		let token = match &self.key {
+			Some(key) => format!("{}-{}", name, hash(key, tag, value)).into_bytes(),
+			None      => {
+				let count = self.counts.entry(tag).or_default();
+				*count   += 1;
+				format!("{}-{}", name, count).into_bytes()
+			}
+		};
+		self.tokens.insert((tag, value.to_vec()), token.clone());
+		token
+	}
+}
+
+/// Hex of the start of the HMAC-SHA256 of `tag=value`.
+fn hash(key: &[u8], tag: u32, value: &[u8]) -> String {
+	let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+	mac.update(format!("{}=", tag).as_bytes());
+	mac.update(value);
+	mac.finalize().into_bytes().iter().take(TOKEN_BYTES).fold(String::new(), |mut hex, byte| {
+		let _ = write!(hex, "{:02x}", byte);
+		hex
+	})
+}
+
+/// Writes the input with the messages redacted.
+pub fn run<D: Dictionary>(args: &Args, output: &mut impl Write) -> Result<(), Error> {
+	// The whole input is written, so a time window can't be applied.
+	if args.from.is_some() || args.to.is_some() {
+		return Err(Error::new(ErrorKind::InvalidInput, "--from and --to can't be used with --mode redact"));
+	}
+	let mut redactor = Redactor::<D>::new(args.redact_tags.clone(), args.mask, args.redact_key.clone().map(String::into_bytes));
+	let separator    = args.field_separator as u8;
+
+	let mut written = Ok(());
+	input::scan(args, |input| {
+		if written.is_ok() {
+			written = match input {
+				Input::Passthrough(bytes)
+				| Input::Invalid(bytes)   => output.write_all(&redactor.redact_bytes(bytes, separator)),
+				Input::Message(message)   => output.write_all(&encoder::encode(&redactor.redact(message), separator)),
+			};
+		}
+	})?;
+	written?;
+	output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{dictionary::BaseDictionary, parser::scanner::{Scanned, Scanner}};
+
+	fn redacted(redactor: &mut Redactor<BaseDictionary>, input: &str) -> String {
+		let mut output = Vec::new();
+		for scanned in Scanner::new(input.as_bytes(), b'|') {
+			match scanned {
+				Scanned::Message(message)   => output.extend(encoder::encode(&redactor.redact(message.into_owned()), b'|')),
+				Scanned::Passthrough(bytes)
+				| Scanned::Invalid(bytes)   => output.extend(redactor.redact_bytes(bytes, b'|')),
+			}
+		}
+		String::from_utf8(output).unwrap()
+	}
+
+	#[test]
+	fn consistent_tokens() {
+		let input = "\
+			8=FIX.4.4|9=40|35=D|1=ACC42|11=A1|55=VOD.L|58=call me|10=000|\n\
+			8=FIX.4.4|9=37|35=D|1=ACC7|11=A2|55=VOD.L|448=ACC42|10=000|\n\
+			8=FIX.4.4|9=26|35=D|1=ACC42|11=A3|5001=X|10=000|\n\
+			8=FIX.4.4|9=13|35=0|112=X|10=000|\n";
+
+		insta::assert_snapshot!(redacted(&mut Redactor::new(vec![1, 448, 58, 5001], false, None), input), @r"
+		8=FIX.4.4|9=42|35=D|1=Account-1|11=A1|55=VOD.L|58=Text-1|10=077|
+		8=FIX.4.4|9=46|35=D|1=Account-2|11=A2|55=VOD.L|448=PartyID-1|10=126|
+		8=FIX.4.4|9=35|35=D|1=Account-1|11=A3|5001=5001-1|10=192|
+		8=FIX.4.4|9=13|35=0|112=X|10=000|
+		");
+	}
+
+	#[test]
+	fn masked_messages_are_valid() {
+		let mut redactor = Redactor::<BaseDictionary>::new(vec![1, 58], true, None);
+		let output       = redacted(&mut redactor, "log 8=FIX.4.4|9=5|35=D|1=ACC42|58=secret|10=000| end\n");
+
+		assert_eq!(output, "log 8=FIX.4.4|9=18|35=D|1=***|58=***|10=001| end\n");
+
+		let Some(Scanned::Message(message)) = Scanner::new(output.as_bytes(), b'|').nth(1) else {
+			panic!("Should be a message");
+		};
+		let message = message.into_owned();
+		assert_eq!(message.body_length(), 18);
+		assert_eq!(message.check_sum(),   1);
+	}
+
+	#[test]
+	fn invalid_messages() {
+		let mut redactor = Redactor::<BaseDictionary>::new(vec![1, 58], false, None);
+		let input        = "8=FIX.4.4|9=5|35=D|1=ACC42|58=|10=000|\n8=FIX.4.4|9=5|35=D|11=A|1=ACC42|58=cut off";
+
+		assert_eq!(redacted(&mut redactor, input), "8=FIX.4.4|9=5|35=D|1=Account-1|58=|10=000|\n8=FIX.4.4|9=5|35=D|11=A|1=Account-1|58=Text-1");
+	}
+
+	#[test]
+	fn keyed_tokens() {
+		let input  = "8=FIX.4.4|9=5|35=D|1=ACC42|448=ACC42|10=000|\n";
+		let first  = redacted(&mut Redactor::new(vec![1, 448], false, Some(b"secret".to_vec())), input);
+		let second = redacted(&mut Redactor::new(vec![1, 448], false, Some(b"secret".to_vec())), &format!("8=FIX.4.4|9=5|35=0|10=000|\n{}", input));
+		let other  = redacted(&mut Redactor::new(vec![1, 448], false, Some(b"other".to_vec())), input);
+
+		assert!(second.ends_with(&first));
+		assert_ne!(first, other);
+		assert_eq!(first.len(), other.len());
+		assert!(first.contains("|1=Account-") && first.contains("|448=PartyID-"));
+	}
+}
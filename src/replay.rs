@@ -0,0 +1,172 @@
+//! Module for replaying one direction of a log against a FIX engine (`nfix replay`) to reproduce what happened.
+//! The application messages of one SenderCompID are sent over a new session, which fills in its own
+//! SenderCompID, TargetCompID, MsgSeqNum, SendingTime, BodyLength and CheckSum. Administrative messages (and
+//! session level Rejects) are left out as they belonged to the original session.
+//!
+//! Messages are sent with the pacing of the log (by the time source, SendingTime by default), sped up or slowed
+//! down by `--speed`. The responses are printed while waiting.
+
+use std::{io::{Error, ErrorKind, Write}, time::{Duration, Instant}};
+
+use crate::{args::{Args, ReplayArgs}, dictionary::Dictionary, filter::Filter, formatter::{FromArgs, SimpleFormatter}, input, parser::{Flush, message::Message, tag::Tag}, session::{self, Session}, timestamp::Clock};
+
+const SENDER_COMP_ID: Tag = Tag::new(49);
+
+const REJECT: &[u8] = b"3";
+
+/// Message to replay with its time in the log (nanoseconds since the epoch).
+#[derive(Debug)]
+pub struct Replayed {
+	pub time:    Option<i64>,
+	pub message: Message,
+}
+
+/// Parses the `--speed` multiplier (0 for no pauses).
+pub fn parse_speed(value: &str) -> Result<f64, String> {
+	match value.parse::<f64>() {
+		Ok(speed) if speed.is_finite() && speed >= 0.0 => Ok(speed),
+		Ok(_) | Err(_)                                 => Err(format!("'{}' is not a non-negative number", value)),
+	}
+}
+
+/// Application messages in the input sent by `sender` (the SenderCompID of the first message if none).
+pub fn messages(args: &Args, sender: Option<&str>) -> Result<Vec<Replayed>, Error> {
+	let clock        = Clock::new(args.time_source, args.time_format.clone());
+	let mut sender   = sender.map(|sender| sender.as_bytes().to_vec());
+	let mut replayed = Vec::new();
+	input::messages(args, |message, line| {
+		let from = message.value(SENDER_COMP_ID).unwrap_or_default();
+		if from != sender.get_or_insert_with(|| from.to_vec()).as_slice() {
+			return;
+		}
+		let msg_type = message.msg_type().unwrap_or_default();
+		if msg_type != REJECT && !session::ADMINISTRATIVE.contains(&msg_type) {
+			replayed.push(Replayed { time: clock.time(&message, line), message });
+		}
+	})?;
+	Ok(replayed)
+}
+
+/// Sends the messages with their pacing in the log divided by `speed` (without pauses if 0) and waits `wait`
+/// for responses after the last one. `observe` is called with each response.
+/// Messages without a time are sent right after the one before.
+pub fn replay(session: &mut Session, messages: Vec<Replayed>, speed: f64, wait: Duration, mut observe: impl FnMut(&Message) -> Result<(), Error>) -> Result<(), Error> {
+	let start   = Instant::now();
+	let first   = messages.iter().find_map(|replayed| replayed.time);
+	let mut due = start;
+	for Replayed { time, message } in messages {
+		if let (Some(first), Some(time)) = (first, time) && speed > 0.0 {
+			let delay = u64::try_from(time.saturating_sub(first)).unwrap_or_default();
+			due = due.max(start + Duration::from_nanos(delay).div_f64(speed));
+		}
+		receive_until(session, due, &mut observe)?;
+		if session.logged_out() {
+			return Err(Error::new(ErrorKind::ConnectionAborted, "Session ended before all messages were replayed"));
+		}
+		session.send(message)?;
+	}
+	receive_until(session, Instant::now() + wait, &mut observe)
+}
+
+/// Passes the responses received until the deadline to `observe`.
+fn receive_until(session: &mut Session, deadline: Instant, observe: &mut impl FnMut(&Message) -> Result<(), Error>) -> Result<(), Error> {
+	while !session.logged_out() {
+		let timeout = deadline.saturating_duration_since(Instant::now());
+		match session.poll(timeout)? {
+			Some(message)             => observe(&message)?,
+			None if timeout.is_zero() => break,
+			None                      => {}
+		}
+	}
+	Ok(())
+}
+
+/// Replays the messages of the files (or stdin) over a session and prints the responses.
+pub fn run<D: Dictionary, F: Filter>(args: &Args, replay_args: &ReplayArgs, output: &mut impl Write, flush: Flush) -> Result<(), Error> {
+	let input    = Args { files: replay_args.files.clone(), ..args.clone() };
+	let messages = messages(&input, replay_args.source.as_deref())?;
+	if messages.is_empty() {
+		return Err(Error::new(ErrorKind::InvalidInput, "No application messages to replay"));
+	}
+
+	let formatter   = SimpleFormatter::<D, F>::new(args);
+	let mut session = Session::connect(replay_args.connect.as_str(), &replay_args.settings)?;
+	replay(&mut session, messages, replay_args.speed, Duration::from_millis(replay_args.wait), |message| session::write_message(&formatter, output, flush, message))?;
+	session.logout(None)?;
+	output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{net::TcpListener, process, thread};
+
+	use clap::Parser;
+
+	use super::*;
+	use crate::{args::Command, parser::COMMAND_NAME, simulate::Exchange};
+
+	const LOG: &str = "\
+8=FIX.4.4|9=5|35=A|49=CLIENT|56=VENUE|34=1|52=20261016-13:00:00.000|98=0|108=30|10=000|
+8=FIX.4.4|9=5|35=A|49=VENUE|56=CLIENT|34=1|52=20261016-13:00:00.010|98=0|108=30|10=000|
+8=FIX.4.4|9=5|35=D|49=CLIENT|56=VENUE|34=2|52=20261016-13:00:01.000|11=B1|55=VOD.L|54=1|40=2|44=10|38=100|10=000|
+8=FIX.4.4|9=5|35=8|49=VENUE|56=CLIENT|34=2|52=20261016-13:00:01.010|37=1|11=B1|150=0|39=0|10=000|
+8=FIX.4.4|9=5|35=0|49=CLIENT|56=VENUE|34=3|52=20261016-13:00:30.000|10=000|
+8=FIX.4.4|9=5|35=D|49=CLIENT|56=VENUE|34=4|52=20261016-13:00:01.200|11=S1|55=VOD.L|54=2|40=2|44=10|38=60|10=000|
+";
+
+	#[test]
+	fn replay_to_simulator() {
+		let path = std::env::temp_dir().join(format!("nfix-replay-{}.log", process::id()));
+		std::fs::write(&path, LOG).unwrap();
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let address  = listener.local_addr().unwrap().to_string();
+
+		let simulator = thread::spawn(move || {
+			let args = Args::parse_from([COMMAND_NAME, "simulate", "--sender-comp-id", "EXCHANGE", "--target-comp-id", "TRADER"]);
+			let Some(Command::Simulate(simulate_args)) = args.command else {
+				panic!("Should be the simulate command");
+			};
+			let mut session  = Session::accept(&listener, &simulate_args.settings).unwrap();
+			let mut received = Vec::new();
+			Exchange::new(&simulate_args).serve(&mut session, |message| {
+				if message.value(SENDER_COMP_ID) == Some(b"TRADER") {
+					let fields = [34, 11, 56].map(|tag| message.get_str(Tag::new(tag)).unwrap().to_string());
+					received.push(fields.join(" "));
+				}
+				Ok(())
+			}).unwrap();
+			received
+		});
+
+		let args = Args::parse_from([COMMAND_NAME, "-s", "|", "replay", "--connect", &address, "--sender-comp-id", "TRADER", "--target-comp-id", "EXCHANGE", "--speed", "2", "--wait", "200", path.to_str().unwrap()]);
+		let Some(Command::Replay(replay_args)) = &args.command else {
+			panic!("Should be the replay command");
+		};
+		let input    = Args { files: replay_args.files.clone(), ..args.clone() };
+		let messages = messages(&input, None).unwrap();
+		assert_eq!(messages.iter().map(|replayed| replayed.message.get_str(Tag::new(11)).unwrap()).collect::<Vec<&str>>(), ["B1", "S1"]);
+
+		let mut session   = Session::connect(address.as_str(), &replay_args.settings).unwrap();
+		let start         = Instant::now();
+		let mut responses = Vec::new();
+		replay(&mut session, messages, replay_args.speed, Duration::from_millis(replay_args.wait), |message| {
+			responses.push(format!("{} {}", message.get_str(Tag::new(11)).unwrap(), message.get_str(Tag::new(150)).unwrap()));
+			Ok(())
+		}).unwrap();
+		// The orders were 200 ms apart in the log.
+		assert!(start.elapsed() >= Duration::from_millis(100));
+		session.logout(None).unwrap();
+		std::fs::remove_file(&path).unwrap();
+
+		assert_eq!(simulator.join().unwrap(), ["2 B1 EXCHANGE", "3 S1 EXCHANGE"]);
+		assert_eq!(responses, ["B1 0", "S1 0", "B1 F", "S1 F"]);
+	}
+
+	#[test]
+	fn speed() {
+		assert_eq!(parse_speed("0.5"), Ok(0.5));
+		assert_eq!(parse_speed("0"), Ok(0.0));
+		assert!(parse_speed("-1").is_err());
+		assert!(parse_speed("fast").is_err());
+	}
+}
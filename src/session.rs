@@ -0,0 +1,770 @@
+//! Module with a small FIX session layer over TCP for testing engines.
+//! A `Session` logs on as the initiator (`connect`) or the acceptor (`accept`), numbers, stamps and keeps the
+//! messages it sends, and handles the administrative messages it receives: Heartbeat and TestRequest,
+//! ResendRequest (stored application messages are resent and administrative ones gap filled), SequenceReset
+//! and Logout. A gap in the received sequence numbers is filled by sending a ResendRequest.
+//!
+//! Sessions are synchronous: Heartbeats and TestRequests are sent while waiting in `poll` or `receive`.
+
+pub mod store;
+
+use std::{collections::VecDeque, io::{BufRead, Error, ErrorKind, Read, Write}, net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs}, sync::mpsc::{self, TryRecvError}, thread, time::{Duration, Instant, SystemTime}};
+
+use crate::{args::{Args, SessionArgs, SessionSettings}, build, dictionary::Dictionary, encoder, filter::Filter, formatter::{FixFormatter, FromArgs, SimpleFormatter}, parser::{Flush, decoder::{Decoder, Event}, field::{Field, SOH}, message::Message, tag::Tag}, session::store::Store, timestamp::Timestamp};
+
+const BEGIN_SEQ_NO:       Tag = Tag::new(7);
+const BEGIN_STRING:       Tag = Tag::new(8);
+const BODY_LENGTH:        Tag = Tag::new(9);
+const CHECK_SUM:          Tag = Tag::new(10);
+const END_SEQ_NO:         Tag = Tag::new(16);
+const MSG_SEQ_NUM:        Tag = Tag::new(34);
+const MSG_TYPE:           Tag = Tag::new(35);
+const NEW_SEQ_NO:         Tag = Tag::new(36);
+const POSS_DUP_FLAG:      Tag = Tag::new(43);
+const SENDER_COMP_ID:     Tag = Tag::new(49);
+const SENDING_TIME:       Tag = Tag::new(52);
+const TARGET_COMP_ID:     Tag = Tag::new(56);
+const TEXT:               Tag = Tag::new(58);
+const ENCRYPT_METHOD:     Tag = Tag::new(98);
+const HEART_BT_INT:       Tag = Tag::new(108);
+const TEST_REQ_ID:        Tag = Tag::new(112);
+const ORIG_SENDING_TIME:  Tag = Tag::new(122);
+const GAP_FILL_FLAG:      Tag = Tag::new(123);
+const RESET_SEQ_NUM_FLAG: Tag = Tag::new(141);
+
+const HEARTBEAT:      &[u8] = b"0";
+const TEST_REQUEST:   &[u8] = b"1";
+const RESEND_REQUEST: &[u8] = b"2";
+const SEQUENCE_RESET: &[u8] = b"4";
+const LOGOUT:         &[u8] = b"5";
+const LOGON:          &[u8] = b"A";
+
+/// Administrative messages are not resent but replaced by a SequenceReset-GapFill (Reject is resent).
+pub(crate) const ADMINISTRATIVE: [&[u8]; 6] = [HEARTBEAT, TEST_REQUEST, RESEND_REQUEST, SEQUENCE_RESET, LOGOUT, LOGON];
+
+/// Header fields filled in by the session.
+const HEADER: [Tag; 9] = [BEGIN_STRING, BODY_LENGTH, MSG_TYPE, MSG_SEQ_NUM, SENDER_COMP_ID, SENDING_TIME, TARGET_COMP_ID, POSS_DUP_FLAG, ORIG_SENDING_TIME];
+
+/// Longest wait for the Logon reply and for the Logout reply.
+const LOGON_TIMEOUT:       Duration = Duration::from_secs(10);
+/// How often the command line session checks stdin for messages to send.
+const POLL_INTERVAL:       Duration = Duration::from_millis(50);
+/// ResendRequests sent for a gap (one per heartbeat interval) before the session is given up.
+const MAX_RESEND_REQUESTS: u32      = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+	LoggedOn,
+	/// Logout sent and waiting for the Logout reply.
+	LogoutSent,
+	LoggedOut,
+}
+
+#[derive(Debug)]
+pub struct Session {
+	begin_string:    String,
+	sender_comp_id:  String,
+	target_comp_id:  String,
+	stream:          TcpStream,
+	decoder:         Decoder,
+	/// Messages received but not processed yet.
+	received:        VecDeque<Message>,
+	store:           Store,
+	state:           State,
+	heart_bt_int:    Duration,
+	last_sent:       Instant,
+	last_received:   Instant,
+	/// When the TestRequest without a reply yet was sent.
+	test_request:    Option<Instant>,
+	test_requests:   u64,
+	/// MsgSeqNum that made us send a ResendRequest (until the gap up to it has been filled).
+	resend_until:    Option<u64>,
+	/// When the last ResendRequest for the gap was sent.
+	resend_request:  Instant,
+	resend_requests: u32,
+	/// Messages dropped because of a wrong CheckSum.
+	garbled:         u64,
+}
+
+impl Session {
+	/// Connects to the acceptor and logs on (as the initiator).
+	pub fn connect(address: impl ToSocketAddrs, settings: &SessionSettings) -> Result<Session, Error> {
+		let mut session = Session::new(TcpStream::connect(address)?, settings)?;
+		if settings.reset_seq_num {
+			session.store.reset()?;
+		}
+
+		let mut logon = vec![field(ENCRYPT_METHOD, "0"), field(HEART_BT_INT, &settings.heart_bt_int.to_string())];
+		if settings.reset_seq_num {
+			logon.push(field(RESET_SEQ_NUM_FLAG, "Y"));
+		}
+		session.send_message(LOGON, logon)?;
+
+		let reply = session.read_message(Instant::now() + LOGON_TIMEOUT)?
+			.ok_or_else(|| Error::new(ErrorKind::TimedOut, "No reply to Logon"))?;
+		match reply.msg_type() {
+			Some(LOGON)  => session.check_comp_ids(&reply)?,
+			Some(LOGOUT) => {
+				session.disconnect();
+				let text = reply.get_str(TEXT).unwrap_or_default();
+				return Err(Error::new(ErrorKind::ConnectionRefused, format!("Logon rejected: {}", text)));
+			}
+			msg_type     => return Err(session.terminate(format!("Expected Logon (35=A) but received MsgType {}", String::from_utf8_lossy(msg_type.unwrap_or_default())))),
+		}
+
+		session.state = State::LoggedOn;
+		session.process(reply)?;
+		Ok(session)
+	}
+
+	/// Accepts a connection on the listener and waits for the initiator to log on (as the acceptor).
+	/// The heartbeat interval is the HeartBtInt (108) of the initiator.
+	pub fn accept(listener: &TcpListener, settings: &SessionSettings) -> Result<Session, Error> {
+		let (stream, _) = listener.accept()?;
+		let mut session = Session::new(stream, settings)?;
+
+		let logon = session.read_message(Instant::now() + LOGON_TIMEOUT)?
+			.ok_or_else(|| Error::new(ErrorKind::TimedOut, "No Logon received"))?;
+		if logon.msg_type() != Some(LOGON) {
+			return Err(session.terminate(format!("Expected Logon (35=A) but received MsgType {}", String::from_utf8_lossy(logon.msg_type().unwrap_or_default()))));
+		}
+		session.check_comp_ids(&logon)?;
+		let heart_bt_int = logon.get_int(HEART_BT_INT).ok().and_then(|seconds| u64::try_from(seconds).ok())
+			.ok_or_else(|| session.terminate("HeartBtInt (108) is missing or invalid".to_string()))?;
+		session.heart_bt_int = Duration::from_secs(heart_bt_int);
+
+		let reset = logon.get_bool(RESET_SEQ_NUM_FLAG) == Ok(true);
+		if reset {
+			session.store.reset()?;
+		}
+		let mut reply = vec![field(ENCRYPT_METHOD, "0"), field(HEART_BT_INT, &heart_bt_int.to_string())];
+		if reset {
+			reply.push(field(RESET_SEQ_NUM_FLAG, "Y"));
+		}
+		session.send_message(LOGON, reply)?;
+
+		session.state = State::LoggedOn;
+		session.process(logon)?;
+		Ok(session)
+	}
+
+	fn new(stream: TcpStream, settings: &SessionSettings) -> Result<Session, Error> {
+		stream.set_nodelay(true)?;
+		let store = match &settings.store {
+			Some(directory) => Store::open(directory, &settings.begin_string, &settings.sender_comp_id, &settings.target_comp_id)?,
+			None            => Store::memory(),
+		};
+
+		Ok(Session {
+			begin_string:    settings.begin_string.clone(),
+			sender_comp_id:  settings.sender_comp_id.clone(),
+			target_comp_id:  settings.target_comp_id.clone(),
+			stream,
+			decoder:         Decoder::new(SOH),
+			received:        VecDeque::new(),
+			store,
+			state:           State::LoggedOut,
+			heart_bt_int:    Duration::from_secs(u64::from(settings.heart_bt_int)),
+			last_sent:       Instant::now(),
+			last_received:   Instant::now(),
+			test_request:    None,
+			test_requests:   0,
+			resend_until:    None,
+			resend_request:  Instant::now(),
+			resend_requests: 0,
+			garbled:         0,
+		})
+	}
+
+	/// Sends an application message.
+	/// The header fields (BeginString, SenderCompID, TargetCompID, MsgSeqNum and SendingTime), BodyLength and
+	/// CheckSum are filled in, replacing any given. Returns the message as sent.
+	pub fn send(&mut self, message: Message) -> Result<Message, Error> {
+		if self.state != State::LoggedOn {
+			return Err(Error::new(ErrorKind::NotConnected, "Session is not logged on"));
+		}
+		let msg_type = message.msg_type().ok_or_else(|| Error::new(ErrorKind::InvalidInput, "MsgType (35) is missing"))?.to_vec();
+		let body     = message.into_iter().filter(|field| !HEADER.contains(&field.tag()) && field.tag() != CHECK_SUM).collect();
+		self.send_message(&msg_type, body)
+	}
+
+	/// Waits up to `timeout` for an application message while answering administrative messages and sending
+	/// Heartbeats and TestRequests when they are due.
+	/// Returns `None` if no application message arrived in time or the session has been logged out.
+	pub fn poll(&mut self, timeout: Duration) -> Result<Option<Message>, Error> {
+		let deadline = Instant::now() + timeout;
+		while self.state != State::LoggedOut {
+			self.heartbeat()?;
+			match self.read_message(deadline.min(self.next_heartbeat()))? {
+				Some(message)                          => if let Some(message) = self.process(message)? {
+					return Ok(Some(message));
+				},
+				None if Instant::now() >= deadline     => return Ok(None),
+				None                                   => {}
+			}
+		}
+		Ok(None)
+	}
+
+	/// Waits for the next application message (`None` once the session has been logged out).
+	pub fn receive(&mut self) -> Result<Option<Message>, Error> {
+		while self.state != State::LoggedOut {
+			if let Some(message) = self.poll(self.heart_bt_int)? {
+				return Ok(Some(message));
+			}
+		}
+		Ok(None)
+	}
+
+	/// Sends Logout and waits for the Logout reply.
+	/// Application messages received in the meantime are dropped.
+	pub fn logout(&mut self, text: Option<&str>) -> Result<(), Error> {
+		if self.state != State::LoggedOn {
+			return Ok(());
+		}
+		self.send_message(LOGOUT, text.map(|text| field(TEXT, text)).into_iter().collect())?;
+		self.state = State::LogoutSent;
+
+		let deadline = Instant::now() + LOGON_TIMEOUT;
+		while self.state != State::LoggedOut && Instant::now() < deadline {
+			self.poll(deadline.saturating_duration_since(Instant::now()))?;
+		}
+		self.disconnect();
+		Ok(())
+	}
+
+	/// Number of messages received with a wrong CheckSum (they are dropped).
+	#[must_use]
+	pub fn garbled(&self) -> u64 {
+		self.garbled
+	}
+
+	/// Whether the session has ended (with a Logout or by a disconnect).
+	#[must_use]
+	pub fn logged_out(&self) -> bool {
+		self.state == State::LoggedOut
+	}
+
+	/// Handles a received message and returns it if it is an application message to hand out.
+	fn process(&mut self, message: Message) -> Result<Option<Message>, Error> {
+		self.last_received = Instant::now();
+		self.test_request  = None;
+
+		let msg_type = message.msg_type().unwrap_or_default().to_vec();
+		let Some(seq_num) = message.get_int(MSG_SEQ_NUM).ok().and_then(|seq_num| u64::try_from(seq_num).ok()) else {
+			return Err(self.terminate("MsgSeqNum (34) is missing".to_string()));
+		};
+
+		// A SequenceReset-Reset sets the next MsgSeqNum whatever its own.
+		if msg_type == SEQUENCE_RESET && message.get_bool(GAP_FILL_FLAG) != Ok(true) {
+			let new_seq_no = self.new_seq_no(&message)?;
+			self.store.set_next_target_seq_num(new_seq_no)?;
+			return Ok(None);
+		}
+
+		let expected = self.store.next_target_seq_num();
+		if seq_num > expected {
+			// Everything from the gap on is resent, so only a Logout or ResendRequest is handled now.
+			if self.resend_until.is_none() {
+				self.request_resend()?;
+				self.resend_until = Some(seq_num);
+			}
+			match msg_type.as_slice() {
+				LOGOUT         => self.logged_out_by_counterparty()?,
+				RESEND_REQUEST => self.resend(&message)?,
+				_              => {}
+			}
+			return Ok(None);
+		}
+		if seq_num < expected {
+			if message.get_bool(POSS_DUP_FLAG) == Ok(true) {
+				return Ok(None);
+			}
+			return Err(self.terminate(format!("MsgSeqNum too low, expecting {} but received {}", expected, seq_num)));
+		}
+
+		self.store.set_next_target_seq_num(expected + 1)?;
+		let message = match msg_type.as_slice() {
+			HEARTBEAT | LOGON => None,
+			TEST_REQUEST      => {
+				let test_req_id = message.value(TEST_REQ_ID).unwrap_or_default();
+				self.send_message(HEARTBEAT, vec![Field::new(TEST_REQ_ID, test_req_id.to_vec())])?;
+				None
+			}
+			RESEND_REQUEST    => {
+				self.resend(&message)?;
+				None
+			}
+			SEQUENCE_RESET    => {
+				let new_seq_no = self.new_seq_no(&message)?;
+				if new_seq_no > expected + 1 {
+					self.store.set_next_target_seq_num(new_seq_no)?;
+				}
+				None
+			}
+			LOGOUT            => {
+				self.logged_out_by_counterparty()?;
+				None
+			}
+			_                 => Some(message),
+		};
+
+		if self.resend_until.is_some_and(|until| self.store.next_target_seq_num() > until) {
+			self.resend_until    = None;
+			self.resend_requests = 0;
+		}
+		Ok(message)
+	}
+
+	/// Asks for everything from the next expected MsgSeqNum on.
+	fn request_resend(&mut self) -> Result<(), Error> {
+		let expected = self.store.next_target_seq_num();
+		self.send_message(RESEND_REQUEST, vec![field(BEGIN_SEQ_NO, &expected.to_string()), field(END_SEQ_NO, "0")])?;
+		self.resend_request   = Instant::now();
+		self.resend_requests += 1;
+		Ok(())
+	}
+
+	/// Resends the stored application messages in the range of the ResendRequest and gap fills the rest.
+	fn resend(&mut self, request: &Message) -> Result<(), Error> {
+		let seq_num = |tag| request.get_int(tag).ok().and_then(|seq_num| u64::try_from(seq_num).ok());
+		let (Some(begin), Some(end)) = (seq_num(BEGIN_SEQ_NO), seq_num(END_SEQ_NO)) else {
+			return Err(self.terminate("ResendRequest without BeginSeqNo (7) or EndSeqNo (16)".to_string()));
+		};
+		let last = self.store.next_sender_seq_num() - 1;
+		let end  = if end == 0 { last } else { end.min(last) };
+
+		let mut gap = None;
+		for seq_num in begin.max(1)..=end {
+			let Some(message) = self.store.message(seq_num).cloned() else {
+				gap = gap.or(Some(seq_num));
+				continue;
+			};
+			if let Some(start) = gap.take() {
+				self.gap_fill(start, seq_num)?;
+			}
+			self.write(&possible_duplicate(message)?)?;
+		}
+		if let Some(start) = gap {
+			self.gap_fill(start, end + 1)?;
+		}
+		Ok(())
+	}
+
+	/// Sends a SequenceReset-GapFill in place of the messages from `seq_num` up to `new_seq_no`.
+	fn gap_fill(&mut self, seq_num: u64, new_seq_no: u64) -> Result<(), Error> {
+		let mut fields = self.header(SEQUENCE_RESET, seq_num);
+		fields.extend([field(POSS_DUP_FLAG, "Y"), field(GAP_FILL_FLAG, "Y"), field(NEW_SEQ_NO, &new_seq_no.to_string())]);
+		self.write(&encoder::complete(Message::new(fields))?)
+	}
+
+	fn new_seq_no(&mut self, message: &Message) -> Result<u64, Error> {
+		match message.get_int(NEW_SEQ_NO).ok().and_then(|seq_num| u64::try_from(seq_num).ok()) {
+			Some(new_seq_no) => Ok(new_seq_no),
+			None             => Err(self.terminate("SequenceReset without NewSeqNo (36)".to_string())),
+		}
+	}
+
+	fn logged_out_by_counterparty(&mut self) -> Result<(), Error> {
+		if self.state == State::LoggedOn {
+			self.send_message(LOGOUT, Vec::new())?;
+		}
+		self.disconnect();
+		Ok(())
+	}
+
+	/// Sends a Heartbeat when nothing has been sent for the heartbeat interval and a TestRequest when nothing has
+	/// been received for a bit longer. The session is lost if the TestRequest is not answered in time.
+	/// A ResendRequest whose gap has not been filled within the heartbeat interval is sent again, and the session
+	/// is given up after `MAX_RESEND_REQUESTS`.
+	fn heartbeat(&mut self) -> Result<(), Error> {
+		let now = Instant::now();
+		if self.resend_until.is_some() && now.duration_since(self.resend_request) >= self.heart_bt_int {
+			if self.resend_requests >= MAX_RESEND_REQUESTS {
+				return Err(self.terminate("No reply to ResendRequest".to_string()));
+			}
+			self.request_resend()?;
+		}
+		if let Some(sent) = self.test_request && now.duration_since(sent) >= self.heart_bt_int {
+			self.disconnect();
+			return Err(Error::new(ErrorKind::TimedOut, "No reply to TestRequest"));
+		}
+		if self.test_request.is_none() && now.duration_since(self.last_received) >= self.test_request_interval() {
+			self.test_requests += 1;
+			self.send_message(TEST_REQUEST, vec![field(TEST_REQ_ID, &format!("TEST{}", self.test_requests))])?;
+			self.test_request = Some(now);
+		}
+		if now.duration_since(self.last_sent) >= self.heart_bt_int {
+			self.send_message(HEARTBEAT, Vec::new())?;
+		}
+		Ok(())
+	}
+
+	/// When the next Heartbeat, TestRequest, TestRequest timeout or repeated ResendRequest is due.
+	fn next_heartbeat(&self) -> Instant {
+		let received = match self.test_request {
+			Some(sent) => sent + self.heart_bt_int,
+			None       => self.last_received + self.test_request_interval(),
+		};
+		let resend   = if self.resend_until.is_some() { self.resend_request + self.heart_bt_int } else { received };
+		received.min(resend).min(self.last_sent + self.heart_bt_int)
+	}
+
+	fn test_request_interval(&self) -> Duration {
+		self.heart_bt_int + self.heart_bt_int / 5
+	}
+
+	/// Numbers, stamps, keeps (unless administrative) and sends the message.
+	fn send_message(&mut self, msg_type: &[u8], body: Vec<Field>) -> Result<Message, Error> {
+		let seq_num    = self.store.next_sender_seq_num();
+		let mut fields = self.header(msg_type, seq_num);
+		fields.extend(body);
+		let message    = encoder::complete(Message::new(fields))?;
+
+		if !ADMINISTRATIVE.contains(&msg_type) {
+			self.store.add(&message)?;
+		}
+		self.store.set_next_sender_seq_num(seq_num + 1)?;
+		self.write(&message)?;
+		Ok(message)
+	}
+
+	fn header(&self, msg_type: &[u8], seq_num: u64) -> Vec<Field> {
+		vec![
+			field(BEGIN_STRING, &self.begin_string),
+			Field::new(MSG_TYPE, msg_type.to_vec()),
+			field(SENDER_COMP_ID, &self.sender_comp_id),
+			field(TARGET_COMP_ID, &self.target_comp_id),
+			field(MSG_SEQ_NUM, &seq_num.to_string()),
+			field(SENDING_TIME, &now()),
+		]
+	}
+
+	fn write(&mut self, message: &Message) -> Result<(), Error> {
+		self.stream.write_all(&encoder::encode(message, SOH))?;
+		self.last_sent = Instant::now();
+		Ok(())
+	}
+
+	/// Reads until a message has been received or the deadline has passed (`None`).
+	/// Garbled messages (with a wrong CheckSum) are counted and ignored.
+	fn read_message(&mut self, deadline: Instant) -> Result<Option<Message>, Error> {
+		let mut buffer = [0; 4096];
+		loop {
+			if let Some(message) = self.received.pop_front() {
+				return Ok(Some(message));
+			}
+
+			let timeout = deadline.saturating_duration_since(Instant::now());
+			if timeout.is_zero() {
+				return Ok(None);
+			}
+			self.stream.set_read_timeout(Some(timeout))?;
+			let count = match self.stream.read(&mut buffer) {
+				Ok(count)                                                                          => count,
+				Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => return Ok(None),
+				Err(error)                                                                         => return Err(error),
+			};
+			if count == 0 {
+				let state = self.state;
+				self.disconnect();
+				return match state {
+					State::LogoutSent | State::LoggedOut => Ok(None),
+					State::LoggedOn                      => Err(Error::new(ErrorKind::ConnectionAborted, "Connection closed without Logout")),
+				};
+			}
+
+			for event in self.decoder.feed(buffer.get(..count).unwrap_or_default()) {
+				if let Event::Message(message) = event {
+					if message.get_int(CHECK_SUM).is_ok_and(|check_sum| check_sum == i64::from(message.check_sum())) {
+						self.received.push_back(message);
+					}
+					else {
+						self.garbled += 1;
+					}
+				}
+			}
+		}
+	}
+
+	/// Checks that the CompIDs of the counterparty match the session (and rejects the Logon if not).
+	fn check_comp_ids(&mut self, logon: &Message) -> Result<(), Error> {
+		let expected = [(BEGIN_STRING, self.begin_string.clone()), (SENDER_COMP_ID, self.target_comp_id.clone()), (TARGET_COMP_ID, self.sender_comp_id.clone())];
+		for (tag, value) in expected {
+			let received = logon.value(tag).unwrap_or_default();
+			if received != value.as_bytes() {
+				return Err(self.terminate(format!("Tag {} is '{}' but expected '{}'", tag.number(), String::from_utf8_lossy(received), value)));
+			}
+		}
+		Ok(())
+	}
+
+	/// Sends a Logout with the reason, disconnects and returns the reason as an error.
+	fn terminate(&mut self, text: String) -> Error {
+		// The session is lost anyway, so it does not matter if the Logout cannot be sent.
+		self.send_message(LOGOUT, vec![field(TEXT, &text)]).ok();
+		self.disconnect();
+		Error::new(ErrorKind::InvalidData, text)
+	}
+
+	fn disconnect(&mut self) {
+		self.state = State::LoggedOut;
+		self.stream.shutdown(Shutdown::Both).ok();
+	}
+}
+
+/// Message to resend with PossDupFlag and OrigSendingTime (the original SendingTime).
+fn possible_duplicate(message: Message) -> Result<Message, Error> {
+	let mut fields = Vec::new();
+	for original in message {
+		match original.tag() {
+			SENDING_TIME                      => {
+				fields.push(field(POSS_DUP_FLAG, "Y"));
+				fields.push(field(SENDING_TIME, &now()));
+				fields.push(Field::new(ORIG_SENDING_TIME, original.value_bytes().to_vec()));
+			}
+			POSS_DUP_FLAG | ORIG_SENDING_TIME => {}
+			_                                 => fields.push(original),
+		}
+	}
+	Ok(encoder::complete(Message::new(fields))?)
+}
+
+fn now() -> String {
+	Timestamp::from_duration(SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default(), 3).to_string()
+}
+
+fn field(tag: Tag, value: &str) -> Field {
+	Field::new(tag, value.as_bytes().to_vec())
+}
+
+/// Listens on `host:port` (or `:port` on all interfaces).
+pub fn bind(address: &str) -> Result<TcpListener, Error> {
+	match address.strip_prefix(':') {
+		Some(port) => TcpListener::bind(format!("0.0.0.0:{}", port)),
+		None       => TcpListener::bind(address),
+	}
+}
+
+/// Runs a session from the command line: messages read from stdin are sent and received application
+/// messages are formatted. The session is logged out at the end of stdin.
+pub fn run<D: Dictionary, F: Filter>(args: &Args, session_args: &SessionArgs, output: &mut impl Write, flush: Flush) -> Result<(), Error> {
+	let mut session = match (&session_args.connect, &session_args.listen) {
+		(Some(address), _)    => Session::connect(address.as_str(), &session_args.settings)?,
+		(None, Some(address)) => Session::accept(&bind(address)?, &session_args.settings)?,
+		(None, None)          => return Err(Error::new(ErrorKind::InvalidInput, "--connect or --listen is required")),
+	};
+	let formatter  = SimpleFormatter::<D, F>::new(args);
+	let dictionary = D::default();
+
+	// Stdin is read on its own thread so the session keeps heartbeating while waiting for input.
+	let (sender, lines) = mpsc::channel();
+	thread::spawn(move || {
+		for line in std::io::stdin().lock().lines() {
+			if sender.send(line).is_err() {
+				break;
+			}
+		}
+	});
+
+	let mut garbled = 0;
+	while !session.logged_out() {
+		if let Some(message) = session.poll(POLL_INTERVAL)? {
+			write_message(&formatter, output, flush, &message)?;
+		}
+		if session.garbled() > garbled {
+			garbled = session.garbled();
+			writeln!(output, "\nIgnored {} message{} with a wrong CheckSum", garbled, if garbled == 1 { "" } else { "s" })?;
+		}
+
+		loop {
+			match lines.try_recv() {
+				Ok(line)                        => {
+					let fields = line?.split(args.field_separator)
+						.filter(|field| !field.is_empty())
+						.map(|field| build::parse(field, &dictionary))
+						.collect::<Result<Vec<Field>, Error>>()?;
+					if !fields.is_empty() {
+						session.send(Message::new(fields))?;
+					}
+				}
+				Err(TryRecvError::Empty)        => break,
+				Err(TryRecvError::Disconnected) => return session.logout(None),
+			}
+		}
+	}
+	output.flush()
+}
+
+/// Writes the formatted message on a new line.
+pub(crate) fn write_message(formatter: &impl FixFormatter, output: &mut impl Write, flush: Flush, message: &Message) -> Result<(), Error> {
+	output.write_all(b"\n")?;
+	formatter.format(message, output)?;
+	if flush == Flush::EachMessage {
+		output.flush()?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{path::PathBuf, thread::JoinHandle};
+
+	use super::*;
+
+	const SYMBOL: Tag = Tag::new(55);
+
+	fn settings(sender_comp_id: &str, target_comp_id: &str, store: Option<PathBuf>) -> SessionSettings {
+		SessionSettings {
+			sender_comp_id: sender_comp_id.to_string(),
+			target_comp_id: target_comp_id.to_string(),
+			begin_string:   "FIX.4.4".to_string(),
+			heart_bt_int:   30,
+			store,
+			reset_seq_num:  false,
+		}
+	}
+
+	fn message(fields: &str) -> Message {
+		Message::new(fields.split('|').map(|field| build::parse(field, &crate::dictionary::BaseDictionary::default()).unwrap()).collect())
+	}
+
+	fn describe(message: &Message) -> String {
+		let fields = message.into_iter()
+			.filter(|field| ![SENDING_TIME, ORIG_SENDING_TIME, BODY_LENGTH, CHECK_SUM].contains(&field.tag()))
+			.map(|field| String::from_utf8_lossy(&field.bytes()).into_owned());
+		fields.collect::<Vec<String>>().join("|")
+	}
+
+	/// Acceptor answering each NewOrderSingle with an ExecutionReport until logged out.
+	fn exchange(listener: TcpListener, settings: SessionSettings) -> JoinHandle<Vec<String>> {
+		thread::spawn(move || {
+			let mut session  = Session::accept(&listener, &settings).unwrap();
+			let mut received = Vec::new();
+			while let Some(message) = session.receive().unwrap() {
+				received.push(describe(&message));
+				let symbol = String::from_utf8_lossy(message.value(SYMBOL).unwrap_or_default()).into_owned();
+				session.send(self::message(&format!("35=8|55={}|39=0", symbol))).unwrap();
+			}
+			received
+		})
+	}
+
+	fn store_directory(name: &str) -> PathBuf {
+		let directory = std::env::temp_dir().join(format!("nfix-session-{}-{}", std::process::id(), name));
+		std::fs::remove_dir_all(&directory).ok();
+		directory
+	}
+
+	#[test]
+	fn logon_resend_and_logout() {
+		let directory = store_directory("resend");
+		let client    = settings("CLIENT", "EXCHANGE", Some(directory.join("client")));
+		let exchange  = settings("EXCHANGE", "CLIENT", Some(directory.join("exchange")));
+		let seq_nums  = |name: &str| std::fs::read_to_string(directory.join(name)).unwrap();
+
+		let listener  = TcpListener::bind("127.0.0.1:0").unwrap();
+		let address   = listener.local_addr().unwrap();
+		let acceptor  = self::exchange(listener.try_clone().unwrap(), exchange.clone());
+		let mut session = Session::connect(address, &client).unwrap();
+		session.send(message("35=D|55=VOD.L|54=1|38=100")).unwrap();
+		assert_eq!(describe(&session.receive().unwrap().unwrap()), "8=FIX.4.4|35=8|49=EXCHANGE|56=CLIENT|34=2|55=VOD.L|39=0");
+		session.logout(None).unwrap();
+
+		assert_eq!(acceptor.join().unwrap(), ["8=FIX.4.4|35=D|49=CLIENT|56=EXCHANGE|34=2|55=VOD.L|54=1|38=100"]);
+		assert_eq!(seq_nums("client/FIX.4.4-CLIENT-EXCHANGE.seqnums"),   "4 4\n");
+		assert_eq!(seq_nums("exchange/FIX.4.4-EXCHANGE-CLIENT.seqnums"), "4 4\n");
+
+		// The client lost the messages of the exchange: the ExecutionReport is resent and the rest gap filled.
+		std::fs::write(directory.join("client/FIX.4.4-CLIENT-EXCHANGE.seqnums"), "4 1\n").unwrap();
+		let acceptor    = self::exchange(listener, exchange);
+		let mut session = Session::connect(address, &client).unwrap();
+		assert_eq!(describe(&session.receive().unwrap().unwrap()), "8=FIX.4.4|35=8|49=EXCHANGE|56=CLIENT|34=2|43=Y|55=VOD.L|39=0");
+		session.logout(Some("done")).unwrap();
+
+		assert_eq!(acceptor.join().unwrap(), Vec::<String>::new());
+		assert_eq!(seq_nums("client/FIX.4.4-CLIENT-EXCHANGE.seqnums"),   "7 6\n");
+		assert_eq!(seq_nums("exchange/FIX.4.4-EXCHANGE-CLIENT.seqnums"), "6 7\n");
+
+		std::fs::remove_dir_all(&directory).unwrap();
+	}
+
+	#[test]
+	fn test_request_and_heartbeats() {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let address  = listener.local_addr().unwrap();
+		let acceptor = thread::spawn(move || {
+			let mut session = Session::accept(&listener, &settings("EXCHANGE", "CLIENT", None)).unwrap();
+			session.heart_bt_int = Duration::from_millis(100);
+			session.receive()
+		});
+
+		// A client that logs on and sends a TestRequest but never answers one.
+		let mut client = TcpStream::connect(address).unwrap();
+		for fields in ["8=FIX.4.4|35=A|49=CLIENT|56=EXCHANGE|34=1|98=0|108=30", "8=FIX.4.4|35=1|49=CLIENT|56=EXCHANGE|34=2|112=ping"] {
+			client.write_all(&encoder::encode(&encoder::complete(message(fields)).unwrap(), SOH)).unwrap();
+		}
+		let mut bytes = Vec::new();
+		client.read_to_end(&mut bytes).unwrap();
+
+		let mut decoder  = Decoder::new(SOH);
+		let received: Vec<String> = decoder.feed(&bytes)
+			.filter_map(|event| if let Event::Message(message) = event { Some(describe(&message)) } else { None })
+			.collect();
+		assert_eq!(received, [
+			"8=FIX.4.4|35=A|49=EXCHANGE|56=CLIENT|34=1|98=0|108=30",
+			"8=FIX.4.4|35=0|49=EXCHANGE|56=CLIENT|34=2|112=ping",
+			"8=FIX.4.4|35=0|49=EXCHANGE|56=CLIENT|34=3",
+			"8=FIX.4.4|35=1|49=EXCHANGE|56=CLIENT|34=4|112=TEST1",
+		]);
+		assert_eq!(acceptor.join().unwrap().unwrap_err().to_string(), "No reply to TestRequest");
+	}
+
+	#[test]
+	fn resend_request_unanswered() {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let address  = listener.local_addr().unwrap();
+		let acceptor = thread::spawn(move || {
+			let mut session = Session::accept(&listener, &settings("EXCHANGE", "CLIENT", None)).unwrap();
+			session.heart_bt_int = Duration::from_millis(100);
+			(session.receive(), session.garbled())
+		});
+
+		// A client that skips MsgSeqNum 2, keeps sending Heartbeats and never answers the ResendRequest.
+		let mut client = TcpStream::connect(address).unwrap();
+		let mut reader = client.try_clone().unwrap();
+		let encoded    = |fields: &str| encoder::encode(&encoder::complete(message(fields)).unwrap(), SOH);
+		client.write_all(&encoded("8=FIX.4.4|35=A|49=CLIENT|56=EXCHANGE|34=1|98=0|108=30")).unwrap();
+		let mut garbled = encoded("8=FIX.4.4|35=0|49=CLIENT|56=EXCHANGE|34=3");
+		garbled.splice(garbled.len() - 4..garbled.len() - 1, *b"999");
+		client.write_all(&garbled).unwrap();
+		let heartbeats = thread::spawn(move || {
+			for seq_num in 3..30 {
+				thread::sleep(Duration::from_millis(40));
+				if client.write_all(&encoded(&format!("8=FIX.4.4|35=0|49=CLIENT|56=EXCHANGE|34={}", seq_num))).is_err() {
+					break;
+				}
+			}
+		});
+		let mut bytes = Vec::new();
+		reader.read_to_end(&mut bytes).ok();
+		heartbeats.join().unwrap();
+
+		// Heartbeats may come in between, so only ResendRequests and the Logout are compared (without MsgSeqNum).
+		let mut decoder  = Decoder::new(SOH);
+		let received: Vec<String> = decoder.feed(&bytes)
+			.filter_map(|event| if let Event::Message(message) = event { Some(message) } else { None })
+			.filter(|message| matches!(message.msg_type(), Some(RESEND_REQUEST | LOGOUT)))
+			.map(|message| describe(&message).split('|').filter(|field| !field.starts_with("34=")).collect::<Vec<_>>().join("|"))
+			.collect();
+		assert_eq!(received, [
+			"8=FIX.4.4|35=2|49=EXCHANGE|56=CLIENT|7=2|16=0",
+			"8=FIX.4.4|35=2|49=EXCHANGE|56=CLIENT|7=2|16=0",
+			"8=FIX.4.4|35=2|49=EXCHANGE|56=CLIENT|7=2|16=0",
+			"8=FIX.4.4|35=5|49=EXCHANGE|56=CLIENT|58=No reply to ResendRequest",
+		]);
+		let (result, garbled) = acceptor.join().unwrap();
+		assert_eq!(result.unwrap_err().to_string(), "No reply to ResendRequest");
+		assert_eq!(garbled, 1);
+	}
+}
@@ -0,0 +1,126 @@
+//! Module for keeping the sequence numbers and the sent application messages of a session so that
+//! it can be resumed after a reconnect (or a restart) and messages can be resent on a ResendRequest.
+//!
+//! A file store keeps two files in its directory, named after the session (`FIX.4.4-CLIENT-EXCHANGE`):
+//! `.seqnums` with the next MsgSeqNum to send and the next one expected (e.g. `4 1`) and `.messages`
+//! with the sent messages, one per line.
+
+use std::{collections::BTreeMap, fs::{File, OpenOptions}, io::{Error, ErrorKind, Write}, path::{Path, PathBuf}};
+
+use crate::{encoder, parser::{field::SOH, message::Message, scanner::{Scanned, Scanner}, tag::Tag}};
+
+const MSG_SEQ_NUM: Tag = Tag::new(34);
+
+#[derive(Debug)]
+pub struct Store {
+	/// `.seqnums` and `.messages` files (none when the store is in memory).
+	files:               Option<(PathBuf, File)>,
+	next_sender_seq_num: u64,
+	next_target_seq_num: u64,
+	/// Sent messages by MsgSeqNum.
+	messages:            BTreeMap<u64, Message>,
+}
+
+impl Store {
+	/// Store that starts at MsgSeqNum 1 and is lost when the session ends.
+	#[must_use]
+	pub fn memory() -> Store {
+		Store { files: None, next_sender_seq_num: 1, next_target_seq_num: 1, messages: BTreeMap::new() }
+	}
+
+	/// Opens (or creates) the files of the session in the directory.
+	pub fn open(directory: &Path, begin_string: &str, sender_comp_id: &str, target_comp_id: &str) -> Result<Store, Error> {
+		std::fs::create_dir_all(directory)?;
+		let name     = format!("{}-{}-{}", begin_string, sender_comp_id, target_comp_id);
+		let seqnums  = directory.join(format!("{}.seqnums", name));
+		let messages = directory.join(format!("{}.messages", name));
+
+		let mut store = Store::memory();
+		match std::fs::read_to_string(&seqnums) {
+			Ok(contents)                                   => {
+				let invalid = || Error::new(ErrorKind::InvalidData, format!("Invalid sequence numbers in {}: '{}'", seqnums.display(), contents.trim()));
+				let mut numbers = contents.split_whitespace().map(str::parse::<u64>);
+				store.next_sender_seq_num = numbers.next().and_then(Result::ok).ok_or_else(invalid)?;
+				store.next_target_seq_num = numbers.next().and_then(Result::ok).ok_or_else(invalid)?;
+			}
+			Err(error) if error.kind() == ErrorKind::NotFound => {}
+			Err(error)                                     => return Err(error),
+		}
+
+		let bytes = match std::fs::read(&messages) {
+			Ok(bytes)                                         => bytes,
+			Err(error) if error.kind() == ErrorKind::NotFound => Vec::new(),
+			Err(error)                                        => return Err(error),
+		};
+		for scanned in Scanner::new(&bytes, SOH) {
+			if let Scanned::Message(message) = scanned {
+				let message = message.into_owned();
+				if let Ok(seq_num) = message.get_int(MSG_SEQ_NUM) && let Ok(seq_num) = u64::try_from(seq_num) {
+					store.messages.insert(seq_num, message);
+				}
+			}
+		}
+
+		store.files = Some((seqnums, OpenOptions::new().create(true).append(true).open(messages)?));
+		store.save()?;
+		Ok(store)
+	}
+
+	#[must_use]
+	pub fn next_sender_seq_num(&self) -> u64 {
+		self.next_sender_seq_num
+	}
+
+	#[must_use]
+	pub fn next_target_seq_num(&self) -> u64 {
+		self.next_target_seq_num
+	}
+
+	pub fn set_next_sender_seq_num(&mut self, seq_num: u64) -> Result<(), Error> {
+		self.next_sender_seq_num = seq_num;
+		self.save()
+	}
+
+	pub fn set_next_target_seq_num(&mut self, seq_num: u64) -> Result<(), Error> {
+		self.next_target_seq_num = seq_num;
+		self.save()
+	}
+
+	/// Keeps a sent message (by its MsgSeqNum) for resending.
+	pub fn add(&mut self, message: &Message) -> Result<(), Error> {
+		let seq_num = message.get_int(MSG_SEQ_NUM).ok().and_then(|seq_num| u64::try_from(seq_num).ok())
+			.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "MsgSeqNum (34) is missing"))?;
+
+		if let Some((_, file)) = &mut self.files {
+			let mut bytes = encoder::encode(message, SOH);
+			bytes.push(b'\n');
+			file.write_all(&bytes)?;
+		}
+		self.messages.insert(seq_num, message.clone());
+		Ok(())
+	}
+
+	/// Sent message with the MsgSeqNum (`None` for administrative messages, which are not kept).
+	#[must_use]
+	pub fn message(&self, seq_num: u64) -> Option<&Message> {
+		self.messages.get(&seq_num)
+	}
+
+	/// Starts over at MsgSeqNum 1 in both directions and forgets the sent messages.
+	pub fn reset(&mut self) -> Result<(), Error> {
+		self.messages.clear();
+		if let Some((_, file)) = &self.files {
+			file.set_len(0)?;
+		}
+		self.next_sender_seq_num = 1;
+		self.next_target_seq_num = 1;
+		self.save()
+	}
+
+	fn save(&self) -> Result<(), Error> {
+		match &self.files {
+			Some((seqnums, _)) => std::fs::write(seqnums, format!("{} {}\n", self.next_sender_seq_num, self.next_target_seq_num)),
+			None               => Ok(()),
+		}
+	}
+}
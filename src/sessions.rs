@@ -0,0 +1,266 @@
+//! Module for checking the sequence numbers (34) of each session.
+//! Each direction of a session - the (BeginString, SenderCompID, TargetCompID) triple - is tracked separately
+//! with the next expected MsgSeqNum.
+//!
+//! Gaps, duplicates, retransmissions (PossDupFlag 43 or PossResend 97), SequenceResets (35=4),
+//! ResendRequests (35=2) and resets at Logon (ResetSeqNumFlag 141) are reported in the order they occur,
+//! followed by a summary line for each direction.
+
+use std::{collections::BTreeMap, fmt::Display, io::{Error, Write}};
+
+use crate::parser::{message::Message, tag::Tag};
+
+const BEGIN_SEQ_NO:       Tag = Tag::new(7);
+const BEGIN_STRING:       Tag = Tag::new(8);
+const END_SEQ_NO:         Tag = Tag::new(16);
+const MSG_SEQ_NUM:        Tag = Tag::new(34);
+const NEW_SEQ_NO:         Tag = Tag::new(36);
+const POSS_DUP_FLAG:      Tag = Tag::new(43);
+const SENDER_COMP_ID:     Tag = Tag::new(49);
+const TARGET_COMP_ID:     Tag = Tag::new(56);
+const POSS_RESEND:        Tag = Tag::new(97);
+const GAP_FILL_FLAG:      Tag = Tag::new(123);
+const RESET_SEQ_NUM_FLAG: Tag = Tag::new(141);
+
+const RESEND_REQUEST: &[u8] = b"2";
+const SEQUENCE_RESET: &[u8] = b"4";
+const LOGON:          &[u8] = b"A";
+
+/// One direction of a session.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Direction {
+	begin_string: Vec<u8>,
+	sender:       Vec<u8>,
+	target:       Vec<u8>,
+}
+
+impl Display for Direction {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} {} -> {}", String::from_utf8_lossy(&self.begin_string), String::from_utf8_lossy(&self.sender), String::from_utf8_lossy(&self.target))
+	}
+}
+
+#[derive(Debug, Default)]
+struct DirectionState {
+	/// Next expected MsgSeqNum.
+	expected:        Option<u64>,
+	messages:        usize,
+	first:           Option<u64>,
+	last:            Option<u64>,
+	gaps:            usize,
+	missing:         u64,
+	duplicates:      usize,
+	retransmissions: usize,
+	resets:          usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Sessions {
+	directions: BTreeMap<Direction, DirectionState>,
+	/// Reported events (position of the message in the input, direction and description).
+	events:     Vec<(usize, Direction, String)>,
+	count:      usize,
+}
+
+impl Sessions {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Checks the sequence number of the message against its direction.
+	pub fn add(&mut self, message: Message) {
+		self.count += 1;
+
+		let direction = Direction {
+			begin_string: message.value(BEGIN_STRING).unwrap_or_default().to_vec(),
+			sender:       message.value(SENDER_COMP_ID).unwrap_or_default().to_vec(),
+			target:       message.value(TARGET_COMP_ID).unwrap_or_default().to_vec(),
+		};
+		let state      = self.directions.entry(direction.clone()).or_default();
+		let mut events = Vec::new();
+		state.messages += 1;
+
+		let Some(sequence) = sequence_number(&message, MSG_SEQ_NUM) else {
+			self.events.push((self.count, direction, "MsgSeqNum missing".to_string()));
+			return;
+		};
+		state.first = state.first.or(Some(sequence));
+		state.last  = Some(sequence);
+
+		let msg_type = message.msg_type();
+		let poss_dup = message.get_bool(POSS_DUP_FLAG) == Ok(true) || message.get_bool(POSS_RESEND) == Ok(true);
+
+		if msg_type == Some(LOGON) && message.get_bool(RESET_SEQ_NUM_FLAG) == Ok(true) {
+			state.resets  += 1;
+			state.expected = None;
+			events.push(format!("Reset at Logon with MsgSeqNum {}", sequence));
+		}
+
+		let expected = *state.expected.get_or_insert(sequence);
+
+		if msg_type == Some(SEQUENCE_RESET) {
+			let new_sequence = sequence_number(&message, NEW_SEQ_NO);
+			let gap_fill     = message.get_bool(GAP_FILL_FLAG) == Ok(true);
+			match new_sequence {
+				Some(new_sequence) if gap_fill => {
+					events.push(format!("GapFill of MsgSeqNum {} to {}", sequence, new_sequence.saturating_sub(1)));
+					if sequence > expected {
+						state.gap(expected, sequence, &mut events);
+					}
+					state.expected = Some(new_sequence.max(expected));
+				}
+				Some(new_sequence) => {
+					events.push(format!("SequenceReset from {} to {}", expected, new_sequence));
+					if new_sequence < expected {
+						events.push(format!("SequenceReset lowers MsgSeqNum from {} to {}", expected, new_sequence));
+					}
+					state.resets  += 1;
+					state.expected = Some(new_sequence);
+				}
+				None => events.push("SequenceReset without NewSeqNo".to_string()),
+			}
+		}
+		else if sequence < expected {
+			if poss_dup {
+				state.retransmissions += 1;
+				events.push(format!("Retransmission of MsgSeqNum {}", sequence));
+			}
+			else {
+				state.duplicates += 1;
+				events.push(format!("Duplicate MsgSeqNum {} (expected {})", sequence, expected));
+			}
+		}
+		else {
+			if sequence > expected {
+				state.gap(expected, sequence, &mut events);
+			}
+			if poss_dup {
+				state.retransmissions += 1;
+				events.push(format!("Retransmission of MsgSeqNum {}", sequence));
+			}
+			state.expected = Some(sequence + 1);
+		}
+
+		if msg_type == Some(RESEND_REQUEST) {
+			match (sequence_number(&message, BEGIN_SEQ_NO), sequence_number(&message, END_SEQ_NO)) {
+				(Some(begin), Some(0)) | (Some(begin), None) => events.push(format!("ResendRequest for MsgSeqNum {} and later", begin)),
+				(Some(begin), Some(end))                     => events.push(format!("ResendRequest for MsgSeqNum {} to {}", begin, end)),
+				(None, _)                                    => events.push("ResendRequest without BeginSeqNo".to_string()),
+			}
+		}
+
+		let position = self.count;
+		self.events.extend(events.into_iter().map(|event| (position, direction.clone(), event)));
+	}
+
+	/// Writes the events followed by a summary line for each direction.
+	pub fn write(self, output: &mut impl Write) -> Result<(), Error> {
+		for (position, direction, event) in &self.events {
+			writeln!(output, "Message {}: {}: {}", position, direction, event)?;
+		}
+		if !self.events.is_empty() {
+			writeln!(output)?;
+		}
+
+		for (direction, state) in &self.directions {
+			write!(output, "{}: {} message{}", direction, state.messages, plural(state.messages))?;
+			if let (Some(first), Some(last)) = (state.first, state.last) {
+				write!(output, ", first MsgSeqNum {}, last MsgSeqNum {}", first, last)?;
+			}
+			writeln!(output, ", {} gap{} ({} missing), {} duplicate{}, {} retransmission{}, {} reset{}",
+				state.gaps, plural(state.gaps), state.missing,
+				state.duplicates, plural(state.duplicates),
+				state.retransmissions, plural(state.retransmissions),
+				state.resets, plural(state.resets))?;
+		}
+		Ok(())
+	}
+}
+
+impl DirectionState {
+	/// Records the sequence numbers from `expected` up to (but excluding) `sequence` as missing.
+	fn gap(&mut self, expected: u64, sequence: u64, events: &mut Vec<String>) {
+		self.gaps    += 1;
+		self.missing += sequence - expected;
+		if sequence - expected == 1 {
+			events.push(format!("Gap, MsgSeqNum {} missing", expected));
+		}
+		else {
+			events.push(format!("Gap, MsgSeqNum {} to {} missing", expected, sequence - 1));
+		}
+	}
+}
+
+fn plural(count: usize) -> &'static str {
+	if count == 1 { "" } else { "s" }
+}
+
+fn sequence_number(message: &Message, tag: Tag) -> Option<u64> {
+	message.get_int(tag).ok().and_then(|number| u64::try_from(number).ok())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::parser::scanner::{Scanned, Scanner};
+
+	#[test]
+	fn gaps_duplicates_and_retransmissions() {
+		let input = "\
+			8=FIX.4.4|35=A|49=CLIENT|56=BROKER|34=1|141=Y|10=000|\n\
+			8=FIX.4.4|35=A|49=BROKER|56=CLIENT|34=1|141=Y|10=000|\n\
+			8=FIX.4.4|35=D|49=CLIENT|56=BROKER|34=2|10=000|\n\
+			8=FIX.4.4|35=D|49=CLIENT|56=BROKER|34=5|10=000|\n\
+			8=FIX.4.4|35=2|49=BROKER|56=CLIENT|34=2|7=3|16=4|10=000|\n\
+			8=FIX.4.4|35=D|49=CLIENT|56=BROKER|34=3|43=Y|10=000|\n\
+			8=FIX.4.4|35=4|49=CLIENT|56=BROKER|34=4|43=Y|123=Y|36=5|10=000|\n\
+			8=FIX.4.4|35=0|49=CLIENT|56=BROKER|34=6|10=000|\n\
+			8=FIX.4.4|35=0|49=CLIENT|56=BROKER|34=6|10=000|\n\
+			8=FIX.4.4|35=2|49=BROKER|56=CLIENT|34=3|7=1|16=0|10=000|\n";
+
+		insta::assert_snapshot!(sessions(input), @r"
+		Message 1: FIX.4.4 CLIENT -> BROKER: Reset at Logon with MsgSeqNum 1
+		Message 2: FIX.4.4 BROKER -> CLIENT: Reset at Logon with MsgSeqNum 1
+		Message 4: FIX.4.4 CLIENT -> BROKER: Gap, MsgSeqNum 3 to 4 missing
+		Message 5: FIX.4.4 BROKER -> CLIENT: ResendRequest for MsgSeqNum 3 to 4
+		Message 6: FIX.4.4 CLIENT -> BROKER: Retransmission of MsgSeqNum 3
+		Message 7: FIX.4.4 CLIENT -> BROKER: GapFill of MsgSeqNum 4 to 4
+		Message 9: FIX.4.4 CLIENT -> BROKER: Duplicate MsgSeqNum 6 (expected 7)
+		Message 10: FIX.4.4 BROKER -> CLIENT: ResendRequest for MsgSeqNum 1 and later
+
+		FIX.4.4 BROKER -> CLIENT: 3 messages, first MsgSeqNum 1, last MsgSeqNum 3, 0 gaps (0 missing), 0 duplicates, 0 retransmissions, 1 reset
+		FIX.4.4 CLIENT -> BROKER: 7 messages, first MsgSeqNum 1, last MsgSeqNum 6, 1 gap (2 missing), 1 duplicate, 1 retransmission, 1 reset
+		");
+	}
+
+	#[test]
+	fn sequence_reset() {
+		let input = "\
+			8=FIX.4.2|35=0|49=A|56=B|34=10|10=000|\n\
+			8=FIX.4.2|35=4|49=A|56=B|34=11|36=5|10=000|\n\
+			8=FIX.4.2|35=0|49=A|56=B|34=5|10=000|\n\
+			8=FIX.4.2|35=0|49=A|56=B|10=000|\n";
+
+		insta::assert_snapshot!(sessions(input), @r"
+		Message 2: FIX.4.2 A -> B: SequenceReset from 11 to 5
+		Message 2: FIX.4.2 A -> B: SequenceReset lowers MsgSeqNum from 11 to 5
+		Message 4: FIX.4.2 A -> B: MsgSeqNum missing
+
+		FIX.4.2 A -> B: 4 messages, first MsgSeqNum 10, last MsgSeqNum 5, 0 gaps (0 missing), 0 duplicates, 0 retransmissions, 1 reset
+		");
+	}
+
+	fn sessions(input: &str) -> String {
+		let mut sessions = Sessions::new();
+		for scanned in Scanner::new(input.as_bytes(), b'|') {
+			if let Scanned::Message(message) = scanned {
+				sessions.add(message.into_owned());
+			}
+		}
+
+		let mut output = Vec::new();
+		sessions.write(&mut output).unwrap();
+		String::from_utf8(output).unwrap()
+	}
+}
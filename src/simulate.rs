@@ -0,0 +1,704 @@
+//! Module for simulating an exchange to run tests against instead of a venue (`nfix simulate`).
+//! Orders (NewOrderSingle) are matched against the resting orders of their Symbol in price-time priority
+//! and can be canceled (OrderCancelRequest) and replaced (OrderCancelReplaceRequest). Every change of an
+//! order is reported with an ExecutionReport.
+//!
+//! Besides matching each other, orders can be filled (in full or in part) by the simulated market,
+//! orders can be rejected and responses can be delayed (see `SimulateArgs`).
+
+pub mod book;
+
+use std::{collections::{HashMap, VecDeque}, io::{Error, Write}, num::NonZeroUsize, time::{Duration, Instant}};
+
+use clap::ValueEnum;
+
+use crate::{args::{Args, SimulateArgs}, chains::order_state::OrdStatus, dictionary::Dictionary, filter::Filter, formatter::{FromArgs, SimpleFormatter}, parser::{Flush, decimal::Decimal, field::Field, message::Message, tag::Tag}, session::{self, Session}, simulate::book::{Book, Side}, timestamp::Timestamp};
+
+const AVG_PX:                 Tag = Tag::new(6);
+const CL_ORD_ID:              Tag = Tag::new(11);
+const CUM_QTY:                Tag = Tag::new(14);
+const EXEC_ID:                Tag = Tag::new(17);
+const LAST_PX:                Tag = Tag::new(31);
+const LAST_QTY:               Tag = Tag::new(32);
+const MSG_SEQ_NUM:            Tag = Tag::new(34);
+const MSG_TYPE:               Tag = Tag::new(35);
+const ORDER_ID:               Tag = Tag::new(37);
+const ORDER_QTY:              Tag = Tag::new(38);
+const ORD_STATUS:             Tag = Tag::new(39);
+const ORD_TYPE:               Tag = Tag::new(40);
+const ORIG_CL_ORD_ID:         Tag = Tag::new(41);
+const PRICE:                  Tag = Tag::new(44);
+const REF_SEQ_NUM:            Tag = Tag::new(45);
+const SIDE:                   Tag = Tag::new(54);
+const SYMBOL:                 Tag = Tag::new(55);
+const TEXT:                   Tag = Tag::new(58);
+const TRANSACT_TIME:          Tag = Tag::new(60);
+const CXL_REJ_REASON:         Tag = Tag::new(102);
+const ORD_REJ_REASON:         Tag = Tag::new(103);
+const EXEC_TYPE:              Tag = Tag::new(150);
+const LEAVES_QTY:             Tag = Tag::new(151);
+const REF_MSG_TYPE:           Tag = Tag::new(372);
+const BUSINESS_REJECT_REASON: Tag = Tag::new(380);
+const CXL_REJ_RESPONSE_TO:    Tag = Tag::new(434);
+
+const NEW_ORDER_SINGLE:             &[u8] = b"D";
+const ORDER_CANCEL_REQUEST:         &[u8] = b"F";
+const ORDER_CANCEL_REPLACE_REQUEST: &[u8] = b"G";
+
+const EXECUTION_REPORT:        &str = "8";
+const ORDER_CANCEL_REJECT:     &str = "9";
+const BUSINESS_MESSAGE_REJECT: &str = "j";
+
+const MARKET: &[u8] = b"1";
+const LIMIT:  &[u8] = b"2";
+
+/// Decimals of AvgPx.
+const AVG_PX_DECIMALS: u32   = 6;
+/// Finished (filled or canceled) orders kept for duplicate ClOrdIDs and late cancel requests.
+/// When there are more, the older half is dropped.
+const FINISHED_ORDERS: usize = 10_000;
+
+/// How orders are filled besides matching each other.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Fill {
+	/// Orders are only filled by matching each other.
+	Book,
+	/// The rest of an order is filled at its limit price.
+	Full,
+	/// A share (`--partial-fill`) of the rest of an order is filled at its limit price and the rest stays on the book.
+	Partial,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ExecType {
+	New,
+	Canceled,
+	Replaced,
+	Rejected,
+	Trade,
+}
+
+impl ExecType {
+	fn value(self) -> &'static str {
+		match self {
+			ExecType::New      => "0",
+			ExecType::Canceled => "4",
+			ExecType::Replaced => "5",
+			ExecType::Rejected => "8",
+			ExecType::Trade    => "F",
+		}
+	}
+}
+
+#[derive(Debug)]
+struct Order {
+	cl_ord_id:      Vec<u8>,
+	/// ClOrdID of the order before it was canceled or replaced.
+	orig_cl_ord_id: Option<Vec<u8>>,
+	symbol:         Vec<u8>,
+	side:           Side,
+	/// Limit price (none for a market order).
+	price:          Option<Decimal>,
+	quantity:       Decimal,
+	cum_qty:        Decimal,
+	/// Sum of the quantity times the price of the fills.
+	notional:       Decimal,
+	status:         OrdStatus,
+}
+
+impl Order {
+	fn leaves_qty(&self) -> Decimal {
+		match self.status {
+			// Fills and replaces are only accepted when the rest can be calculated.
+			OrdStatus::New | OrdStatus::PartiallyFilled => self.quantity.checked_sub(self.cum_qty).unwrap_or(Decimal::ZERO),
+			OrdStatus::Filled
+			| OrdStatus::DoneForDay
+			| OrdStatus::Canceled
+			| OrdStatus::Replaced
+			| OrdStatus::PendingCancel
+			| OrdStatus::Stopped
+			| OrdStatus::Rejected
+			| OrdStatus::Suspended
+			| OrdStatus::PendingNew
+			| OrdStatus::Calculated
+			| OrdStatus::Expired
+			| OrdStatus::AcceptedForBidding
+			| OrdStatus::PendingReplace             => Decimal::ZERO,
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct Exchange {
+	fill:           Fill,
+	partial_fill:   u8,
+	reject_symbols: Vec<String>,
+	reject_every:   Option<NonZeroUsize>,
+	latency:        Duration,
+	books:          HashMap<Vec<u8>, Book>,
+	orders:         HashMap<u64, Order>,
+	/// Order by ClOrdID (the current one and the ones it had before).
+	cl_ord_ids:     HashMap<Vec<u8>, u64>,
+	/// Orders that can't be changed any more, oldest first.
+	finished:       VecDeque<u64>,
+	max_finished:   usize,
+	new_orders:     usize,
+	order_ids:      u64,
+	exec_ids:       u64,
+}
+
+impl Exchange {
+	#[must_use]
+	pub fn new(args: &SimulateArgs) -> Exchange {
+		Exchange {
+			fill:           args.fill,
+			partial_fill:   args.partial_fill,
+			reject_symbols: args.reject_symbols.clone(),
+			reject_every:   args.reject_every,
+			latency:        Duration::from_millis(args.latency),
+			books:          HashMap::new(),
+			orders:         HashMap::new(),
+			cl_ord_ids:     HashMap::new(),
+			finished:       VecDeque::new(),
+			max_finished:   FINISHED_ORDERS,
+			new_orders:     0,
+			order_ids:      0,
+			exec_ids:       0,
+		}
+	}
+
+	/// Answers the application messages of the session until it is logged out.
+	/// `observe` is called with each message received and each response sent.
+	/// Responses are held back for the latency while the session keeps heartbeating and receiving.
+	pub fn serve(&mut self, session: &mut Session, mut observe: impl FnMut(&Message) -> Result<(), Error>) -> Result<(), Error> {
+		// Responses in the order they are due.
+		let mut delayed: VecDeque<(Instant, Vec<Message>)> = VecDeque::new();
+		while !session.logged_out() {
+			let received = match delayed.front() {
+				Some((due, _)) => session.poll(due.saturating_duration_since(Instant::now()))?,
+				None           => session.receive()?,
+			};
+			if let Some(message) = received {
+				observe(&message)?;
+				delayed.push_back((Instant::now() + self.latency, self.handle(&message)));
+			}
+
+			while !session.logged_out() && delayed.front().is_some_and(|(due, _)| *due <= Instant::now()) {
+				for response in delayed.pop_front().map(|(_, responses)| responses).unwrap_or_default() {
+					observe(&session.send(response)?)?;
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Responses to the message (without the header fields filled in by the session).
+	#[must_use]
+	pub fn handle(&mut self, message: &Message) -> Vec<Message> {
+		let responses = match message.msg_type() {
+			Some(NEW_ORDER_SINGLE)             => self.new_order(message),
+			Some(ORDER_CANCEL_REQUEST)         => self.cancel(message),
+			Some(ORDER_CANCEL_REPLACE_REQUEST) => self.replace(message),
+			Some(_) | None                     => vec![business_reject(message)],
+		};
+		self.prune();
+		responses
+	}
+
+	/// Drops the older half of the finished orders (and their ClOrdIDs) when there are too many.
+	fn prune(&mut self) {
+		if self.finished.len() <= self.max_finished {
+			return;
+		}
+		let dropped: Vec<u64> = self.finished.drain(..self.finished.len() - self.max_finished / 2).collect();
+		for order_id in &dropped {
+			self.orders.remove(order_id);
+		}
+		self.cl_ord_ids.retain(|_, order_id| self.orders.contains_key(order_id));
+	}
+
+	fn new_order(&mut self, message: &Message) -> Vec<Message> {
+		self.new_orders += 1;
+		let order = match self.parse_order(message) {
+			Ok(order)           => order,
+			Err((reason, text)) => return vec![self.reject(message, reason, &text)],
+		};
+
+		self.order_ids += 1;
+		let order_id = self.order_ids;
+		self.cl_ord_ids.insert(order.cl_ord_id.clone(), order_id);
+		self.orders.insert(order_id, order);
+
+		let mut reports = vec![self.report(order_id, ExecType::New, None, None)];
+		self.execute(order_id, &mut reports);
+		reports
+	}
+
+	/// New order or the OrdRejReason (103) and text to reject it with.
+	fn parse_order(&self, message: &Message) -> Result<Order, (&'static str, String)> {
+		const UNKNOWN_SYMBOL:     &str = "1";
+		const DUPLICATE_ORDER:    &str = "6";
+		const INCORRECT_QUANTITY: &str = "13";
+		const OTHER:              &str = "99";
+
+		let cl_ord_id = message.value(CL_ORD_ID).ok_or((OTHER, "ClOrdID (11) is missing".to_string()))?;
+		if self.cl_ord_ids.contains_key(cl_ord_id) {
+			return Err((DUPLICATE_ORDER, "Duplicate ClOrdID".to_string()));
+		}
+		let symbol = message.value(SYMBOL).ok_or((UNKNOWN_SYMBOL, "Symbol (55) is missing".to_string()))?;
+		if self.reject_symbols.iter().any(|rejected| rejected.as_bytes() == symbol) {
+			return Err((UNKNOWN_SYMBOL, format!("Unknown symbol {}", String::from_utf8_lossy(symbol))));
+		}
+		let side     = message.value(SIDE).and_then(Side::parse).ok_or((OTHER, "Side (54) must be 1 (Buy) or 2 (Sell)".to_string()))?;
+		let quantity = message.get_decimal(ORDER_QTY).ok().filter(|quantity| *quantity > Decimal::ZERO)
+			.ok_or((INCORRECT_QUANTITY, "OrderQty (38) must be a positive number".to_string()))?;
+		let price    = match message.value(ORD_TYPE) {
+			Some(MARKET)   => None,
+			Some(LIMIT)    => Some(message.get_decimal(PRICE).map_err(|_| (OTHER, "Price (44) of the limit order is missing or invalid".to_string()))?),
+			Some(_) | None => return Err((OTHER, "OrdType (40) must be 1 (Market) or 2 (Limit)".to_string())),
+		};
+		if price.is_some_and(|price| quantity.checked_mul(price).is_none()) {
+			return Err((INCORRECT_QUANTITY, "OrderQty (38) times Price (44) is out of range".to_string()));
+		}
+		if self.reject_every.is_some_and(|every| self.new_orders.is_multiple_of(every.get())) {
+			return Err((OTHER, "Rejected by the simulator".to_string()));
+		}
+
+		Ok(Order {
+			cl_ord_id: cl_ord_id.to_vec(),
+			orig_cl_ord_id: None,
+			symbol: symbol.to_vec(),
+			side,
+			price,
+			quantity,
+			cum_qty: Decimal::ZERO,
+			notional: Decimal::ZERO,
+			status: OrdStatus::New,
+		})
+	}
+
+	/// Matches the order with the book, fills it by the simulated market and leaves the rest on the book
+	/// (or cancels the rest of a market order and of an order whose fills would be out of range).
+	fn execute(&mut self, order_id: u64, reports: &mut Vec<Message>) {
+		let Some(order) = self.orders.get(&order_id) else {
+			return;
+		};
+		let (symbol, side, limit) = (order.symbol.clone(), order.side, order.price);
+		let mut out_of_range      = false;
+
+		while let Some((price, resting_id)) = self.books.get(&symbol).and_then(|book| book.best(side.opposite()))
+		&& side.crosses(limit, price) {
+			let quantity = self.leaves_qty(order_id).min(self.leaves_qty(resting_id));
+			if quantity.is_zero() {
+				break;
+			}
+			let (Some(resting), Some(incoming)) = (self.filled(resting_id, quantity, price), self.filled(order_id, quantity, price)) else {
+				out_of_range = true;
+				break;
+			};
+			reports.push(self.execution(resting_id, quantity, price, resting));
+			reports.push(self.execution(order_id, quantity, price, incoming));
+			if self.leaves_qty(resting_id).is_zero() && let Some(book) = self.books.get_mut(&symbol) {
+				book.remove(side.opposite(), price, resting_id);
+			}
+		}
+
+		let leaves_qty = self.leaves_qty(order_id);
+		if let Some(price) = limit && !leaves_qty.is_zero() && !out_of_range {
+			let quantity = match self.fill {
+				Fill::Book    => Decimal::ZERO,
+				Fill::Full    => leaves_qty,
+				Fill::Partial => leaves_qty.checked_mul(Decimal::from(i64::from(self.partial_fill)))
+					.and_then(|quantity| quantity.checked_div(Decimal::from(100), leaves_qty.scale()))
+					.map_or(leaves_qty, |quantity| quantity.min(leaves_qty)),
+			};
+			if !quantity.is_zero() {
+				match self.filled(order_id, quantity, price) {
+					Some(filled) => reports.push(self.execution(order_id, quantity, price, filled)),
+					None         => out_of_range = true,
+				}
+			}
+		}
+
+		if self.leaves_qty(order_id).is_zero() {
+			return;
+		}
+		match limit {
+			Some(price) if !out_of_range => self.books.entry(symbol).or_default().add(side, price, order_id),
+			Some(_) | None               => {
+				if let Some(order) = self.orders.get_mut(&order_id) {
+					order.status = OrdStatus::Canceled;
+				}
+				self.finished.push_back(order_id);
+				let text = if out_of_range { "Fill quantity or price out of range" } else { "No liquidity" };
+				reports.push(self.report(order_id, ExecType::Canceled, None, Some(text)));
+			}
+		}
+	}
+
+	fn leaves_qty(&self, order_id: u64) -> Decimal {
+		self.orders.get(&order_id).map_or(Decimal::ZERO, Order::leaves_qty)
+	}
+
+	/// CumQty and notional of the order after filling the quantity at the price
+	/// (`None` when they or the rest of the order are out of range).
+	fn filled(&self, order_id: u64, quantity: Decimal, price: Decimal) -> Option<(Decimal, Decimal)> {
+		let order    = self.orders.get(&order_id)?;
+		let cum_qty  = order.cum_qty.checked_add(quantity)?;
+		let notional = quantity.checked_mul(price).and_then(|amount| order.notional.checked_add(amount))?;
+		order.quantity.checked_sub(cum_qty)?;
+		Some((cum_qty, notional))
+	}
+
+	/// Fills the quantity of the order at the price with the CumQty and notional from `filled`.
+	fn execution(&mut self, order_id: u64, quantity: Decimal, price: Decimal, (cum_qty, notional): (Decimal, Decimal)) -> Message {
+		if let Some(order) = self.orders.get_mut(&order_id) {
+			order.cum_qty  = cum_qty;
+			order.notional = notional;
+			order.status   = if order.cum_qty >= order.quantity { OrdStatus::Filled } else { OrdStatus::PartiallyFilled };
+			if order.status == OrdStatus::Filled {
+				self.finished.push_back(order_id);
+			}
+		}
+		self.report(order_id, ExecType::Trade, Some((quantity, price)), None)
+	}
+
+	fn cancel(&mut self, message: &Message) -> Vec<Message> {
+		let Some(order_id) = self.open_order(message) else {
+			return vec![self.cancel_reject(message, "1")];
+		};
+		if let Some(order) = self.orders.get_mut(&order_id) {
+			if let (Some(price), Some(book)) = (order.price, self.books.get_mut(&order.symbol)) {
+				book.remove(order.side, price, order_id);
+			}
+			order.status = OrdStatus::Canceled;
+		}
+		self.finished.push_back(order_id);
+		self.new_cl_ord_id(order_id, message);
+		vec![self.report(order_id, ExecType::Canceled, None, None)]
+	}
+
+	/// Changes OrderQty and Price of the order. The order keeps its place in the book unless its price changes
+	/// or its quantity goes up.
+	fn replace(&mut self, message: &Message) -> Vec<Message> {
+		let Some(order) = self.open_order(message).and_then(|order_id| Some((order_id, self.orders.get(&order_id)?))) else {
+			return vec![self.cancel_reject(message, "2")];
+		};
+		let (order_id, order) = order;
+		let quantity = message.get_decimal(ORDER_QTY).unwrap_or(order.quantity);
+		let price    = message.get_decimal(PRICE).ok().filter(|_| order.price.is_some()).or(order.price);
+		let in_range = quantity.checked_sub(order.cum_qty).is_some() && price.is_none_or(|price| quantity.checked_mul(price).is_some());
+		if quantity <= order.cum_qty || !in_range {
+			return vec![self.cancel_reject(message, "2")];
+		}
+
+		let requeue = price != order.price || quantity > order.quantity;
+		if requeue && let (Some(old_price), Some(book)) = (order.price, self.books.get_mut(&order.symbol)) {
+			book.remove(order.side, old_price, order_id);
+		}
+		if let Some(order) = self.orders.get_mut(&order_id) {
+			order.quantity = quantity;
+			order.price    = price;
+		}
+		self.new_cl_ord_id(order_id, message);
+
+		let mut reports = vec![self.report(order_id, ExecType::Replaced, None, None)];
+		if requeue {
+			self.execute(order_id, &mut reports);
+		}
+		reports
+	}
+
+	/// Order of the OrigClOrdID (41) of a cancel or replace request if it can still be changed.
+	fn open_order(&self, message: &Message) -> Option<u64> {
+		let order_id = *self.cl_ord_ids.get(message.value(ORIG_CL_ORD_ID)?)?;
+		let order    = self.orders.get(&order_id)?;
+		let unique   = message.value(CL_ORD_ID).is_some_and(|cl_ord_id| !self.cl_ord_ids.contains_key(cl_ord_id));
+		(unique && !order.leaves_qty().is_zero()).then_some(order_id)
+	}
+
+	fn new_cl_ord_id(&mut self, order_id: u64, message: &Message) {
+		let cl_ord_id = message.value(CL_ORD_ID).unwrap_or_default().to_vec();
+		if let Some(order) = self.orders.get_mut(&order_id) {
+			order.orig_cl_ord_id = Some(std::mem::replace(&mut order.cl_ord_id, cl_ord_id.clone()));
+		}
+		self.cl_ord_ids.insert(cl_ord_id, order_id);
+	}
+
+	/// ExecutionReport with the state of the order.
+	fn report(&mut self, order_id: u64, exec_type: ExecType, last: Option<(Decimal, Decimal)>, text: Option<&str>) -> Message {
+		self.exec_ids += 1;
+		let mut fields = vec![field(MSG_TYPE, EXECUTION_REPORT), field(ORDER_ID, &order_id.to_string())];
+		let Some(order) = self.orders.get(&order_id) else {
+			return Message::new(fields);
+		};
+
+		fields.push(Field::new(CL_ORD_ID, order.cl_ord_id.clone()));
+		if let Some(orig_cl_ord_id) = &order.orig_cl_ord_id {
+			fields.push(Field::new(ORIG_CL_ORD_ID, orig_cl_ord_id.clone()));
+		}
+		fields.push(field(EXEC_ID, &self.exec_ids.to_string()));
+		fields.push(field(EXEC_TYPE, exec_type.value()));
+		fields.push(field(ORD_STATUS, order.status.value()));
+		fields.push(Field::new(SYMBOL, order.symbol.clone()));
+		fields.push(field(SIDE, order.side.value()));
+		fields.push(field(ORDER_QTY, &order.quantity.to_string()));
+		if let Some(price) = order.price {
+			fields.push(field(PRICE, &price.to_string()));
+		}
+		if let Some((last_qty, last_px)) = last {
+			fields.push(field(LAST_QTY, &last_qty.to_string()));
+			fields.push(field(LAST_PX, &last_px.to_string()));
+		}
+		let avg_px = order.notional.checked_div(order.cum_qty, AVG_PX_DECIMALS).unwrap_or(Decimal::ZERO);
+		fields.push(field(LEAVES_QTY, &order.leaves_qty().to_string()));
+		fields.push(field(CUM_QTY, &order.cum_qty.to_string()));
+		fields.push(field(AVG_PX, &avg_px.to_string()));
+		fields.push(field(TRANSACT_TIME, &now()));
+		if let Some(text) = text {
+			fields.push(field(TEXT, text));
+		}
+		Message::new(fields)
+	}
+
+	/// ExecutionReport rejecting a NewOrderSingle with the OrdRejReason (103).
+	fn reject(&mut self, message: &Message, reason: &str, text: &str) -> Message {
+		self.exec_ids += 1;
+		let mut fields = vec![field(MSG_TYPE, EXECUTION_REPORT), field(ORDER_ID, "NONE")];
+		fields.extend(copy(message, &[CL_ORD_ID]));
+		fields.extend([field(EXEC_ID, &self.exec_ids.to_string()), field(EXEC_TYPE, ExecType::Rejected.value()), field(ORD_STATUS, OrdStatus::Rejected.value())]);
+		fields.extend(copy(message, &[SYMBOL, SIDE, ORDER_QTY]));
+		fields.extend([field(LEAVES_QTY, "0"), field(CUM_QTY, "0"), field(AVG_PX, "0"), field(TRANSACT_TIME, &now()), field(TEXT, text), field(ORD_REJ_REASON, reason)]);
+		Message::new(fields)
+	}
+
+	/// OrderCancelReject for a cancel (`1`) or replace (`2`) request.
+	fn cancel_reject(&self, message: &Message, response_to: &str) -> Message {
+		let order = message.value(ORIG_CL_ORD_ID).and_then(|orig_cl_ord_id| self.cl_ord_ids.get(orig_cl_ord_id))
+			.and_then(|order_id| Some((order_id, self.orders.get(order_id)?)));
+		let (order_id, status, reason, text) = match order {
+			Some((order_id, order)) => (order_id.to_string(), order.status, "0", "Too late to cancel or replace"),
+			None                    => ("NONE".to_string(), OrdStatus::Rejected, "1", "Unknown order"),
+		};
+
+		let mut fields = vec![field(MSG_TYPE, ORDER_CANCEL_REJECT), field(ORDER_ID, &order_id)];
+		fields.extend(copy(message, &[CL_ORD_ID, ORIG_CL_ORD_ID]));
+		fields.extend([field(ORD_STATUS, status.value()), field(CXL_REJ_RESPONSE_TO, response_to), field(CXL_REJ_REASON, reason), field(TEXT, text)]);
+		Message::new(fields)
+	}
+}
+
+/// BusinessMessageReject for a MsgType the simulator does not support.
+fn business_reject(message: &Message) -> Message {
+	const UNSUPPORTED_MESSAGE_TYPE: &str = "3";
+
+	let msg_type   = String::from_utf8_lossy(message.msg_type().unwrap_or_default()).into_owned();
+	let mut fields = vec![field(MSG_TYPE, BUSINESS_MESSAGE_REJECT)];
+	fields.extend(copy(message, &[MSG_SEQ_NUM]).map(|seq_num| Field::new(REF_SEQ_NUM, seq_num.value_bytes().to_vec())));
+	fields.extend([field(REF_MSG_TYPE, &msg_type), field(BUSINESS_REJECT_REASON, UNSUPPORTED_MESSAGE_TYPE), field(TEXT, &format!("Unsupported MsgType {}", msg_type))]);
+	Message::new(fields)
+}
+
+/// Fields of the message with the tags (in the order of the tags).
+fn copy<'m>(message: &'m Message, tags: &'m [Tag]) -> impl Iterator<Item = Field> + 'm {
+	tags.iter().filter_map(|tag| message.get(*tag).cloned())
+}
+
+fn now() -> String {
+	Timestamp::from_duration(std::time::SystemTime::now().duration_since(std::time::SystemTime::UNIX_EPOCH).unwrap_or_default(), 3).to_string()
+}
+
+fn field(tag: Tag, value: &str) -> Field {
+	Field::new(tag, value.as_bytes().to_vec())
+}
+
+/// Accepts sessions one after the other (keeping the order books) and prints the messages of each.
+/// A session that ends with an error is reported and the next one accepted.
+pub fn run<D: Dictionary, F: Filter>(args: &Args, simulate_args: &SimulateArgs, output: &mut impl Write, flush: Flush) -> Result<(), Error> {
+	let listener     = session::bind(&simulate_args.listen)?;
+	let formatter    = SimpleFormatter::<D, F>::new(args);
+	let mut exchange = Exchange::new(simulate_args);
+
+	loop {
+		let result = Session::accept(&listener, &simulate_args.settings)
+			.and_then(|mut session| exchange.serve(&mut session, |message| session::write_message(&formatter, output, flush, message)));
+		if let Err(error) = result {
+			writeln!(output, "\nSession ended: {}", error)?;
+			output.flush()?;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{net::TcpListener, thread};
+
+	use clap::Parser;
+
+	use super::*;
+	use crate::{args::{Command, SessionSettings}, dictionary::BaseDictionary, parser::COMMAND_NAME};
+
+	fn exchange(arguments: &[&str]) -> Exchange {
+		let args = Args::parse_from([COMMAND_NAME, "simulate", "--sender-comp-id", "EXCHANGE", "--target-comp-id", "CLIENT"].iter().chain(arguments));
+		let Some(Command::Simulate(simulate_args)) = args.command else {
+			panic!("Should be the simulate command");
+		};
+		Exchange::new(&simulate_args)
+	}
+
+	fn message(fields: &str) -> Message {
+		Message::new(fields.split('|').map(|field| crate::build::parse(field, &BaseDictionary::default()).unwrap()).collect())
+	}
+
+	/// Fields of the responses without TransactTime.
+	fn responses(exchange: &mut Exchange, fields: &str) -> Vec<String> {
+		exchange.handle(&message(fields)).iter()
+			.map(|response| response.into_iter()
+				.filter(|field| field.tag() != TRANSACT_TIME)
+				.map(|field| String::from_utf8_lossy(&field.bytes()).into_owned())
+				.collect::<Vec<String>>()
+				.join("|"))
+			.collect()
+	}
+
+	#[test]
+	fn price_time_priority() {
+		let mut exchange = exchange(&[]);
+		responses(&mut exchange, "35=D|11=B1|55=VOD.L|54=1|40=2|44=10|38=100");
+		responses(&mut exchange, "35=D|11=B2|55=VOD.L|54=1|40=2|44=10.5|38=50");
+		responses(&mut exchange, "35=D|11=B3|55=VOD.L|54=1|40=2|44=10.5|38=50");
+
+		assert_eq!(responses(&mut exchange, "35=D|11=S1|55=VOD.L|54=2|40=2|44=10|38=120"), [
+			"35=8|37=4|11=S1|17=4|150=0|39=0|55=VOD.L|54=2|38=120|44=10|151=120|14=0|6=0",
+			"35=8|37=2|11=B2|17=5|150=F|39=2|55=VOD.L|54=1|38=50|44=10.5|32=50|31=10.5|151=0|14=50|6=10.500000",
+			"35=8|37=4|11=S1|17=6|150=F|39=1|55=VOD.L|54=2|38=120|44=10|32=50|31=10.5|151=70|14=50|6=10.500000",
+			"35=8|37=3|11=B3|17=7|150=F|39=2|55=VOD.L|54=1|38=50|44=10.5|32=50|31=10.5|151=0|14=50|6=10.500000",
+			"35=8|37=4|11=S1|17=8|150=F|39=1|55=VOD.L|54=2|38=120|44=10|32=50|31=10.5|151=20|14=100|6=10.500000",
+			"35=8|37=1|11=B1|17=9|150=F|39=1|55=VOD.L|54=1|38=100|44=10|32=20|31=10|151=80|14=20|6=10.000000",
+			"35=8|37=4|11=S1|17=10|150=F|39=2|55=VOD.L|54=2|38=120|44=10|32=20|31=10|151=0|14=120|6=10.416667",
+		]);
+
+		// A market order without liquidity left is canceled.
+		assert_eq!(responses(&mut exchange, "35=D|11=S2|55=VOD.L|54=2|40=1|38=100"), [
+			"35=8|37=5|11=S2|17=11|150=0|39=0|55=VOD.L|54=2|38=100|151=100|14=0|6=0",
+			"35=8|37=1|11=B1|17=12|150=F|39=2|55=VOD.L|54=1|38=100|44=10|32=80|31=10|151=0|14=100|6=10.000000",
+			"35=8|37=5|11=S2|17=13|150=F|39=1|55=VOD.L|54=2|38=100|32=80|31=10|151=20|14=80|6=10.000000",
+			"35=8|37=5|11=S2|17=14|150=4|39=4|55=VOD.L|54=2|38=100|151=0|14=80|6=10.000000|58=No liquidity",
+		]);
+	}
+
+	#[test]
+	fn cancel_and_replace() {
+		let mut exchange = exchange(&[]);
+		responses(&mut exchange, "35=D|11=B1|55=VOD.L|54=1|40=2|44=10|38=100");
+
+		assert_eq!(responses(&mut exchange, "35=G|11=B2|41=B1|55=VOD.L|54=1|40=2|44=10.25|38=80"), [
+			"35=8|37=1|11=B2|41=B1|17=2|150=5|39=0|55=VOD.L|54=1|38=80|44=10.25|151=80|14=0|6=0",
+		]);
+		assert_eq!(responses(&mut exchange, "35=F|11=B3|41=B2|55=VOD.L|54=1"), [
+			"35=8|37=1|11=B3|41=B2|17=3|150=4|39=4|55=VOD.L|54=1|38=80|44=10.25|151=0|14=0|6=0",
+		]);
+		assert_eq!(responses(&mut exchange, "35=F|11=B4|41=B3|55=VOD.L|54=1"), [
+			"35=9|37=1|11=B4|41=B3|39=4|434=1|102=0|58=Too late to cancel or replace",
+		]);
+		assert_eq!(responses(&mut exchange, "35=G|11=X2|41=X1|55=VOD.L|54=1|38=10"), [
+			"35=9|37=NONE|11=X2|41=X1|39=8|434=2|102=1|58=Unknown order",
+		]);
+		assert_eq!(responses(&mut exchange, "35=AE|34=7"), ["35=j|45=7|372=AE|380=3|58=Unsupported MsgType AE"]);
+	}
+
+	#[test]
+	fn fills_and_rejects() {
+		let mut exchange = exchange(&["--fill", "partial", "--partial-fill", "25", "--reject-symbols", "XXX", "--reject-every", "3"]);
+		assert_eq!(responses(&mut exchange, "35=D|11=B1|55=VOD.L|54=1|40=2|44=10|38=100"), [
+			"35=8|37=1|11=B1|17=1|150=0|39=0|55=VOD.L|54=1|38=100|44=10|151=100|14=0|6=0",
+			"35=8|37=1|11=B1|17=2|150=F|39=1|55=VOD.L|54=1|38=100|44=10|32=25|31=10|151=75|14=25|6=10.000000",
+		]);
+		assert_eq!(responses(&mut exchange, "35=D|11=B2|55=XXX|54=1|40=2|44=10|38=100"), [
+			"35=8|37=NONE|11=B2|17=3|150=8|39=8|55=XXX|54=1|38=100|151=0|14=0|6=0|58=Unknown symbol XXX|103=1",
+		]);
+		assert_eq!(responses(&mut exchange, "35=D|11=B3|55=VOD.L|54=1|40=2|44=10|38=100"), [
+			"35=8|37=NONE|11=B3|17=4|150=8|39=8|55=VOD.L|54=1|38=100|151=0|14=0|6=0|58=Rejected by the simulator|103=99",
+		]);
+		assert_eq!(responses(&mut exchange, "35=D|11=B1|55=VOD.L|54=1|40=2|38=100"), [
+			"35=8|37=NONE|11=B1|17=5|150=8|39=8|55=VOD.L|54=1|38=100|151=0|14=0|6=0|58=Duplicate ClOrdID|103=6",
+		]);
+		assert_eq!(responses(&mut exchange, "35=D|11=B4|55=VOD.L|54=1|40=2|38=100"), [
+			"35=8|37=NONE|11=B4|17=6|150=8|39=8|55=VOD.L|54=1|38=100|151=0|14=0|6=0|58=Price (44) of the limit order is missing or invalid|103=99",
+		]);
+	}
+
+	#[test]
+	fn out_of_range() {
+		let mut full = exchange(&["--fill", "full"]);
+		assert_eq!(responses(&mut full, "35=D|11=B1|55=VOD.L|54=1|40=2|44=0.00000000000000000001|38=0.0000000000000000001"), [
+			"35=8|37=NONE|11=B1|17=1|150=8|39=8|55=VOD.L|54=1|38=0.0000000000000000001|151=0|14=0|6=0|58=OrderQty (38) times Price (44) is out of range|103=13",
+		]);
+
+		// Filling the sell order would make its notional out of range, so the rest of the buy order is canceled.
+		let mut book = exchange(&[]);
+		responses(&mut book, "35=D|11=S1|55=VOD.L|54=2|40=2|44=0.0000000000000000001|38=100000000000000000");
+		assert_eq!(responses(&mut book, "35=D|11=B2|55=VOD.L|54=1|40=2|44=1|38=0.0000000000000000000000000000000000001"), [
+			"35=8|37=2|11=B2|17=2|150=0|39=0|55=VOD.L|54=1|38=0.0000000000000000000000000000000000001|44=1|151=0.0000000000000000000000000000000000001|14=0|6=0",
+			"35=8|37=2|11=B2|17=3|150=4|39=4|55=VOD.L|54=1|38=0.0000000000000000000000000000000000001|44=1|151=0|14=0|6=0|58=Fill quantity or price out of range",
+		]);
+	}
+
+	#[test]
+	fn session_over_tcp() {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let address  = listener.local_addr().unwrap();
+		let settings = |sender: &str, target: &str| SessionSettings {
+			sender_comp_id: sender.to_string(),
+			target_comp_id: target.to_string(),
+			begin_string:   "FIX.4.4".to_string(),
+			heart_bt_int:   30,
+			store:          None,
+			reset_seq_num:  false,
+		};
+		let exchange_settings = settings("EXCHANGE", "CLIENT");
+		let simulator         = thread::spawn(move || {
+			let mut session  = Session::accept(&listener, &exchange_settings).unwrap();
+			let mut observed = 0;
+			exchange(&["--fill", "full", "--latency", "300"]).serve(&mut session, |_| {
+				observed += 1;
+				Ok(())
+			}).unwrap();
+			observed
+		});
+
+		// Both orders are answered after the latency, not one after the other.
+		let mut session = Session::connect(address, &settings("CLIENT", "EXCHANGE")).unwrap();
+		let sent        = Instant::now();
+		session.send(message("35=D|11=B1|55=VOD.L|54=1|40=2|44=10|38=100")).unwrap();
+		session.send(message("35=D|11=B2|55=VOD.L|54=1|40=2|44=10|38=100")).unwrap();
+		let statuses: Vec<String> = (0..4)
+			.map(|_| session.receive().unwrap().unwrap())
+			.map(|report| format!("{}/{}", report.get_str(EXEC_TYPE).unwrap(), report.get_str(ORD_STATUS).unwrap()))
+			.collect();
+		assert_eq!(statuses, ["0/0", "F/2", "0/0", "F/2"]);
+		assert!(sent.elapsed() >= Duration::from_millis(300) && sent.elapsed() < Duration::from_millis(550));
+		session.logout(None).unwrap();
+
+		assert_eq!(simulator.join().unwrap(), 6);
+	}
+
+	#[test]
+	fn finished_orders_are_dropped() {
+		let mut exchange = exchange(&["--fill", "full"]);
+		exchange.max_finished = 4;
+		for order in 1..=5 {
+			responses(&mut exchange, &format!("35=D|11=B{}|55=VOD.L|54=1|40=2|44=10|38=100", order));
+		}
+		assert_eq!((exchange.orders.len(), exchange.cl_ord_ids.len()), (2, 2));
+
+		// The older orders are unknown and their ClOrdIDs can be used again, the newer ones are still known.
+		assert_eq!(responses(&mut exchange, "35=F|11=C1|41=B1|55=VOD.L|54=1"), [
+			"35=9|37=NONE|11=C1|41=B1|39=8|434=1|102=1|58=Unknown order",
+		]);
+		assert_eq!(responses(&mut exchange, "35=F|11=C5|41=B5|55=VOD.L|54=1"), [
+			"35=9|37=5|11=C5|41=B5|39=2|434=1|102=0|58=Too late to cancel or replace",
+		]);
+		assert_eq!(responses(&mut exchange, "35=D|11=B1|55=VOD.L|54=1|40=1|38=100").len(), 2);
+	}
+}
@@ -0,0 +1,92 @@
+//! Module with a price-time priority order book of one Symbol.
+//! The book only keeps the IDs of the resting orders: the best price is matched first and orders
+//! at the same price in the order they were added.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::parser::decimal::Decimal;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+	Buy,
+	Sell,
+}
+
+impl Side {
+	/// Parses the Side (54) value (only buy and sell).
+	#[must_use]
+	pub fn parse(value: &[u8]) -> Option<Side> {
+		match value {
+			b"1" => Some(Side::Buy),
+			b"2" => Some(Side::Sell),
+			_    => None,
+		}
+	}
+
+	/// Side (54) value.
+	#[must_use]
+	pub fn value(self) -> &'static str {
+		match self {
+			Side::Buy  => "1",
+			Side::Sell => "2",
+		}
+	}
+
+	#[must_use]
+	pub fn opposite(self) -> Side {
+		match self {
+			Side::Buy  => Side::Sell,
+			Side::Sell => Side::Buy,
+		}
+	}
+
+	/// Whether an order on this side at the limit price (none for a market order) trades at the price.
+	#[must_use]
+	pub fn crosses(self, limit: Option<Decimal>, price: Decimal) -> bool {
+		match (self, limit) {
+			(_, None)                 => true,
+			(Side::Buy, Some(limit))  => limit >= price,
+			(Side::Sell, Some(limit)) => limit <= price,
+		}
+	}
+}
+
+#[derive(Debug, Default)]
+pub struct Book {
+	bids: BTreeMap<Decimal, VecDeque<u64>>,
+	asks: BTreeMap<Decimal, VecDeque<u64>>,
+}
+
+impl Book {
+	/// Adds the order behind the orders at the same price.
+	pub fn add(&mut self, side: Side, price: Decimal, order_id: u64) {
+		self.levels(side).entry(price).or_default().push_back(order_id);
+	}
+
+	pub fn remove(&mut self, side: Side, price: Decimal, order_id: u64) {
+		let levels = self.levels(side);
+		if let Some(orders) = levels.get_mut(&price) {
+			orders.retain(|id| *id != order_id);
+			if orders.is_empty() {
+				levels.remove(&price);
+			}
+		}
+	}
+
+	/// Price and ID of the first order at the best price on the side (highest bid or lowest ask).
+	#[must_use]
+	pub fn best(&self, side: Side) -> Option<(Decimal, u64)> {
+		let level = match side {
+			Side::Buy  => self.bids.last_key_value(),
+			Side::Sell => self.asks.first_key_value(),
+		};
+		level.and_then(|(price, orders)| Some((*price, *orders.front()?)))
+	}
+
+	fn levels(&mut self, side: Side) -> &mut BTreeMap<Decimal, VecDeque<u64>> {
+		match side {
+			Side::Buy  => &mut self.bids,
+			Side::Sell => &mut self.asks,
+		}
+	}
+}
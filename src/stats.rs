@@ -0,0 +1,273 @@
+//! Module for summarising the input with aggregate counts instead of printing the messages:
+//! counts per MsgType, session, Symbol and OrdStatus, filled quantity and notional per Symbol,
+//! messages per second (per minute of input), tags missing from the dictionary, parse failures
+//! and BodyLength (9) and CheckSum (10) failures.
+
+use std::{collections::{BTreeMap, HashMap}, hash::Hash, io::{Error, Write}, time::Duration};
+
+use crate::{chains::order_state::OrdStatus, dictionary::{BaseDictionary, Dictionary}, parser::{decimal::Decimal, message::Message, tag::Tag}, timestamp::{Clock, Timestamp}};
+
+const BODY_LENGTH:    Tag = Tag::new(9);
+const CHECK_SUM:      Tag = Tag::new(10);
+const LAST_PX:        Tag = Tag::new(31);
+const LAST_QTY:       Tag = Tag::new(32);
+const MSG_TYPE:       Tag = Tag::new(35);
+const ORD_STATUS:     Tag = Tag::new(39);
+const SENDER_COMP_ID: Tag = Tag::new(49);
+const SYMBOL:         Tag = Tag::new(55);
+const TARGET_COMP_ID: Tag = Tag::new(56);
+
+const EXECUTION_REPORT: &[u8] = b"8";
+
+const NANOSECONDS_PER_SECOND: i64 = 1_000_000_000;
+
+#[derive(Debug)]
+pub struct Stats {
+	clock:        Clock,
+	dictionary:   BaseDictionary,
+	messages:     usize,
+	parse:        usize,
+	body_length:  usize,
+	check_sum:    usize,
+	msg_types:    HashMap<Vec<u8>, usize>,
+	sessions:     HashMap<(Vec<u8>, Vec<u8>), usize>,
+	symbols:      HashMap<Vec<u8>, usize>,
+	ord_statuses: HashMap<Vec<u8>, usize>,
+	unknown_tags: HashMap<u32, usize>,
+	/// Fills per Symbol.
+	fills:        BTreeMap<Vec<u8>, Fills>,
+	/// Messages per second since the epoch.
+	seconds:      BTreeMap<i64, usize>,
+}
+
+impl Stats {
+	#[must_use]
+	pub fn new(clock: Clock) -> Self {
+		Self {
+			clock,
+			dictionary:   BaseDictionary::default(),
+			messages:     0,
+			parse:        0,
+			body_length:  0,
+			check_sum:    0,
+			msg_types:    HashMap::new(),
+			sessions:     HashMap::new(),
+			symbols:      HashMap::new(),
+			ord_statuses: HashMap::new(),
+			unknown_tags: HashMap::new(),
+			fills:        BTreeMap::new(),
+			seconds:      BTreeMap::new(),
+		}
+	}
+
+	/// Counts the message.
+	/// `line` is the start of the log line before the message.
+	pub fn add(&mut self, message: &Message, line: &[u8]) {
+		self.messages += 1;
+
+		for (tag, counts) in [(MSG_TYPE, &mut self.msg_types), (SYMBOL, &mut self.symbols), (ORD_STATUS, &mut self.ord_statuses)] {
+			if let Some(value) = message.value(tag) {
+				increment(counts, value.to_vec());
+			}
+		}
+		let sender = message.value(SENDER_COMP_ID).unwrap_or_default().to_vec();
+		let target = message.value(TARGET_COMP_ID).unwrap_or_default().to_vec();
+		increment(&mut self.sessions, (sender, target));
+
+		for field in message {
+			if self.dictionary.tag_name(field.tag()).is_none() {
+				increment(&mut self.unknown_tags, field.tag().number());
+			}
+		}
+
+		if message.msg_type() == Some(EXECUTION_REPORT)
+		&& let Ok(last_qty) = message.get_decimal(LAST_QTY) && last_qty > Decimal::ZERO {
+			let fills = self.fills.entry(message.value(SYMBOL).unwrap_or_default().to_vec()).or_default();
+			fills.count   += 1;
+			fills.quantity = fills.quantity.and_then(|quantity| quantity.checked_add(last_qty));
+			if let Ok(last_px) = message.get_decimal(LAST_PX) {
+				fills.notional = fills.notional.and_then(|notional| notional.checked_add(last_qty.checked_mul(last_px)?));
+			}
+		}
+
+		if message.get_int(BODY_LENGTH).is_ok_and(|body_length| usize::try_from(body_length) != Ok(message.body_length())) {
+			self.body_length += 1;
+		}
+		if message.get_int(CHECK_SUM).is_ok_and(|check_sum| check_sum != i64::from(message.check_sum())) {
+			self.check_sum += 1;
+		}
+
+		if let Some(time) = self.clock.time(message, line) {
+			*self.seconds.entry(time.div_euclid(NANOSECONDS_PER_SECOND)).or_default() += 1;
+		}
+	}
+
+	/// Adds messages that could not be parsed.
+	pub fn add_parse_failures(&mut self, count: usize) {
+		self.parse += count;
+	}
+
+	pub fn write(self, output: &mut impl Write) -> Result<(), Error> {
+		writeln!(output, "Messages: {}", self.messages)?;
+		writeln!(output, "Parse failures: {}", self.parse)?;
+		writeln!(output, "BodyLength failures: {}", self.body_length)?;
+		writeln!(output, "CheckSum failures: {}", self.check_sum)?;
+
+		write_counts(output, "MsgType", self.msg_types, |msg_type| String::from_utf8_lossy(msg_type).into_owned())?;
+		write_counts(output, "Session", self.sessions, |(sender, target)| format!("{} -> {}", String::from_utf8_lossy(sender), String::from_utf8_lossy(target)))?;
+		write_counts(output, "Symbol", self.symbols, |symbol| String::from_utf8_lossy(symbol).into_owned())?;
+		write_counts(output, "OrdStatus", self.ord_statuses, |status| match OrdStatus::parse(status) {
+			Some(ord_status) => format!("{} ({})", String::from_utf8_lossy(status), ord_status),
+			None             => String::from_utf8_lossy(status).into_owned(),
+		})?;
+
+		if !self.fills.is_empty() {
+			writeln!(output, "\nFills:")?;
+		}
+		for (symbol, fills) in &self.fills {
+			writeln!(output, "    {}: {} in {} fill{}, notional {}", String::from_utf8_lossy(symbol), amount(fills.quantity), fills.count, if fills.count == 1 { "" } else { "s" }, amount(fills.notional))?;
+		}
+
+		// Messages per second are shown per minute with the average and the busiest second.
+		let mut minutes = BTreeMap::<i64, (usize, usize)>::new();
+		for (second, count) in &self.seconds {
+			let (total, peak) = minutes.entry(second.div_euclid(60)).or_default();
+			*total += count;
+			*peak   = (*peak).max(*count);
+		}
+		if !minutes.is_empty() {
+			writeln!(output, "\nMessages per second:")?;
+		}
+		for (minute, (total, peak)) in minutes {
+			let start = Duration::from_secs(u64::try_from(minute * 60).unwrap_or_default());
+			let start = Timestamp::from_duration(start, 0).format_utc();
+			writeln!(output, "    {}: {} message{}, {:.3}/s, peak {}/s", start.get(..16).unwrap_or(&start), total, if total == 1 { "" } else { "s" }, total as f64 / 60.0, peak)?;
+		}
+
+		write_counts(output, "Unknown tags", self.unknown_tags, u32::to_string)
+	}
+}
+
+/// Quantity (sum of LastQty) and notional (sum of LastQty * LastPx) of fills.
+/// A sum is `None` once it is out of range.
+#[derive(Debug)]
+struct Fills {
+	count:    usize,
+	quantity: Option<Decimal>,
+	notional: Option<Decimal>,
+}
+
+impl Default for Fills {
+	fn default() -> Self {
+		Self { count: 0, quantity: Some(Decimal::ZERO), notional: Some(Decimal::ZERO) }
+	}
+}
+
+fn amount(sum: Option<Decimal>) -> String {
+	sum.map_or_else(|| "overflow".to_string(), |sum| sum.to_string())
+}
+
+fn increment<K: Hash + Eq>(counts: &mut HashMap<K, usize>, key: K) {
+	*counts.entry(key).or_default() += 1;
+}
+
+/// Writes the counts in a section (most frequent first).
+fn write_counts<K: Ord>(output: &mut impl Write, title: &str, counts: HashMap<K, usize>, name: impl Fn(&K) -> String) -> Result<(), Error> {
+	if counts.is_empty() {
+		return Ok(());
+	}
+
+	let mut counts: Vec<(K, usize)> = counts.into_iter().collect();
+	counts.sort_by(|(key, count), (other_key, other_count)| other_count.cmp(count).then_with(|| key.cmp(other_key)));
+
+	writeln!(output, "\n{}:", title)?;
+	for (key, count) in counts {
+		writeln!(output, "    {}: {}", name(&key), count)?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{parser::scanner::{Scanned, Scanner}, timestamp::TimeSource};
+
+	#[test]
+	fn counts() {
+		let input = "\
+			8=FIX.4.2|9=65|35=A|49=SERVER|56=CLIENT|34=177|52=20090107-18:15:16|98=0|108=30|10=062|\n\
+			8=FIX.4.2|9=64|35=A|49=SERVER|56=CLIENT|34=178|52=20090107-18:15:16|98=0|108=30|10=062|\n\
+			8=FIX.4.4|9=5|35=D|49=CLIENT|56=SERVER|52=20090107-18:16:01.500|55=VOD.L|5001=X|10=000|\n\
+			8=FIX.4.4|9=5|35=8|49=SERVER|56=CLIENT|52=20090107-18:16:02|55=VOD.L|39=1|32=40|31=10.25|10=000|\n\
+			8=FIX.4.4|9=5|35|\n";
+
+		let mut stats = Stats::new(TimeSource::SendingTime.into());
+		for scanned in Scanner::new(input.as_bytes(), b'|') {
+			match scanned {
+				Scanned::Message(message) => stats.add(&message.into_owned(), b""),
+				Scanned::Invalid(_)       => stats.add_parse_failures(1),
+				Scanned::Passthrough(_)   => {}
+			}
+		}
+
+		let mut output = Vec::new();
+		stats.write(&mut output).unwrap();
+
+		insta::assert_snapshot!(String::from_utf8(output).unwrap(), @r"
+		Messages: 4
+		Parse failures: 1
+		BodyLength failures: 3
+		CheckSum failures: 2
+
+		MsgType:
+		    A: 2
+		    8: 1
+		    D: 1
+
+		Session:
+		    SERVER -> CLIENT: 3
+		    CLIENT -> SERVER: 1
+
+		Symbol:
+		    VOD.L: 2
+
+		OrdStatus:
+		    1 (PartiallyFilled): 1
+
+		Fills:
+		    VOD.L: 40 in 1 fill, notional 410.00
+
+		Messages per second:
+		    2009-01-07 18:15: 2 messages, 0.033/s, peak 2/s
+		    2009-01-07 18:16: 2 messages, 0.033/s, peak 1/s
+
+		Unknown tags:
+		    5001: 1
+		");
+	}
+
+	#[test]
+	fn fills_out_of_range() {
+		let input = "\
+			8=FIX.4.4|35=8|55=X|32=1|31=0.0000000000000000001|10=000|\n\
+			8=FIX.4.4|35=8|55=X|32=100000000000000000|31=100000000000000000|10=000|\n\
+			8=FIX.4.4|35=8|55=Y|32=0.0000000000000000001|31=0.00000000000000000001|10=000|\n";
+
+		let mut stats = Stats::new(TimeSource::SendingTime.into());
+		for scanned in Scanner::new(input.as_bytes(), b'|') {
+			if let Scanned::Message(message) = scanned {
+				stats.add(&message.into_owned(), b"");
+			}
+		}
+
+		let mut output = Vec::new();
+		stats.write(&mut output).unwrap();
+		let output = String::from_utf8(output).unwrap();
+		let fills  = output.lines().skip_while(|line| *line != "Fills:").collect::<Vec<&str>>();
+
+		assert_eq!(fills.get(1..3), Some(&[
+			"    X: 100000000000000001 in 2 fills, notional overflow",
+			"    Y: 0.0000000000000000001 in 1 fill, notional overflow",
+		][..]));
+	}
+}
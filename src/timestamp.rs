@@ -0,0 +1,396 @@
+//! Module for timestamps in messages (SendingTime and TransactTime) and in log lines.
+//! Timestamps are parsed with up to picosecond precision and handled as nanoseconds since the Unix epoch
+//! by the analysis modes.
+
+use std::{cmp::Ordering, fmt::Display, time::Duration};
+
+use clap::ValueEnum;
+
+use crate::{parser::{message::Message, tag::Tag}, timezone::TimeZone};
+
+const SENDING_TIME:  Tag = Tag::new(52);
+const TRANSACT_TIME: Tag = Tag::new(60);
+
+/// Where the time of a message is taken from.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum TimeSource {
+	/// SendingTime (52).
+	SendingTime,
+	/// TransactTime (60).
+	TransactTime,
+	/// Timestamp at the start of the log line.
+	Log,
+}
+
+const PICOSECONDS_PER_SECOND: i128       = 1_000_000_000_000;
+/// Most fractional digits kept (picoseconds).
+const MAX_PRECISION:          u32        = 12;
+/// Numbers of fractional digits allowed (milli-, micro-, nano- and picoseconds).
+const PRECISIONS:             [usize; 4] = [3, 6, 9, 12];
+
+/// Point in time with up to picosecond precision (e.g. a FIX UTCTimestamp).
+/// The number of fractional digits it was parsed with is kept for display,
+/// so `11:14:36.5` is shown as is but equals `11:14:36.500`.
+#[derive(Debug, Clone, Copy)]
+pub struct Timestamp {
+	/// Seconds since the Unix epoch.
+	seconds:     i64,
+	picoseconds: u64,
+	/// Number of fractional digits.
+	precision:   u32,
+}
+
+impl Timestamp {
+	/// Parses a timestamp like `20200302-11:14:36.393` (FIX UTCTimestamp) or `2020/03/02-06:14:36,393`.
+	/// The fraction of a second has 3, 6, 9 or 12 digits (milli-, micro-, nano- or picoseconds).
+	#[must_use]
+	pub fn parse(bytes: &[u8]) -> Option<Timestamp> {
+		Timestamp::parse_prefix(bytes).and_then(|(timestamp, length)| (length == bytes.len()).then_some(timestamp))
+	}
+
+	/// Time since the Unix epoch with `precision` fractional digits (at most 12).
+	#[must_use]
+	pub fn from_duration(since_epoch: Duration, precision: u32) -> Timestamp {
+		let precision   = precision.min(MAX_PRECISION);
+		let nanoseconds = u64::from(since_epoch.subsec_nanos()) * 1000;
+		let unit        = 10u64.pow(MAX_PRECISION - precision);
+		Timestamp { seconds: i64::try_from(since_epoch.as_secs()).unwrap_or(i64::MAX), picoseconds: nanoseconds / unit * unit, precision }
+	}
+
+	/// Parses the timestamp at the start of the bytes and returns it with its length.
+	fn parse_prefix(bytes: &[u8]) -> Option<(Timestamp, usize)> {
+		let digits = |range: std::ops::Range<usize>| -> Option<i64> {
+			let digits = bytes.get(range)?;
+			digits.iter().try_fold(0, |number, digit| digit.is_ascii_digit().then(|| number * 10 + i64::from(digit - b'0')))
+		};
+
+		// The date is either `YYYYMMDD` or `YYYY?MM?DD` with any separator.
+		let separated = bytes.get(4).is_some_and(|byte| !byte.is_ascii_digit());
+		let (year, month, day, time) = if separated {
+			(digits(0..4)?, digits(5..7)?, digits(8..10)?, 11)
+		}
+		else {
+			(digits(0..4)?, digits(4..6)?, digits(6..8)?, 9)
+		};
+		if !matches!(bytes.get(time - 1), Some(b'-' | b' ' | b'T')) || bytes.get(time + 2) != Some(&b':') || bytes.get(time + 5) != Some(&b':') {
+			return None;
+		}
+		let (hours, minutes, seconds) = (digits(time..time + 2)?, digits(time + 3..time + 5)?, digits(time + 6..time + 8)?);
+		// Seconds can be 60 on a leap second.
+		if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hours > 23 || minutes > 59 || seconds > 60 {
+			return None;
+		}
+
+		let mut length      = time + 8;
+		let mut picoseconds = 0;
+		let mut precision   = 0;
+		if matches!(bytes.get(length), Some(b'.' | b',')) {
+			let fraction = bytes.get(length + 1..).unwrap_or_default().iter().take_while(|byte| byte.is_ascii_digit()).count();
+			if !PRECISIONS.contains(&fraction) {
+				return None;
+			}
+			precision   = fraction as u32;
+			picoseconds = digits(length + 1..length + 1 + fraction)? as u64 * 10u64.pow(MAX_PRECISION - precision);
+			length     += 1 + fraction;
+		}
+
+		// Days beyond the end of the month (e.g. 30 February) end up in the next month.
+		let days = days_from_civil(year, month, day);
+		if civil_from_days(days) != (year, month as u32, day as u32) {
+			return None;
+		}
+		Some((Timestamp { seconds: ((days * 24 + hours) * 60 + minutes) * 60 + seconds, picoseconds, precision }, length))
+	}
+
+	/// Nanoseconds since the Unix epoch.
+	#[must_use]
+	pub fn nanoseconds(&self) -> i64 {
+		self.seconds * 1_000_000_000 + (self.picoseconds / 1000) as i64
+	}
+
+	/// Number of fractional digits.
+	#[must_use]
+	pub fn precision(&self) -> u32 {
+		self.precision
+	}
+
+	/// Picoseconds since the earlier timestamp (negative if it is later).
+	#[must_use]
+	pub fn since(&self, earlier: &Timestamp) -> i128 {
+		self.picoseconds_since_epoch() - earlier.picoseconds_since_epoch()
+	}
+
+	/// Formats the timestamp in the time zone as `YYYY-MM-DD HH:MM:SS.sss +HH:MM`.
+	#[must_use]
+	pub fn format(&self, time_zone: &TimeZone) -> String {
+		let offset = time_zone.offset(self.seconds);
+		format!("{} {}{:02}:{:02}", self.format_utc_offset(offset), if offset < 0 { '-' } else { '+' }, offset.unsigned_abs() / 3600, offset.unsigned_abs() / 60 % 60)
+	}
+
+	/// Formats the timestamp in UTC as `YYYY-MM-DD HH:MM:SS.sss`.
+	#[must_use]
+	pub fn format_utc(&self) -> String {
+		self.format_utc_offset(0)
+	}
+
+	/// `YYYY-MM-DD HH:MM:SS.sss` at the UTC offset (in seconds).
+	fn format_utc_offset(&self, offset: i32) -> String {
+		let ((year, month, day), time) = self.civil(self.seconds + i64::from(offset));
+		format!("{:04}-{:02}-{:02} {}", year, month, day, time)
+	}
+
+	fn picoseconds_since_epoch(&self) -> i128 {
+		i128::from(self.seconds) * PICOSECONDS_PER_SECOND + i128::from(self.picoseconds)
+	}
+
+	/// Date and `HH:MM:SS.sss` time of the local seconds since the epoch (with the fraction of the timestamp).
+	fn civil(&self, seconds: i64) -> ((i64, u32, u32), String) {
+		let date = civil_from_days(seconds.div_euclid(86_400));
+		let time = seconds.rem_euclid(86_400);
+		let time = format!("{:02}:{:02}:{:02}{}", time / 3600, time / 60 % 60, time % 60, fraction(self.picoseconds, self.precision));
+		(date, time)
+	}
+}
+
+impl PartialEq for Timestamp {
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == Ordering::Equal
+	}
+}
+
+impl Eq for Timestamp {}
+
+impl PartialOrd for Timestamp {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Timestamp {
+	fn cmp(&self, other: &Self) -> Ordering {
+		(self.seconds, self.picoseconds).cmp(&(other.seconds, other.picoseconds))
+	}
+}
+
+/// Formats the timestamp as a FIX UTCTimestamp with its precision.
+impl Display for Timestamp {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let ((year, month, day), time) = self.civil(self.seconds);
+		write!(f, "{:04}{:02}{:02}-{}", year, month, day, time)
+	}
+}
+
+/// Fraction of a second (`.sss` with `precision` digits or nothing).
+fn fraction(picoseconds: u64, precision: u32) -> String {
+	if precision == 0 {
+		return String::new();
+	}
+	format!(".{:0width$}", picoseconds / 10u64.pow(MAX_PRECISION - precision), width = precision as usize)
+}
+
+/// Formats a duration in picoseconds as signed seconds (e.g. `+0.250s`) with `precision` fractional digits.
+#[must_use]
+pub fn format_delta(picoseconds: i128, precision: u32) -> String {
+	let precision = precision.min(MAX_PRECISION);
+	let magnitude = picoseconds.unsigned_abs();
+	let seconds   = magnitude / PICOSECONDS_PER_SECOND as u128;
+	let fraction  = fraction((magnitude % PICOSECONDS_PER_SECOND as u128) as u64, precision);
+	format!("{}{}{}s", if picoseconds < 0 { '-' } else { '+' }, seconds, fraction)
+}
+
+/// Finds the time of messages in the time source.
+#[derive(Debug, Clone)]
+pub struct Clock {
+	source:  TimeSource,
+	/// Pattern of the timestamp in log lines (any timestamp is found without it).
+	pattern: Option<Pattern>,
+}
+
+impl Clock {
+	#[must_use]
+	pub fn new(source: TimeSource, pattern: Option<Pattern>) -> Self {
+		Self { source, pattern }
+	}
+
+	/// Time of the message (nanoseconds since the epoch).
+	/// `line` is the start of the log line before the message.
+	#[must_use]
+	pub fn time(&self, message: &Message, line: &[u8]) -> Option<i64> {
+		match (self.source, &self.pattern) {
+			(TimeSource::SendingTime, _)     => message.value(SENDING_TIME).and_then(Timestamp::parse).map(|time| time.nanoseconds()),
+			(TimeSource::TransactTime, _)    => message.value(TRANSACT_TIME).and_then(Timestamp::parse).map(|time| time.nanoseconds()),
+			(TimeSource::Log, Some(pattern)) => pattern.find(line),
+			(TimeSource::Log, None)          => find(line),
+		}
+	}
+}
+
+impl From<TimeSource> for Clock {
+	fn from(source: TimeSource) -> Self {
+		Clock::new(source, None)
+	}
+}
+
+/// Pattern of timestamps in log lines like `%Y/%m/%d-%H:%M:%S,%f` with strftime-like specifiers:
+/// `%Y` (year), `%m` (month), `%d` (day), `%H` (hours), `%M` (minutes), `%S` (seconds),
+/// `%f` (fractional seconds with 3, 6, 9 or 12 digits) and `%%`. Other characters must match as they are.
+/// The date is optional (for logs with the time of day only).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+	items: Vec<Item>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Item {
+	Literal(u8),
+	/// Fixed number of digits at a position of the canonical `YYYYMMDD-HH:MM:SS` timestamp.
+	Digits { length: usize, position: usize },
+	Fraction,
+}
+
+impl Pattern {
+	pub fn parse(pattern: &str) -> Result<Pattern, String> {
+		let mut items     = Vec::new();
+		let mut specifier = false;
+		for byte in pattern.bytes() {
+			if !specifier {
+				specifier = byte == b'%';
+				if !specifier {
+					items.push(Item::Literal(byte));
+				}
+				continue;
+			}
+			specifier = false;
+			items.push(match byte {
+				b'Y' => Item::Digits { length: 4, position: 0 },
+				b'm' => Item::Digits { length: 2, position: 4 },
+				b'd' => Item::Digits { length: 2, position: 6 },
+				b'H' => Item::Digits { length: 2, position: 9 },
+				b'M' => Item::Digits { length: 2, position: 12 },
+				b'S' => Item::Digits { length: 2, position: 15 },
+				b'f' => Item::Fraction,
+				b'%' => Item::Literal(b'%'),
+				_    => return Err(format!("Unknown specifier %{} in time format '{}'", char::from(byte), pattern)),
+			});
+		}
+
+		let complete = [9, 12, 15].iter().all(|start| items.iter().any(|item| matches!(item, Item::Digits { position, .. } if position == start)));
+		if specifier || !complete {
+			return Err(format!("Time format '{}' must have %H, %M and %S", pattern));
+		}
+		Ok(Pattern { items })
+	}
+
+	/// Finds the first timestamp matching the pattern in the log line.
+	#[must_use]
+	pub fn find(&self, line: &[u8]) -> Option<i64> {
+		(0..line.len()).find_map(|start| self.matches(line.get(start..)?))
+	}
+
+	/// Timestamp at the start of the bytes.
+	fn matches(&self, bytes: &[u8]) -> Option<i64> {
+		// The date is 1 January 1970 unless it is in the pattern.
+		let mut canonical = *b"19700101-00:00:00";
+		let mut fraction  = &b""[..];
+		let mut rest      = bytes;
+		for item in &self.items {
+			match *item {
+				Item::Literal(byte)               => rest = rest.strip_prefix(&[byte])?,
+				Item::Digits { length, position } => {
+					let digits = rest.get(..length).filter(|digits| digits.iter().all(u8::is_ascii_digit))?;
+					canonical.get_mut(position..position + length)?.copy_from_slice(digits);
+					rest = rest.get(length..)?;
+				}
+				Item::Fraction                    => {
+					let length = rest.iter().take_while(|byte| byte.is_ascii_digit()).count();
+					(fraction, rest) = rest.split_at(length);
+				}
+			}
+		}
+		let timestamp = if fraction.is_empty() { Timestamp::parse(&canonical) } else { Timestamp::parse(&[&canonical[..], b".", fraction].concat()) };
+		timestamp.map(|timestamp| timestamp.nanoseconds())
+	}
+}
+
+/// Finds the first timestamp in the log line.
+#[must_use]
+pub fn find(line: &[u8]) -> Option<i64> {
+	(0..line.len())
+		.filter(|start| line.get(*start).is_some_and(u8::is_ascii_digit) && !start.checked_sub(1).and_then(|before| line.get(before)).is_some_and(u8::is_ascii_digit))
+		.find_map(|start| Timestamp::parse_prefix(line.get(start..)?))
+		.map(|(timestamp, _)| timestamp.nanoseconds())
+}
+
+/// Converts a date to days since the Unix epoch.
+/// (Algorithm from Howard Hinnant's "chrono-Compatible Low-Level Date Algorithms".)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+	let year        = if month <= 2 { year - 1 } else { year };
+	let era         = year.div_euclid(400);
+	let year_of_era = year.rem_euclid(400);
+	let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+	let day_of_era  = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+	era * 146_097 + day_of_era - 719_468
+}
+
+/// Converts days since the Unix epoch to a (year, month, day) date.
+/// (Algorithm from Howard Hinnant's "chrono-Compatible Low-Level Date Algorithms".)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+	let days        = days + 719_468;
+	let era         = days.div_euclid(146_097);
+	let day_of_era  = days.rem_euclid(146_097);
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month_index = (5 * day_of_year + 2) / 153;
+	let day         = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+	let month       = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+	let year        = year_of_era + era * 400 + i64::from(month <= 2);
+
+	(year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(bytes: &[u8]) -> Option<i64> {
+		Timestamp::parse(bytes).map(|timestamp| timestamp.nanoseconds())
+	}
+
+	#[test]
+	fn timestamps() {
+		assert_eq!(parse(b"19700101-00:00:00"),                Some(0));
+		assert_eq!(parse(b"20000229-00:00:01.500"),            Some(951_782_401_500_000_000));
+		assert_eq!(parse(b"2000-02-29 00:00:01.000000000001"), Some(951_782_401_000_000_000));
+		assert_eq!(parse(b"20001329-00:00:00"),                None);
+		assert_eq!(parse(b"20010229-00:00:00"),                None);
+		assert_eq!(parse(b"20000229"),                         None);
+		assert_eq!(parse(b"20000229-00:00:01.5"),              None);
+		assert_eq!(parse(b"20000229-00:00:01.0000000000001"),  None);
+		assert_eq!(parse(b"20000229-00:00:01 UTC"),            None);
+		assert_eq!(find(b"[12] 2020/03/02-06:14:36,393 INFO"), parse(b"20200302-06:14:36.393"));
+	}
+
+	#[test]
+	fn precise_timestamps() {
+		let timestamp = Timestamp::parse(b"20200302-11:14:36.123456789012").unwrap();
+		assert_eq!(timestamp.to_string(),  "20200302-11:14:36.123456789012");
+		assert_eq!(timestamp.nanoseconds(), 1_583_147_676_123_456_789);
+		assert_eq!(Timestamp::parse(b"20200302-11:14:36.500"), Timestamp::parse(b"20200302-11:14:36.500000"));
+		assert_eq!(Timestamp::parse(b"2020/03/02-11:14:36,500").unwrap().to_string(), "20200302-11:14:36.500");
+
+		let earlier = Timestamp::parse(b"20200302-11:14:35.999").unwrap();
+		assert_eq!(format_delta(timestamp.since(&earlier), 12), "+0.124456789012s");
+		assert_eq!(format_delta(earlier.since(&timestamp), 3),  "-0.124s");
+	}
+
+	#[test]
+	fn durations() {
+		assert_eq!(Timestamp::from_duration(Duration::ZERO, 6).format_utc(), "1970-01-01 00:00:00.000000");
+		assert_eq!(Timestamp::from_duration(Duration::from_micros(951_782_400_000_001), 6).format_utc(), "2000-02-29 00:00:00.000001");
+
+		let timestamp = Timestamp::from_duration(Duration::from_micros(951_782_401_500_900), 3);
+		assert_eq!(timestamp.to_string(), "20000229-00:00:01.500");
+		assert_eq!(Timestamp::parse(timestamp.to_string().as_bytes()), Some(timestamp));
+	}
+}
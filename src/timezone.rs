@@ -0,0 +1,113 @@
+//! Module for converting timestamps to local time.
+//! Time zones are looked up in the system's time zone database (`/usr/share/zoneinfo` or `TZDIR`)
+//! by `jiff`. Fixed offsets (`+02:00`), POSIX TZ rules (`EST5EDT,M3.2.0,M11.1.0`) and `local`
+//! (`TZ` or `/etc/localtime`) are supported as well.
+
+/// Longest fixed offset (25:59:59 like in POSIX TZ rules).
+const MAX_OFFSET: i32 = 93_599;
+
+/// Time zone with the UTC offsets of local time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeZone(jiff::tz::TimeZone);
+
+impl TimeZone {
+	/// Time zone from a name like `Europe/London`, `UTC`, `+05:30`, a POSIX TZ rule or `local`.
+	pub fn parse(name: &str) -> Result<TimeZone, String> {
+		match name {
+			"local"     => Ok(TimeZone::local()),
+			"UTC" | "Z" => Ok(TimeZone::fixed(0)),
+			_           => {
+				if let Some(offset) = name.strip_prefix(['+', '-']).and_then(parse_offset) {
+					return Ok(TimeZone::fixed(if name.starts_with('-') { -offset } else { offset }));
+				}
+				jiff::tz::TimeZone::get(name)
+					.or_else(|_| jiff::tz::TimeZone::posix(name))
+					.map(TimeZone)
+					.map_err(|_| format!("Unknown time zone '{}'", name))
+			}
+		}
+	}
+
+	/// Local time zone from `TZ` or `/etc/localtime` (UTC if neither is set).
+	#[must_use]
+	pub fn local() -> TimeZone {
+		TimeZone(jiff::tz::TimeZone::system())
+	}
+
+	/// Time zone with a fixed UTC offset in seconds (clamped to 25:59:59).
+	#[must_use]
+	pub fn fixed(offset: i32) -> TimeZone {
+		let offset = jiff::tz::Offset::from_seconds(offset.clamp(-MAX_OFFSET, MAX_OFFSET)).unwrap_or(jiff::tz::Offset::UTC);
+		TimeZone(jiff::tz::TimeZone::fixed(offset))
+	}
+
+	/// UTC offset (in seconds) of local time at the time (seconds since the epoch).
+	/// Times outside of the years -9999 to 9999 are in UTC.
+	#[must_use]
+	pub fn offset(&self, seconds: i64) -> i32 {
+		jiff::Timestamp::from_second(seconds).map_or(0, |timestamp| self.0.to_offset(timestamp).seconds())
+	}
+}
+
+/// Parses an unsigned `hh[:mm[:ss]]` or `hhmm` offset in seconds.
+fn parse_offset(offset: &str) -> Option<i32> {
+	let parts: Vec<&str> = if offset.contains(':') || offset.len() <= 2 {
+		offset.split(':').collect()
+	}
+	else {
+		vec![offset.get(..2)?, offset.get(2..)?]
+	};
+	if parts.len() > 3 || parts.iter().any(|part| part.is_empty() || part.len() > 2 || !part.bytes().all(|byte| byte.is_ascii_digit())) {
+		return None;
+	}
+	let numbers: Vec<i32> = parts.iter().map(|part| part.parse().ok()).collect::<Option<_>>()?;
+	let seconds = numbers.iter().zip([3600, 60, 1]).map(|(number, unit)| number * unit).sum();
+	(numbers.iter().skip(1).all(|number| *number < 60) && seconds <= MAX_OFFSET).then_some(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::timestamp::Timestamp;
+
+	fn format(timestamp: &str, time_zone: &TimeZone) -> String {
+		Timestamp::parse(timestamp.as_bytes()).unwrap().format(time_zone)
+	}
+
+	#[test]
+	fn fixed_offsets() {
+		assert_eq!(format("20200302-11:14:36.393", &TimeZone::parse("UTC").unwrap()),    "2020-03-02 11:14:36.393 +00:00");
+		assert_eq!(format("20200302-11:14:36.393", &TimeZone::parse("+05:30").unwrap()), "2020-03-02 16:44:36.393 +05:30");
+		assert_eq!(format("20200302-01:14:36",     &TimeZone::parse("-0500").unwrap()),  "2020-03-01 20:14:36 -05:00");
+		assert_eq!(TimeZone::parse("Nowhere/Special").unwrap_err(), "Unknown time zone 'Nowhere/Special'");
+		assert_eq!(TimeZone::parse("+26:00").unwrap_err(),          "Unknown time zone '+26:00'");
+	}
+
+	#[test]
+	fn posix_rules() {
+		let new_york = TimeZone::parse("EST5EDT,M3.2.0,M11.1.0").unwrap();
+		assert_eq!(format("20200308-06:59:59", &new_york), "2020-03-08 01:59:59 -05:00");
+		assert_eq!(format("20200308-07:00:00", &new_york), "2020-03-08 03:00:00 -04:00");
+		assert_eq!(format("20201101-05:59:59", &new_york), "2020-11-01 01:59:59 -04:00");
+		assert_eq!(format("20201101-06:00:00", &new_york), "2020-11-01 01:00:00 -05:00");
+
+		// Daylight saving time over the turn of the year.
+		let sydney = TimeZone::parse("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+		assert_eq!(format("20200105-00:00:00", &sydney), "2020-01-05 11:00:00 +11:00");
+		assert_eq!(format("20200705-00:00:00", &sydney), "2020-07-05 10:00:00 +10:00");
+
+		assert_eq!(format("20200705-00:00:00", &TimeZone::parse("<+0530>-5:30").unwrap()), "2020-07-05 05:30:00 +05:30");
+	}
+
+	#[test]
+	fn database() {
+		let Ok(berlin) = TimeZone::parse("Europe/Berlin") else {
+			// No time zone database on this system.
+			return;
+		};
+		assert_eq!(berlin.offset(-1_693_706_401), 3600);
+		assert_eq!(berlin.offset(-1_693_706_400), 7200);
+		assert_eq!(format("20200302-11:14:36", &berlin), "2020-03-02 12:14:36 +01:00");
+		assert_eq!(format("20200702-11:14:36", &berlin), "2020-07-02 13:14:36 +02:00");
+	}
+}
@@ -0,0 +1,387 @@
+//! Module for checking messages against their message definitions in the dictionary.
+//! The checks and the reasons reported follow the session level rejects (SessionRejectReason 373)
+//! of QuickFIX: required fields, unknown tags, field order, duplicates, repeating groups and
+//! values that do not fit the data type of the field.
+//! Messages of other FIX versions than the dictionary's are only checked for the header, the trailer,
+//! field order and data types, as their allowed values and message definitions differ.
+
+use std::{fmt::Display, io::{Error, Write}};
+
+use crate::{dictionary::{Dictionary, Member}, parser::{message::Message, state::CHECK_SUM, tag::Tag, value}};
+
+const BEGIN_STRING: u32 = 8;
+const BODY_LENGTH:  u32 = 9;
+const MSG_TYPE:     u32 = 35;
+
+/// Reasons with their SessionRejectReason (373) values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reason {
+	InvalidTagNumber,
+	RequiredTagMissing,
+	TagNotDefinedForMessageType,
+	ValueIsIncorrect,
+	IncorrectDataFormat,
+	InvalidMsgType,
+	TagAppearsMoreThanOnce,
+	TagSpecifiedOutOfRequiredOrder,
+	RepeatingGroupFieldsOutOfOrder,
+	IncorrectNumInGroupCount,
+}
+
+impl Reason {
+	/// SessionRejectReason (373).
+	#[must_use]
+	pub fn code(self) -> u32 {
+		match self {
+			Reason::InvalidTagNumber               => 0,
+			Reason::RequiredTagMissing             => 1,
+			Reason::TagNotDefinedForMessageType    => 2,
+			Reason::ValueIsIncorrect               => 5,
+			Reason::IncorrectDataFormat            => 6,
+			Reason::InvalidMsgType                 => 11,
+			Reason::TagAppearsMoreThanOnce         => 13,
+			Reason::TagSpecifiedOutOfRequiredOrder => 14,
+			Reason::RepeatingGroupFieldsOutOfOrder => 15,
+			Reason::IncorrectNumInGroupCount       => 16,
+		}
+	}
+}
+
+impl Display for Reason {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let text = match self {
+			Reason::InvalidTagNumber               => "Invalid tag number",
+			Reason::RequiredTagMissing             => "Required tag missing",
+			Reason::TagNotDefinedForMessageType    => "Tag not defined for this message type",
+			Reason::ValueIsIncorrect               => "Value is incorrect (out of range) for this tag",
+			Reason::IncorrectDataFormat            => "Incorrect data format for value",
+			Reason::InvalidMsgType                 => "Invalid MsgType",
+			Reason::TagAppearsMoreThanOnce         => "Tag appears more than once",
+			Reason::TagSpecifiedOutOfRequiredOrder => "Tag specified out of required order",
+			Reason::RepeatingGroupFieldsOutOfOrder => "Repeating group fields out of order",
+			Reason::IncorrectNumInGroupCount       => "Incorrect NumInGroup count for repeating group",
+		};
+		write!(f, "{} ({})", text, self.code())
+	}
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Violation {
+	pub tag:    u32,
+	pub reason: Reason,
+}
+
+/// Repeating group being checked.
+struct ActiveGroup {
+	count:     u32,
+	members:   &'static [Member],
+	expected:  usize,
+	instances: usize,
+	/// Tags seen in the current instance.
+	seen:      Vec<u32>,
+}
+
+/// Checks the message against its definition in the dictionary.
+pub fn validate(message: &Message, dictionary: &impl Dictionary) -> Vec<Violation> {
+	let mut violations = Vec::new();
+	let mut violation  = |tag, reason| violations.push(Violation { tag, reason });
+	let fields: Vec<(u32, &[u8])> = message.into_iter().map(|field| (field.tag().number(), field.value_bytes())).collect();
+
+	let covered    = dictionary.begin_string().is_none_or(|begin_string| message.value(Tag::new(BEGIN_STRING)) == Some(begin_string.as_bytes()));
+	let in_header  = |tag| dictionary.header().iter().any(|member| member.tag() == tag);
+	let in_trailer = |tag| dictionary.trailer().iter().any(|member| member.tag() == tag);
+
+	// Values.
+	for (tag, value) in &fields {
+		let Some(definition) = Tag::try_from(*tag).ok().and_then(|tag| dictionary.field(tag)) else {
+			continue;
+		};
+		if !value::valid(definition.field_type, value) {
+			violation(*tag, Reason::IncorrectDataFormat);
+		}
+		else if covered && !definition.values.is_empty() && !definition.values.iter().any(|(allowed, _)| allowed.as_bytes() == *value) {
+			violation(*tag, Reason::ValueIsIncorrect);
+		}
+	}
+
+	// Order: BeginString, BodyLength and MsgType first, then the rest of the header, the body and the trailer.
+	for (position, tag) in [BEGIN_STRING, BODY_LENGTH, MSG_TYPE].into_iter().enumerate() {
+		if fields.get(position).is_none_or(|(other, _)| *other != tag) && fields.iter().any(|(other, _)| *other == tag) {
+			violation(tag, Reason::TagSpecifiedOutOfRequiredOrder);
+		}
+	}
+	let mut body_started    = false;
+	let mut trailer_started = false;
+	for (tag, _) in &fields {
+		if in_header(*tag) {
+			if (body_started || trailer_started) && ![BEGIN_STRING, BODY_LENGTH, MSG_TYPE].contains(tag) {
+				violation(*tag, Reason::TagSpecifiedOutOfRequiredOrder);
+			}
+		}
+		else if in_trailer(*tag) {
+			trailer_started = true;
+		}
+		else if trailer_started {
+			violation(*tag, Reason::TagSpecifiedOutOfRequiredOrder);
+		}
+		else {
+			body_started = true;
+		}
+	}
+	if fields.iter().any(|(tag, _)| *tag == CHECK_SUM) && fields.last().is_some_and(|(tag, _)| *tag != CHECK_SUM) {
+		violation(CHECK_SUM, Reason::TagSpecifiedOutOfRequiredOrder);
+	}
+
+	// Header and trailer.
+	for (members, in_section) in [(dictionary.header(), &in_header as &dyn Fn(u32) -> bool), (dictionary.trailer(), &in_trailer)] {
+		let tags: Vec<u32> = fields.iter().map(|(tag, _)| *tag).filter(|tag| in_section(*tag)).collect();
+		for (index, tag) in tags.iter().enumerate() {
+			if tags.get(..index).is_some_and(|before| before.contains(tag)) {
+				violation(*tag, Reason::TagAppearsMoreThanOnce);
+			}
+		}
+		for member in members.iter().filter(|member| member.required() && !tags.contains(&member.tag())) {
+			violation(member.tag(), Reason::RequiredTagMissing);
+		}
+	}
+
+	// Body.
+	if !covered {
+		return violations;
+	}
+	let msg_type = message.msg_type();
+	let Some(definition) = msg_type.and_then(|msg_type| dictionary.message(msg_type)) else {
+		if msg_type.is_some() {
+			violation(MSG_TYPE, Reason::InvalidMsgType);
+		}
+		return violations;
+	};
+
+	let mut seen   = Vec::new();
+	let mut groups = Vec::<ActiveGroup>::new();
+	for (tag, value) in fields.iter().filter(|(tag, _)| !in_header(*tag) && !in_trailer(*tag)) {
+		loop {
+			let Some(group) = groups.last_mut() else {
+				match definition.members.iter().find(|member| member.tag() == *tag) {
+					Some(member) => {
+						if seen.contains(tag) {
+							violation(*tag, Reason::TagAppearsMoreThanOnce);
+						}
+						seen.push(*tag);
+						if let Member::Group { members, .. } = member {
+							groups.push(ActiveGroup::new(*tag, members, value));
+						}
+					}
+					None if Tag::try_from(*tag).ok().and_then(|tag| dictionary.field(tag)).is_some() => violation(*tag, Reason::TagNotDefinedForMessageType),
+					None => violation(*tag, Reason::InvalidTagNumber),
+				}
+				break;
+			};
+
+			let Some(index) = group.members.iter().position(|member| member.tag() == *tag) else {
+				// Not part of the group - the group has ended.
+				if let Some(group) = groups.pop() {
+					group.finish(&mut violation);
+				}
+				continue;
+			};
+
+			if index == 0 {
+				group.finish_instance(&mut violation);
+				group.instances += 1;
+				group.seen.clear();
+			}
+			else if group.instances == 0 || group.seen.iter().any(|seen| group.members.iter().position(|member| member.tag() == *seen) >= Some(index)) {
+				violation(*tag, Reason::RepeatingGroupFieldsOutOfOrder);
+			}
+			group.seen.push(*tag);
+
+			if let Some(Member::Group { members, .. }) = group.members.get(index) {
+				groups.push(ActiveGroup::new(*tag, members, value));
+			}
+			break;
+		}
+	}
+	while let Some(group) = groups.pop() {
+		group.finish(&mut violation);
+	}
+
+	for member in definition.members.iter().filter(|member| member.required() && !seen.contains(&member.tag())) {
+		violation(member.tag(), Reason::RequiredTagMissing);
+	}
+
+	violations
+}
+
+impl ActiveGroup {
+	fn new(count: u32, members: &'static [Member], value: &[u8]) -> Self {
+		let expected = str::from_utf8(value).ok().and_then(|value| value.parse().ok()).unwrap_or_default();
+		Self { count, members, expected, instances: 0, seen: Vec::new() }
+	}
+
+	/// Checks the required fields of the current instance.
+	fn finish_instance(&self, violation: &mut impl FnMut(u32, Reason)) {
+		if self.instances == 0 {
+			return;
+		}
+		for member in self.members.iter().filter(|member| member.required() && !self.seen.contains(&member.tag())) {
+			violation(member.tag(), Reason::RequiredTagMissing);
+		}
+	}
+
+	fn finish(self, violation: &mut impl FnMut(u32, Reason)) {
+		self.finish_instance(violation);
+		if self.instances != self.expected {
+			violation(self.count, Reason::IncorrectNumInGroupCount);
+		}
+	}
+}
+
+/// Invalid message with its position in the input, MsgType and violations.
+type Invalid = (usize, Option<Vec<u8>>, Vec<Violation>);
+
+#[derive(Debug, Default)]
+pub struct Validator<D: Dictionary> {
+	dictionary: D,
+	count:      usize,
+	invalid:    Vec<Invalid>,
+}
+
+impl<D: Dictionary> Validator<D> {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn add(&mut self, message: &Message) {
+		self.count += 1;
+
+		let violations = validate(message, &self.dictionary);
+		if !violations.is_empty() {
+			let msg_type = message.msg_type().map(<[u8]>::to_vec);
+			self.invalid.push((self.count, msg_type, violations));
+		}
+	}
+
+	/// Writes the violations of each invalid message followed by a summary line.
+	pub fn write(self, output: &mut impl Write) -> Result<(), Error> {
+		for (position, msg_type, violations) in &self.invalid {
+			write!(output, "Message {}", position)?;
+			if let Some(msg_type) = msg_type {
+				write!(output, " (MsgType {}", String::from_utf8_lossy(msg_type))?;
+				if let Some(definition) = self.dictionary.message(msg_type) {
+					write!(output, " {}", definition.name)?;
+				}
+				write!(output, ")")?;
+			}
+			writeln!(output)?;
+
+			for Violation { tag, reason } in violations {
+				match Tag::try_from(*tag).ok().and_then(|tag| self.dictionary.tag_name(tag)) {
+					Some(name) => writeln!(output, "    Tag {} ({}): {}", tag, name, reason)?,
+					None       => writeln!(output, "    Tag {}: {}", tag, reason)?,
+				}
+			}
+		}
+
+		writeln!(output, "{} message{}, {} invalid", self.count, if self.count == 1 { "" } else { "s" }, self.invalid.len())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{dictionary::BaseDictionary, parser::scanner::{Scanned, Scanner}};
+
+	#[test]
+	fn valid_messages() {
+		let input = "\
+			8=FIX.4.4|9=5|35=A|49=CLIENT|56=BROKER|34=1|52=20200302-11:14:36.393|98=0|108=30|141=Y|10=000|\
+			8=FIX.4.4|9=5|35=D|49=CLIENT|56=BROKER|34=2|52=20200302-11:14:36|11=A|453=2|448=P1|447=D|452=1|448=P2|55=VOD.L|54=1|60=20200302-11:14:36.123456789012|40=2|44=1.5|64=20200304|10=000|";
+
+		assert_eq!(reasons(input), Vec::<Vec<(u32, Reason)>>::from([vec![], vec![]]));
+	}
+
+	#[test]
+	fn violations() {
+		let input = "\
+			8=FIX.4.4|9=5|35=D|49=CLIENT|56=BROKER|52=2020-03-02|11=A|11=B|54=X|40=2|44=1,5|5001=Y|1=ACC|108=30|34=3|10=000|\
+			8=FIX.4.4|35=8|9=5|49=B|56=C|34=4|52=20200302-11:14:36.39|37=O|17=E|150=0|39=0|453=2|447=D|448=P1|55=VOD.L|54=1|151=1|14=0|6=0|10=000|\
+			8=FIX.4.4|9=5|35=Z|49=B|56=C|34=5|52=20200302-11:14:36|10=000|";
+
+		assert_eq!(reasons(input), vec![
+			vec![
+				(52,  Reason::IncorrectDataFormat),
+				(54,  Reason::ValueIsIncorrect),
+				(44,  Reason::IncorrectDataFormat),
+				(34,  Reason::TagSpecifiedOutOfRequiredOrder),
+				(11,  Reason::TagAppearsMoreThanOnce),
+				(5001, Reason::InvalidTagNumber),
+				(108, Reason::TagNotDefinedForMessageType),
+				(55,  Reason::RequiredTagMissing),
+				(60,  Reason::RequiredTagMissing),
+			],
+			vec![
+				(52,  Reason::IncorrectDataFormat),
+				(9,   Reason::TagSpecifiedOutOfRequiredOrder),
+				(35,  Reason::TagSpecifiedOutOfRequiredOrder),
+				(447, Reason::RepeatingGroupFieldsOutOfOrder),
+				(453, Reason::IncorrectNumInGroupCount),
+			],
+			vec![
+				(35,  Reason::InvalidMsgType),
+			],
+		]);
+	}
+
+	#[test]
+	fn other_version() {
+		let input = "\
+			8=FIX.4.2|9=5|35=8|49=B|56=C|34=1|52=20200302-11:14:36|37=O|17=E|20=0|150=2|39=2|55=VOD.L|54=1|151=0|14=100|6=10|44=X|10=000|\
+			8=FIX.4.2|9=5|35=8|49=B|56=C|52=20200302-11:14:36|10=000|";
+
+		assert_eq!(reasons(input), vec![
+			vec![
+				(44,  Reason::IncorrectDataFormat),
+			],
+			vec![
+				(34,  Reason::RequiredTagMissing),
+			],
+		]);
+	}
+
+	#[test]
+	fn report() {
+		let input = "8=FIX.4.4|9=5|35=0|49=B|56=C|34=1|52=20200302-11:14:36|10=000|8=FIX.4.4|9=5|35=1|49=B|56=C|34=2|52=20200302-25:14:36|10=000|";
+
+		let mut validator = Validator::<BaseDictionary>::new();
+		messages(input).iter().for_each(|message| validator.add(message));
+
+		let mut output = Vec::new();
+		validator.write(&mut output).unwrap();
+
+		insta::assert_snapshot!(String::from_utf8(output).unwrap(), @r"
+		Message 2 (MsgType 1 TestRequest)
+		    Tag 52 (SendingTime): Incorrect data format for value (6)
+		    Tag 112 (TestReqID): Required tag missing (1)
+		2 messages, 1 invalid
+		");
+	}
+
+	fn reasons(input: &str) -> Vec<Vec<(u32, Reason)>> {
+		let dictionary = BaseDictionary::default();
+		messages(input).iter()
+			.map(|message| validate(message, &dictionary).into_iter().map(|violation| (violation.tag, violation.reason)).collect())
+			.collect()
+	}
+
+	fn messages(input: &str) -> Vec<Message> {
+		Scanner::new(input.as_bytes(), b'|')
+			.filter_map(|scanned| match scanned {
+				Scanned::Message(message) => Some(message.into_owned()),
+				Scanned::Passthrough(_)
+				| Scanned::Invalid(_)     => None,
+			})
+			.collect()
+	}
+}
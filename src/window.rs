@@ -0,0 +1,221 @@
+//! Module for keeping only the messages in a time window (`--from` and `--to`).
+//! The time of a message is taken from the time source (SendingTime, TransactTime or the log line).
+//!
+//! Files are assumed to be sorted by time: the start and end of the window are found with a binary
+//! search over the lines of the memory-mapped file so only the lines in between are scanned.
+
+use std::{cell::Cell, ops::Range};
+
+use crate::{args::Args, input::passthrough, parser::{message::Message, scanner::{Scanned, Scanner}}, timestamp::{Clock, Timestamp}};
+
+const NANOSECONDS_PER_DAY: i64 = 86_400_000_000_000;
+
+/// Start or end of the window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bound {
+	/// Nanoseconds since the epoch.
+	Time(i64),
+	/// Nanoseconds since midnight on the date of the other bound (or of the first message).
+	TimeOfDay(i64),
+}
+
+impl Bound {
+	/// Parses `2026-10-16T13:00`, `20261016-13:00:00.5` (with any date and time separator) or `13:05`.
+	pub fn parse(value: &str) -> Result<Bound, String> {
+		let invalid = || format!("Invalid time '{}': expected YYYY-MM-DDTHH:MM[:SS[.sss]] or HH:MM[:SS[.sss]]", value);
+
+		// Seconds are optional.
+		let colon = value.find(':').ok_or_else(invalid)?;
+		let time  = value.get(colon.saturating_sub(2)..).ok_or_else(invalid)?;
+		let time  = if time.len() == 5 { format!("{}:00", time) } else { time.to_string() };
+
+		match value.get(..colon.saturating_sub(3)) {
+			Some("") | None => {
+				let time = Timestamp::parse(format!("19700101-{}", time).as_bytes()).ok_or_else(invalid)?.nanoseconds();
+				Ok(Bound::TimeOfDay(time))
+			}
+			Some(date)      => {
+				let time = Timestamp::parse(format!("{}-{}", date, time).as_bytes()).ok_or_else(invalid)?.nanoseconds();
+				Ok(Bound::Time(time))
+			}
+		}
+	}
+
+	/// Time on the date (nanoseconds since the epoch of any time on the date).
+	fn on(self, date: i64) -> i64 {
+		match self {
+			Bound::Time(time)      => time,
+			Bound::TimeOfDay(time) => date.div_euclid(NANOSECONDS_PER_DAY) * NANOSECONDS_PER_DAY + time,
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct Window {
+	from:   Option<Bound>,
+	to:     Option<Bound>,
+	clock:  Clock,
+	/// Start and end (exclusive) once the dates of the bounds are known.
+	bounds: Cell<Option<(i64, i64)>>,
+}
+
+impl Window {
+	/// Window of `--from` and `--to` (`None` without them).
+	#[must_use]
+	pub fn new(args: &Args) -> Option<Window> {
+		if args.from.is_none() && args.to.is_none() {
+			return None;
+		}
+		let window = Window { from: args.from, to: args.to, clock: Clock::new(args.time_source, args.time_format.clone()), bounds: Cell::new(None) };
+
+		// A time of day takes the date of the other bound.
+		match (args.from, args.to) {
+			(Some(Bound::Time(time)), _) | (_, Some(Bound::Time(time))) => window.resolve(time),
+			(_, _)                                                      => {}
+		}
+		Some(window)
+	}
+
+	/// Whether the time of the message is in the window (messages without a time are not).
+	/// `line` is the start of the log line before the message.
+	#[must_use]
+	pub fn contains(&self, message: &Message, line: &[u8]) -> bool {
+		let Some(time) = self.clock.time(message, line) else {
+			return false;
+		};
+		let (from, to) = self.bounds(time);
+		from <= time && time < to
+	}
+
+	/// Range of the (sorted) input holding the lines of the messages in the window.
+	#[must_use]
+	pub fn range(&self, input: &[u8], field_delimiter: u8) -> Range<usize> {
+		let Some(first) = self.next_time(input, 0, field_delimiter) else {
+			return 0..input.len();
+		};
+		let (from, to) = self.bounds(first);
+
+		let start = if self.from.is_some() { self.seek(input, from, field_delimiter) } else { 0 };
+		let end   = if self.to.is_some() { self.seek(input, to, field_delimiter) } else { input.len() };
+		start..end.max(start)
+	}
+
+	/// Start and end of the window with times of day on the date of `time` unless already known.
+	fn bounds(&self, time: i64) -> (i64, i64) {
+		if self.bounds.get().is_none() {
+			self.resolve(time);
+		}
+		self.bounds.get().unwrap_or((i64::MIN, i64::MAX))
+	}
+
+	fn resolve(&self, date: i64) {
+		let from = self.from.map_or(i64::MIN, |from| from.on(date));
+		let mut to = self.to.map_or(i64::MAX, |to| to.on(date));
+		// A window like 23:55 to 00:05 ends the next day.
+		if matches!(self.to, Some(Bound::TimeOfDay(_))) && to < from {
+			to += NANOSECONDS_PER_DAY;
+		}
+		self.bounds.set(Some((from, to)));
+	}
+
+	/// Start of the first line with a message at or after the time.
+	fn seek(&self, input: &[u8], time: i64, field_delimiter: u8) -> usize {
+		let (mut low, mut high) = (0, input.len());
+		while low < high {
+			let middle = low + (high - low) / 2;
+			match self.next_time(input, middle, field_delimiter) {
+				Some(found) if found < time => low = middle + 1,
+				Some(_) | None              => high = middle,
+			}
+		}
+		line_start(input, low)
+	}
+
+	/// Time of the first message with a time in the lines starting at or after `position`.
+	fn next_time(&self, input: &[u8], position: usize, field_delimiter: u8) -> Option<i64> {
+		let start    = line_start(input, position);
+		let mut line = Vec::new();
+		for scanned in Scanner::new(input.get(start..)?, field_delimiter) {
+			match scanned {
+				Scanned::Message(message)   => {
+					if let Some(time) = self.clock.time(&message.into_owned(), &line) {
+						return Some(time);
+					}
+					line.clear();
+				}
+				Scanned::Passthrough(bytes)
+				| Scanned::Invalid(bytes)   => passthrough(&mut line, bytes),
+			}
+		}
+		None
+	}
+}
+
+/// Start of the first line at or after `position` (or end of input).
+fn line_start(input: &[u8], position: usize) -> usize {
+	match position.checked_sub(1) {
+		None           => 0,
+		Some(previous) => input.get(previous..)
+			.and_then(|remaining| memchr::memchr(b'\n', remaining))
+			.map_or(input.len(), |offset| previous + offset + 1),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::parser::COMMAND_NAME;
+	use clap::Parser;
+
+	const INPUT: &str = "\
+		start of log\n\
+		10:00:01 8=FIX.4.4|9=5|35=0|52=20261016-12:59:59|10=000|\n\
+		10:00:02 8=FIX.4.4|9=5|35=0|52=20261016-13:00:00|10=000|\n\
+		no message\n\
+		10:00:03 8=FIX.4.4|9=5|35=0|52=20261016-13:02:30|10=000|\n\
+		10:00:04 8=FIX.4.4|9=5|35=0|52=20261016-13:05:00|10=000|\n\
+		10:00:05 8=FIX.4.4|9=5|35=0|52=20261016-13:06:00|10=000|\n";
+
+	fn window(arguments: &[&str]) -> Window {
+		Window::new(&Args::parse_from([COMMAND_NAME].iter().chain(arguments))).unwrap()
+	}
+
+	fn nanoseconds(timestamp: &str) -> i64 {
+		Timestamp::parse(timestamp.as_bytes()).unwrap().nanoseconds()
+	}
+
+	fn lines(window: &Window) -> Vec<&'static str> {
+		let range = window.range(INPUT.as_bytes(), b'|');
+		INPUT.get(range).unwrap().lines().collect()
+	}
+
+	#[test]
+	fn bounds() {
+		assert_eq!(Bound::parse("2026-10-16T13:00"),      Ok(Bound::Time(nanoseconds("20261016-13:00:00"))));
+		assert_eq!(Bound::parse("20261016-13:00:00.500"), Ok(Bound::Time(nanoseconds("20261016-13:00:00.500"))));
+		assert_eq!(Bound::parse("13:05"),                 Ok(Bound::TimeOfDay(47_100_000_000_000)));
+		assert!(Bound::parse("13h05").is_err());
+		assert!(Bound::parse("2026-10-16T25:00").is_err());
+	}
+
+	#[test]
+	fn binary_search() {
+		assert_eq!(lines(&window(&["--from", "2026-10-16T13:00", "--to", "13:05"])), vec![
+			"10:00:02 8=FIX.4.4|9=5|35=0|52=20261016-13:00:00|10=000|",
+			"no message",
+			"10:00:03 8=FIX.4.4|9=5|35=0|52=20261016-13:02:30|10=000|",
+		]);
+		assert_eq!(lines(&window(&["--from", "13:05:00"])).len(), 2);
+		assert_eq!(lines(&window(&["--to", "13:00"])).len(),      2);
+		assert_eq!(lines(&window(&["--from", "14:00"])),          Vec::<&str>::new());
+	}
+
+	#[test]
+	fn log_time_with_pattern() {
+		let window = window(&["--time-source", "log", "--time-format", "%H:%M:%S ", "--from", "10:00:03"]);
+		assert_eq!(lines(&window).len(), 4);
+		assert!(window.contains(&Message::new(vec![]), b"10:00:04 "));
+		assert!(!window.contains(&Message::new(vec![]), b"10:00:02 "));
+		assert!(!window.contains(&Message::new(vec![]), b"no time"));
+	}
+}